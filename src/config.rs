@@ -0,0 +1,669 @@
+use crate::pb::uniswap::Pool;
+use crate::storage::uniswap_v3_pool::PoolLayout;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use substreams::scalar::BigDecimal;
+use substreams::{hex, Hex};
+
+// Addresses of a Uniswap v3 deployment. The modules which need them take a `params` input of
+// the form `network=<name>`, optionally followed by overrides of single fields, e.g.
+// `network=mainnet&whitelist_tokens=c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2,6b175474e89094c44da98b954eedeac495271d0f`.
+// An empty params string selects mainnet.
+//
+// Token and pool addresses are lowercase hex without the `0x` prefix, like everywhere in the stores.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub network: String,
+    // factories whose pools are indexed, the `Factory` entity of the first one holds the totals. Forks are
+    // added with `factories=<protocol>:<address>,...`, e.g. `factories=uniswap-v3:1f98...,pancakeswap-v3:0bfb...`
+    pub factories: Vec<PoolFactory>,
+    pub position_manager: [u8; 20],
+    // wrapped native token, the "ETH" of the derived prices
+    pub weth: String,
+    // stable coin/native token pools giving the USD price of the native token, i.e. `Bundle.ethPriceUSD`,
+    // which is the median of their prices weighted by the stable coins they hold
+    pub eth_price_pools: Vec<String>,
+    pub stable_coins: HashSet<String>,
+    pub whitelist_tokens: HashSet<String>,
+    // pools which are not indexed, either listed or holding a listed token
+    pub denylist_pools: HashSet<String>,
+    pub denylist_tokens: HashSet<String>,
+    // ETH locked on the whitelisted side of a pool for it to price the other token, unless that token
+    // is whitelisted as well
+    pub min_eth_locked: BigDecimal,
+    // longest path of pools from a token to WETH or a stable coin when deriving its ETH price
+    pub max_price_hops: u32,
+    // price stable coins from their own pools instead of pegging them to 1 USD, see `Token.pegDeviation`
+    pub price_stable_coins: bool,
+    // relative deviation of a stable coin from its peg past which a `DepegEvent` is emitted
+    pub depeg_threshold: BigDecimal,
+    // ranges around the current price, in basis points, of the token amounts of map_pool_liquidity_depth
+    pub liquidity_depth_bps: Vec<u32>,
+}
+
+// configs parsed by `Config::get`, per params string
+static CONFIGS: Mutex<Vec<(String, &'static Config)>> = Mutex::new(Vec::new());
+
+impl Config {
+    // The config of the params, only parsed on the first call for them. Every module gets its params on
+    // each block, the wasm instance keeps the configs across blocks.
+    pub fn get(params: &str) -> &'static Config {
+        let cached = CONFIGS
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(cached_params, _)| cached_params == params)
+            .map(|(_, config)| *config);
+        if let Some(config) = cached {
+            return config;
+        }
+
+        // parsed outside of the lock, as invalid params panic
+        let config: &'static Config = Box::leak(Box::new(Config::parse(params)));
+        CONFIGS.lock().unwrap().push((params.to_string(), config));
+        config
+    }
+
+    pub fn parse(params: &str) -> Config {
+        let mut overrides: Vec<(&str, &str)> = vec![];
+        let mut network = "mainnet";
+        for param in params.split("&").map(str::trim).filter(|param| !param.is_empty()) {
+            let (key, value) = param
+                .split_once("=")
+                .expect(&format!("invalid param {param}, expected key=value"));
+            match key {
+                "network" => network = value,
+                _ => overrides.push((key, value)),
+            }
+        }
+
+        let mut config = match network {
+            "mainnet" => mainnet(),
+            "polygon" => polygon(),
+            "arbitrum" => arbitrum(),
+            "optimism" => optimism(),
+            "base" => base(),
+            _ => panic!("unknown network {network}"),
+        };
+
+        for (key, value) in overrides {
+            match key {
+                "factory" => config.factories[0].address = parse_address(value),
+                "factories" => config.factories = parse_factories(value),
+                "position_manager" => config.position_manager = parse_address(value),
+                "weth" => config.weth = normalize_address(value),
+                "eth_price_pools" => config.eth_price_pools = parse_address_list(value),
+                "stable_coins" => config.stable_coins = parse_addresses(value),
+                "whitelist_tokens" => config.whitelist_tokens = parse_addresses(value),
+                "denylist_pools" => config.denylist_pools = parse_addresses(value),
+                "denylist_tokens" => config.denylist_tokens = parse_addresses(value),
+                "min_eth_locked" => {
+                    config.min_eth_locked = BigDecimal::try_from(value).expect("invalid min_eth_locked")
+                }
+                "max_price_hops" => config.max_price_hops = value.parse().expect("invalid max_price_hops"),
+                "price_stable_coins" => config.price_stable_coins = value.parse().expect("invalid price_stable_coins"),
+                "depeg_threshold" => {
+                    config.depeg_threshold = BigDecimal::try_from(value).expect("invalid depeg_threshold")
+                }
+                "liquidity_depth_bps" => {
+                    config.liquidity_depth_bps = value
+                        .split(",")
+                        .filter(|bps| !bps.is_empty())
+                        .map(|bps| bps.parse().expect("invalid liquidity_depth_bps"))
+                        .collect()
+                }
+                _ => panic!("unknown param {key}"),
+            }
+        }
+
+        config
+    }
+
+    // id of the `Factory` entity, the checksummed factory address like in the subgraph
+    pub fn factory_id(&self) -> String {
+        crate::utils::checksum_address(&self.factories[0].address)
+    }
+
+    pub fn is_denylisted(&self, pool_address: &str, token0_address: &str, token1_address: &str) -> bool {
+        self.denylist_pools.contains(pool_address)
+            || self.denylist_tokens.contains(token0_address)
+            || self.denylist_tokens.contains(token1_address)
+    }
+
+    pub fn is_denylisted_pool(&self, pool: &Pool) -> bool {
+        self.is_denylisted(&pool.address, &pool.token0_ref().address, &pool.token1_ref().address)
+    }
+
+    pub fn is_denylisted_token(&self, token_address: &str) -> bool {
+        self.denylist_tokens.contains(token_address)
+    }
+
+    pub fn factory_address(&self) -> String {
+        Hex(&self.factories[0].address).to_string()
+    }
+
+    pub fn factory_addresses(&self) -> Vec<&[u8]> {
+        self.factories
+            .iter()
+            .map(|factory| factory.address.as_slice())
+            .collect()
+    }
+
+    pub fn pool_factory(&self, factory_address: &[u8]) -> Option<&PoolFactory> {
+        self.factories.iter().find(|factory| factory.address == factory_address)
+    }
+
+    pub fn is_whitelisted(&self, token_address: &str) -> bool {
+        self.whitelist_tokens.contains(token_address)
+    }
+
+    pub fn is_stable_coin(&self, token_address: &str) -> bool {
+        self.stable_coins.contains(token_address)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PoolFactory {
+    pub address: [u8; 20],
+    // id of the protocol deploying the pools, e.g. `uniswap-v3` or `pancakeswap-v3`, see `Pool.protocol`
+    pub protocol: String,
+}
+
+impl PoolFactory {
+    fn uniswap_v3(address: [u8; 20]) -> PoolFactory {
+        PoolFactory {
+            address,
+            protocol: "uniswap-v3".to_string(),
+        }
+    }
+
+    pub fn pool_layout(&self) -> PoolLayout {
+        PoolLayout::for_protocol(&self.protocol)
+    }
+}
+
+fn mainnet() -> Config {
+    Config {
+        network: "mainnet".to_string(),
+        factories: vec![PoolFactory::uniswap_v3(hex!(
+            "1f98431c8ad98523631ae4a59f267346ea31f984"
+        ))],
+        position_manager: hex!("c36442b4a4522e871399cd717abdd847ab11fe88"),
+        weth: "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2".to_string(),
+        eth_price_pools: vec![
+            "88e6a0c2ddd26feeb64f039a2c41296fcb3f5640".to_string(), // USDC 0.05%
+            "8ad599c3a0ff1de082011efddc58f1908eb6e6d8".to_string(), // USDC 0.3%
+            "4e68ccd3e89f51c3074ca5072bbac773960dfa36".to_string(), // USDT 0.3%
+            "c2e9f25be6257c210d7adf0d4cd6e3e881ba25f8".to_string(), // DAI 0.3%
+        ],
+        stable_coins: to_strings(&[
+            "6b175474e89094c44da98b954eedeac495271d0f", // DAI
+            "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", // USDC
+            "dac17f958d2ee523a2206206994597c13d831ec7", // USDT
+            "0000000000085d4780b73119b644ae5ecd22b376", // TUSD
+            "956f47f50a910163d8bf957cf5846d573e7f87ca", // FEI
+            "4dd28568d05f09b02220b09c2cb307bfd837cb95", // PRINTS
+        ]),
+        whitelist_tokens: to_strings(&[
+            "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2", // WETH
+            "6b175474e89094c44da98b954eedeac495271d0f", // DAI
+            "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", // USDC
+            "dac17f958d2ee523a2206206994597c13d831ec7", // USDT
+            "0000000000085d4780b73119b644ae5ecd22b376", // TUSD
+            "2260fac5e5542a773aa44fbcfedf7c193bc2c599", // WBTC
+            "5d3a536e4d6dbd6114cc1ead35777bab948e3643", // cDAI
+            "39aa39c021dfbae8fac545936693ac917d5e7563", // cUSDC
+            "86fadb80d8d2cff3c3680819e4da99c10232ba0f", // EBASE
+            "57ab1ec28d129707052df4df418d58a2d46d5f51", // sUSD
+            "9f8f72aa9304c8b593d555f12ef6589cc3a579a2", // MKR
+            "c00e94cb662c3520282e6f5717214004a7f26888", // COMP
+            "514910771af9ca656af840dff83e8264ecf986ca", // LINK
+            "c011a73ee8576fb46f5e1c5751ca3b9fe0af2a6f", // SNX
+            "0bc529c00c6401aef6d220be8c6ea1667f6ad93e", // YFI
+            "111111111117dc0aa78b770fa6a738034120c302", // 1INCH
+            "df5e0e81dff6faf3a7e52ba697820c5e32d806a8", // yCurv
+            "956f47f50a910163d8bf957cf5846d573e7f87ca", // FEI
+            "7d1afa7b718fb893db30a3abc0cfc608aacfebb0", // MATIC
+            "7fc66500c84a76ad7e9c93437bfc5ac33e2ddae9", // AAVE
+            "fe2e637202056d30016725477c5da089ab0a043a", // sETH2
+        ]),
+        denylist_pools: to_strings(&[
+            "8fe8d9bb8eeba3ed688069c3d6b556c9ca258248", // skipped by the subgraph
+        ]),
+        denylist_tokens: HashSet::new(),
+        min_eth_locked: BigDecimal::from(52),
+        max_price_hops: 3,
+        price_stable_coins: false,
+        depeg_threshold: BigDecimal::try_from("0.02").unwrap(),
+        liquidity_depth_bps: vec![100, 200, 500, 1000],
+    }
+}
+
+// the native token is MATIC, prices are derived in WMATIC like in the polygon subgraph
+fn polygon() -> Config {
+    Config {
+        network: "polygon".to_string(),
+        factories: vec![PoolFactory::uniswap_v3(hex!(
+            "1f98431c8ad98523631ae4a59f267346ea31f984"
+        ))],
+        position_manager: hex!("c36442b4a4522e871399cd717abdd847ab11fe88"),
+        weth: "0d500b1d8e8ef31e21c99d1db9a6444d3adf1270".to_string(),
+        eth_price_pools: vec!["a374094527e1673a86de625aa59517c5de346d32".to_string()],
+        stable_coins: to_strings(&[
+            "2791bca1f2de4661ed88a30c99a7a9449aa84174", // USDC.e
+            "3c499c542cef5e3811e1192ce70d8cc03d5c3359", // USDC
+            "c2132d05d31c914a87c6611c10748aeb04b58e8f", // USDT
+            "8f3cf7ad23cd3cadbd9735aff958023239c6a063", // DAI
+        ]),
+        whitelist_tokens: to_strings(&[
+            "0d500b1d8e8ef31e21c99d1db9a6444d3adf1270", // WMATIC
+            "7ceb23fd6bc0add59e62ac25578270cff1b9f619", // WETH
+            "2791bca1f2de4661ed88a30c99a7a9449aa84174", // USDC.e
+            "3c499c542cef5e3811e1192ce70d8cc03d5c3359", // USDC
+            "c2132d05d31c914a87c6611c10748aeb04b58e8f", // USDT
+            "8f3cf7ad23cd3cadbd9735aff958023239c6a063", // DAI
+            "1bfd67037b42cf73acf2047067bd4f2c47d9bfd6", // WBTC
+        ]),
+        denylist_pools: HashSet::new(),
+        denylist_tokens: HashSet::new(),
+        min_eth_locked: BigDecimal::from(52),
+        max_price_hops: 3,
+        price_stable_coins: false,
+        depeg_threshold: BigDecimal::try_from("0.02").unwrap(),
+        liquidity_depth_bps: vec![100, 200, 500, 1000],
+    }
+}
+
+fn arbitrum() -> Config {
+    Config {
+        network: "arbitrum".to_string(),
+        factories: vec![PoolFactory::uniswap_v3(hex!(
+            "1f98431c8ad98523631ae4a59f267346ea31f984"
+        ))],
+        position_manager: hex!("c36442b4a4522e871399cd717abdd847ab11fe88"),
+        weth: "82af49447d8a07e3bd95bd0d56f35241523fbab1".to_string(),
+        eth_price_pools: vec!["17c14d2c404d167802b16c450d3c99f88f2c4f4d".to_string()],
+        stable_coins: to_strings(&[
+            "ff970a61a04b1ca14834a43f5de4533ebddb5cc8", // USDC.e
+            "af88d065e77c8cc2239327c5edb3a432268e5831", // USDC
+            "fd086bc7cd5c481dcc9c85ebe478a1c0b69fcbb9", // USDT
+            "da10009cbd5d07dd0cecc66161fc93d7c9000da1", // DAI
+        ]),
+        whitelist_tokens: to_strings(&[
+            "82af49447d8a07e3bd95bd0d56f35241523fbab1", // WETH
+            "ff970a61a04b1ca14834a43f5de4533ebddb5cc8", // USDC.e
+            "af88d065e77c8cc2239327c5edb3a432268e5831", // USDC
+            "fd086bc7cd5c481dcc9c85ebe478a1c0b69fcbb9", // USDT
+            "da10009cbd5d07dd0cecc66161fc93d7c9000da1", // DAI
+            "2f2a2543b76a4166549f7aab2e75bef0aefc5b0f", // WBTC
+            "912ce59144191c1204e64559fe8253a0e49e6548", // ARB
+        ]),
+        denylist_pools: HashSet::new(),
+        denylist_tokens: HashSet::new(),
+        min_eth_locked: BigDecimal::from(52),
+        max_price_hops: 3,
+        price_stable_coins: false,
+        depeg_threshold: BigDecimal::try_from("0.02").unwrap(),
+        liquidity_depth_bps: vec![100, 200, 500, 1000],
+    }
+}
+
+fn optimism() -> Config {
+    Config {
+        network: "optimism".to_string(),
+        factories: vec![PoolFactory::uniswap_v3(hex!(
+            "1f98431c8ad98523631ae4a59f267346ea31f984"
+        ))],
+        position_manager: hex!("c36442b4a4522e871399cd717abdd847ab11fe88"),
+        weth: "4200000000000000000000000000000000000006".to_string(),
+        eth_price_pools: vec!["85149247691df622eaf1a8bd0cafd40bc45154a9".to_string()],
+        stable_coins: to_strings(&[
+            "7f5c764cbc14f9669b88837ca1490cca17c31607", // USDC.e
+            "0b2c639c533813f4aa9d7837caf62653d097ff85", // USDC
+            "94b008aa00579c1307b0ef2c499ad98a8ce58e58", // USDT
+            "da10009cbd5d07dd0cecc66161fc93d7c9000da1", // DAI
+        ]),
+        whitelist_tokens: to_strings(&[
+            "4200000000000000000000000000000000000006", // WETH
+            "7f5c764cbc14f9669b88837ca1490cca17c31607", // USDC.e
+            "0b2c639c533813f4aa9d7837caf62653d097ff85", // USDC
+            "94b008aa00579c1307b0ef2c499ad98a8ce58e58", // USDT
+            "da10009cbd5d07dd0cecc66161fc93d7c9000da1", // DAI
+            "68f180fcce6836688e9084f035309e29bf0a2095", // WBTC
+            "4200000000000000000000000000000000000042", // OP
+        ]),
+        denylist_pools: HashSet::new(),
+        denylist_tokens: HashSet::new(),
+        min_eth_locked: BigDecimal::from(52),
+        max_price_hops: 3,
+        price_stable_coins: false,
+        depeg_threshold: BigDecimal::try_from("0.02").unwrap(),
+        liquidity_depth_bps: vec![100, 200, 500, 1000],
+    }
+}
+
+fn base() -> Config {
+    Config {
+        network: "base".to_string(),
+        factories: vec![PoolFactory::uniswap_v3(hex!(
+            "33128a8fc17869897dce68ed026d694621f6fdfd"
+        ))],
+        position_manager: hex!("03a520b32c04bf3beef7beb72e919cf822ed34f1"),
+        weth: "4200000000000000000000000000000000000006".to_string(),
+        eth_price_pools: vec!["d0b53d9277642d899df5c87a3966a349a798f224".to_string()],
+        stable_coins: to_strings(&[
+            "833589fcd6edb6e08f4c7c32d4f71b54bda02913", // USDC
+            "d9aaec86b65d86f6a7b5b1b0c42ffa531710b6ca", // USDbC
+            "50c5725949a6f0c72e6c4a641f24049a917db0cb", // DAI
+        ]),
+        whitelist_tokens: to_strings(&[
+            "4200000000000000000000000000000000000006", // WETH
+            "833589fcd6edb6e08f4c7c32d4f71b54bda02913", // USDC
+            "d9aaec86b65d86f6a7b5b1b0c42ffa531710b6ca", // USDbC
+            "50c5725949a6f0c72e6c4a641f24049a917db0cb", // DAI
+            "2ae3f1ec7f1f5012cfeab0185bfc7aa3cf0dec22", // cbETH
+        ]),
+        denylist_pools: HashSet::new(),
+        denylist_tokens: HashSet::new(),
+        min_eth_locked: BigDecimal::from(52),
+        max_price_hops: 3,
+        price_stable_coins: false,
+        depeg_threshold: BigDecimal::try_from("0.02").unwrap(),
+        liquidity_depth_bps: vec![100, 200, 500, 1000],
+    }
+}
+
+fn to_strings(addresses: &[&str]) -> HashSet<String> {
+    addresses.iter().map(|address| address.to_string()).collect()
+}
+
+fn normalize_address(address: &str) -> String {
+    address.trim().trim_start_matches("0x").to_lowercase()
+}
+
+fn parse_address(address: &str) -> [u8; 20] {
+    ::hex::decode(normalize_address(address))
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .expect(&format!("invalid address {address}"))
+}
+
+fn parse_factories(factories: &str) -> Vec<PoolFactory> {
+    let factories: Vec<PoolFactory> = factories
+        .split(",")
+        .map(str::trim)
+        .filter(|factory| !factory.is_empty())
+        .map(|factory| {
+            let (protocol, address) = factory
+                .split_once(":")
+                .expect(&format!("invalid factory {factory}, expected protocol:address"));
+            PoolFactory {
+                address: parse_address(address),
+                protocol: protocol.to_string(),
+            }
+        })
+        .collect();
+    assert!(!factories.is_empty(), "factories can't be empty");
+    factories
+}
+
+fn parse_address_list(addresses: &str) -> Vec<String> {
+    addresses
+        .split(",")
+        .map(normalize_address)
+        .filter(|address| !address.is_empty())
+        .collect()
+}
+
+fn parse_addresses(addresses: &str) -> HashSet<String> {
+    addresses
+        .split(",")
+        .map(normalize_address)
+        .filter(|address| !address.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::Config;
+    use crate::storage::uniswap_v3_pool::PoolLayout;
+    use std::panic;
+    use substreams::scalar::BigDecimal;
+    use substreams::Hex;
+
+    const UNISWAP_FACTORY: &str = "1f98431c8ad98523631ae4a59f267346ea31f984";
+    const BASE_FACTORY: &str = "33128a8fc17869897dce68ed026d694621f6fdfd";
+    const PANCAKE_FACTORY: &str = "0bfbcf9fa4f9c56b0f40a671ad40e0805a091865";
+    const WETH: &str = "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+    const USDC: &str = "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const WBTC: &str = "2260fac5e5542a773aa44fbcfedf7c193bc2c599";
+    const DAI: &str = "6b175474e89094c44da98b954eedeac495271d0f";
+    const USDC_WETH_POOL: &str = "88e6a0c2ddd26feeb64f039a2c41296fcb3f5640";
+    const OTHER_USDC_WETH_POOL: &str = "8ad599c3a0ff1de082011efddc58f1908eb6e6d8";
+    const MAINNET_DENYLISTED_POOL: &str = "8fe8d9bb8eeba3ed688069c3d6b556c9ca258248";
+
+    #[test]
+    fn test_networks_and_factories() {
+        // (params, network, factories, protocol of each factory, weth)
+        for (params, network, factories, protocols, weth) in [
+            ("", "mainnet", vec![UNISWAP_FACTORY], vec!["uniswap-v3"], WETH),
+            (
+                "network=mainnet",
+                "mainnet",
+                vec![UNISWAP_FACTORY],
+                vec!["uniswap-v3"],
+                WETH,
+            ),
+            (
+                "network=polygon",
+                "polygon",
+                vec![UNISWAP_FACTORY],
+                vec!["uniswap-v3"],
+                "0d500b1d8e8ef31e21c99d1db9a6444d3adf1270",
+            ),
+            (
+                "network=arbitrum",
+                "arbitrum",
+                vec![UNISWAP_FACTORY],
+                vec!["uniswap-v3"],
+                "82af49447d8a07e3bd95bd0d56f35241523fbab1",
+            ),
+            (
+                "network=optimism",
+                "optimism",
+                vec![UNISWAP_FACTORY],
+                vec!["uniswap-v3"],
+                "4200000000000000000000000000000000000006",
+            ),
+            (
+                "network=base",
+                "base",
+                vec![BASE_FACTORY],
+                vec!["uniswap-v3"],
+                "4200000000000000000000000000000000000006",
+            ),
+            (
+                "factory=0x33128a8fc17869897dce68ed026d694621f6fdfd",
+                "mainnet",
+                vec![BASE_FACTORY],
+                vec!["uniswap-v3"],
+                WETH,
+            ),
+            (
+                "factories=uniswap-v3:1f98431c8ad98523631ae4a59f267346ea31f984,\
+                 pancakeswap-v3:0x0BFbCF9fa4f9C56B0F40a671Ad40E0805A091865",
+                "mainnet",
+                vec![UNISWAP_FACTORY, PANCAKE_FACTORY],
+                vec!["uniswap-v3", "pancakeswap-v3"],
+                WETH,
+            ),
+        ] {
+            let config = Config::parse(params);
+            assert_eq!(network, config.network, "{params}");
+            assert_eq!(factories[0], config.factory_address(), "{params}");
+            assert_eq!(
+                factories,
+                config
+                    .factory_addresses()
+                    .iter()
+                    .map(|address| Hex(address).to_string())
+                    .collect::<Vec<String>>(),
+                "{params}"
+            );
+            assert_eq!(
+                protocols,
+                config
+                    .factories
+                    .iter()
+                    .map(|factory| factory.protocol.as_str())
+                    .collect::<Vec<&str>>(),
+                "{params}"
+            );
+            assert_eq!(weth, config.weth, "{params}");
+            // the native token is whitelisted and priced from pools holding a stable coin
+            assert!(config.is_whitelisted(&config.weth), "{params}");
+            assert!(!config.eth_price_pools.is_empty(), "{params}");
+            assert!(!config.stable_coins.is_empty(), "{params}");
+        }
+    }
+
+    #[test]
+    fn test_pool_layouts() {
+        let config = Config::parse(&format!(
+            "factories=uniswap-v3:{UNISWAP_FACTORY},pancakeswap-v3:{PANCAKE_FACTORY}"
+        ));
+        for (factory, layout) in [
+            (UNISWAP_FACTORY, PoolLayout::UniswapV3),
+            (PANCAKE_FACTORY, PoolLayout::PancakeSwapV3),
+        ] {
+            let address = ::hex::decode(factory).unwrap();
+            assert_eq!(
+                layout,
+                config.pool_factory(&address).unwrap().pool_layout(),
+                "{factory}"
+            );
+        }
+        assert!(config.pool_factory(&[0u8; 20]).is_none());
+    }
+
+    #[test]
+    fn test_overrides() {
+        // (params, weth, min_eth_locked, max_price_hops, price_stable_coins, depeg_threshold, liquidity_depth_bps)
+        for (params, weth, min_eth_locked, max_price_hops, price_stable_coins, depeg_threshold, liquidity_depth_bps) in [
+            ("", WETH, "52", 3, false, "0.02", vec![100, 200, 500, 1000]),
+            (
+                "network=base & weth=0xC02AAA39B223FE8D0A0E5C4F27EAD9083C756CC2&min_eth_locked=1.5&max_price_hops=2\
+                 &price_stable_coins=true&depeg_threshold=0.05&liquidity_depth_bps=50,,300",
+                WETH,
+                "1.5",
+                2,
+                true,
+                "0.05",
+                vec![50, 300],
+            ),
+        ] {
+            let config = Config::parse(params);
+            assert_eq!(weth, config.weth, "{params}");
+            assert_eq!(
+                BigDecimal::try_from(min_eth_locked).unwrap(),
+                config.min_eth_locked,
+                "{params}"
+            );
+            assert_eq!(max_price_hops, config.max_price_hops, "{params}");
+            assert_eq!(price_stable_coins, config.price_stable_coins, "{params}");
+            assert_eq!(
+                BigDecimal::try_from(depeg_threshold).unwrap(),
+                config.depeg_threshold,
+                "{params}"
+            );
+            assert_eq!(liquidity_depth_bps, config.liquidity_depth_bps, "{params}");
+        }
+    }
+
+    #[test]
+    fn test_address_lists() {
+        type Check = fn(&Config, &str) -> bool;
+        let is_whitelisted: Check = Config::is_whitelisted;
+        let is_stable_coin: Check = Config::is_stable_coin;
+        let is_denylisted_token: Check = Config::is_denylisted_token;
+        let is_denylisted_pool: Check = |config, pool| config.is_denylisted(pool, "", "");
+        let is_denylisted_pair: Check =
+            |config, pool| config.is_denylisted(pool, USDC, WETH) && config.is_denylisted(pool, WETH, USDC);
+
+        let overrides = format!(
+            "whitelist_tokens=0x{}, {WBTC},&stable_coins={USDC}&denylist_pools={USDC_WETH_POOL}&denylist_tokens=0x{}",
+            USDC.to_uppercase(),
+            USDC.to_uppercase()
+        );
+        let overrides = overrides.as_str();
+
+        // (params, check, address, expected), the overrides replace the profile's lists
+        for (params, check, address, expected) in [
+            ("", is_whitelisted, WETH, true),
+            ("", is_stable_coin, DAI, true),
+            ("", is_denylisted_pool, MAINNET_DENYLISTED_POOL, true),
+            ("", is_denylisted_pair, USDC_WETH_POOL, false),
+            ("", is_denylisted_token, WETH, false),
+            (overrides, is_whitelisted, USDC, true),
+            (overrides, is_whitelisted, WBTC, true),
+            (overrides, is_whitelisted, WETH, false),
+            (overrides, is_stable_coin, USDC, true),
+            (overrides, is_stable_coin, DAI, false),
+            (overrides, is_denylisted_pool, USDC_WETH_POOL, true),
+            (overrides, is_denylisted_pool, MAINNET_DENYLISTED_POOL, false),
+            // a pool holding a denylisted token is denylisted on either side
+            (overrides, is_denylisted_pair, OTHER_USDC_WETH_POOL, true),
+            (overrides, is_denylisted_token, USDC, true),
+            (overrides, is_denylisted_token, WETH, false),
+        ] {
+            assert_eq!(expected, check(&Config::parse(params), address), "{params} {address}");
+        }
+
+        // the order of the eth price pools is kept
+        let config = Config::parse(&format!("eth_price_pools={OTHER_USDC_WETH_POOL},{USDC_WETH_POOL}"));
+        assert_eq!(vec![OTHER_USDC_WETH_POOL, USDC_WETH_POOL], config.eth_price_pools);
+    }
+
+    #[test]
+    fn test_factory_id_is_checksummed() {
+        for (params, factory_id) in [
+            ("network=mainnet", "0x1F98431c8aD98523631AE4a59f267346ea31F984"),
+            ("network=base", "0x33128a8fC17869897dcE68Ed026d694621f6FDfD"),
+        ] {
+            assert_eq!(factory_id, Config::parse(params).factory_id());
+        }
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        // (params, start of the panic message)
+        for (params, message) in [
+            ("network=solana", "unknown network"),
+            (
+                "network=mainnet&whitelist=c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+                "unknown param",
+            ),
+            ("mainnet", "invalid param mainnet, expected key=value"),
+            ("factory=1f98431c8ad98523631ae4a59f267346ea31f9", "invalid address"),
+            ("factories=1f98431c8ad98523631ae4a59f267346ea31f984", "invalid factory"),
+            ("factories=,", "factories can't be empty"),
+            ("max_price_hops=-1", "invalid max_price_hops"),
+            ("liquidity_depth_bps=100,1%", "invalid liquidity_depth_bps"),
+        ] {
+            let panic = panic::catch_unwind(|| Config::parse(params)).expect_err(params);
+            let panic_message = match panic.downcast_ref::<String>() {
+                Some(panic_message) => panic_message.as_str(),
+                None => *panic.downcast_ref::<&str>().unwrap(),
+            };
+            assert!(panic_message.starts_with(message), "{params}: {panic_message}");
+        }
+    }
+
+    #[test]
+    fn test_get_caches_per_params() {
+        let mainnet = Config::get("network=mainnet");
+        assert!(std::ptr::eq(mainnet, Config::get("network=mainnet")));
+        assert_eq!("base", Config::get("network=base").network);
+        assert_eq!("mainnet", Config::get("network=mainnet").network);
+    }
+}
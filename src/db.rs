@@ -1,3 +1,4 @@
+use std::collections::{BTreeMap, HashSet};
 use std::ops::Div;
 use substreams::key::{
     key_first_segment_in, key_first_segments_in, key_last_segment_in, key_last_segments_in, operations_eq,
@@ -7,51 +8,114 @@ use substreams::pb::substreams::store_delta::Operation;
 use substreams::prelude::*;
 use substreams::scalar::{BigDecimal, BigInt};
 use substreams::store::{
-    DeltaArray, DeltaBigDecimal, DeltaBigInt, DeltaProto, Deltas, StoreGet, StoreGetBigDecimal, StoreGetBigInt,
-    StoreGetProto,
+    DeltaArray, DeltaBigDecimal, DeltaBigInt, DeltaInt64, DeltaProto, Deltas, StoreGet, StoreGetBigDecimal,
+    StoreGetBigInt, StoreGetProto,
 };
 use substreams::{log, Hex};
 use substreams_entity_change::tables::Tables;
 
-use crate::pb::uniswap::events::pool_event::Type::{Burn as BurnEvent, Mint as MintEvent, Swap as SwapEvent};
+use crate::config::Config;
+use crate::pb::uniswap::events::pool_event::Type::{
+    Burn as BurnEvent, Collect as CollectEvent, Mint as MintEvent, Swap as SwapEvent,
+};
 use crate::pb::uniswap::events::position_event::Type;
 use crate::pb::uniswap::events::{IncreaseLiquidityPosition, PoolSqrtPrice, PositionEvent};
-use crate::pb::uniswap::{events, Events, Pool};
+use crate::pb::uniswap::{events, factory_events, Events, Pool, SeedSnapshot};
 use crate::uniswap::{Erc20Token, Pools};
 use crate::{key, utils};
 
+// -------------------
+//  Map Seed Entities
+// -------------------
+// entities of a seeded deployment, their values are then set by the deltas of the seeded stores
+pub fn seed_snapshot_entity_changes(tables: &mut Tables, seed: &SeedSnapshot) {
+    let mut tokens: HashSet<&String> = HashSet::new();
+    for pool in &seed.pools {
+        create_pool(tables, pool);
+        for token in [pool.token0_ref(), pool.token1_ref()] {
+            if tokens.insert(token.address()) {
+                add_token_entity_change(tables, token);
+            }
+        }
+    }
+
+    let mut created_positions = vec![];
+    let mut transfer_positions = vec![];
+    for position in &seed.positions {
+        match position.r#type.as_ref().unwrap() {
+            Type::CreatedPosition(created) => created_positions.push(created.clone()),
+            Type::TransferPosition(transfer) => transfer_positions.push(transfer.clone()),
+            _ => {}
+        }
+    }
+    position_create_entity_change(tables, &created_positions);
+    transfer_position_entity_change(tables, &transfer_positions);
+}
+
 // -------------------
 //  Map Bundle Entities
 // -------------------
+pub fn singletons_created_entity_change(tables: &mut Tables, singletons_deltas: &Deltas<DeltaInt64>) {
+    for delta in singletons_deltas.deltas.iter().filter(|delta| delta.old_value == 0) {
+        match key::first_segment(&delta.key) {
+            "factory" => {
+                let factory_id = utils::factory_id(key::segment(&delta.key, 1));
+                factory_created_factory_entity_change(tables, &factory_id)
+            }
+            "bundle" => created_bundle_entity_change(tables),
+            _ => {}
+        }
+    }
+}
+
 pub fn created_bundle_entity_change(tables: &mut Tables) {
     tables
         .create_row("Bundle", "1")
-        .set_bigdecimal("ethPriceUSD", &"0.0".to_string());
+        .set_bigdecimal("ethPriceUSD", &"0.0".to_string())
+        .set("ethPricePools", &Vec::<String>::new());
 }
 
 pub fn bundle_store_eth_price_usd_bundle_entity_change(
     tables: &mut Tables,
     derived_eth_prices_deltas: &Deltas<DeltaBigDecimal>,
 ) {
+    // weights of the pools are all set along with each price, the last ones of the block are the ones
+    // of the last price
+    let mut pool_weights: BTreeMap<String, BigDecimal> = BTreeMap::new();
     for delta in derived_eth_prices_deltas
         .deltas
         .iter()
         .filter(key_first_segment_in("bundle"))
     {
-        tables.update_row("Bundle", "1").set("ethPriceUSD", &delta.new_value);
+        match delta.key.as_str() {
+            "bundle" => {
+                tables.update_row("Bundle", "1").set("ethPriceUSD", &delta.new_value);
+            }
+            _ => {
+                pool_weights.insert(key::last_segment(&delta.key).to_string(), delta.new_value.clone());
+            }
+        }
     }
+
+    if pool_weights.is_empty() {
+        return;
+    }
+    let pools: Vec<String> = pool_weights
+        .into_iter()
+        .filter(|(_, weight)| weight.gt(&BigDecimal::zero()))
+        .map(|(pool_address, _)| format!("0x{pool_address}"))
+        .collect();
+    tables.update_row("Bundle", "1").set("ethPricePools", &pools);
 }
 
 // -------------------
 //  Map Factory Entities
 // -------------------
-pub fn factory_created_factory_entity_change(tables: &mut Tables) {
-    let id = "0x1F98431c8aD98523631AE4a59f267346ea31F984";
-
+pub fn factory_created_factory_entity_change(tables: &mut Tables, factory_id: &String) {
     let bigint0 = BigInt::zero();
     let bigdecimal0 = BigDecimal::zero();
     tables
-        .create_row("Factory", id)
+        .create_row("Factory", factory_id)
         .set("poolCount", &bigint0)
         .set("txCount", &bigint0)
         .set("totalVolumeUSD", &bigdecimal0)
@@ -63,25 +127,40 @@ pub fn factory_created_factory_entity_change(tables: &mut Tables) {
         .set("totalValueLockedETH", &bigdecimal0)
         .set("totalValueLockedUSDUntracked", &bigdecimal0)
         .set("totalValueLockedETHUntracked", &bigdecimal0)
+        .set("protocolFeesUSD", &bigdecimal0)
+        .set("protocolFeesCollectedUSD", &bigdecimal0)
         .set("owner", &format!("0x{}", Hex(utils::ZERO_ADDRESS).to_string()));
 }
 
-pub fn pool_created_factory_entity_change(tables: &mut Tables, pool_count_deltas: &Deltas<DeltaBigInt>) {
-    pool_count_deltas.deltas.iter().for_each(|delta| {
-        let id = "0x1F98431c8aD98523631AE4a59f267346ea31F984".to_string();
-        tables.update_row("Factory", &id).set("poolCount", &delta.new_value);
-    })
+pub fn pool_created_factory_entity_change(
+    tables: &mut Tables,
+    pool_count_deltas: &Deltas<DeltaBigInt>,
+    factory_id: &String,
+) {
+    pool_count_deltas
+        .deltas
+        .iter()
+        .filter(key_first_segment_in("factory"))
+        .for_each(|delta| {
+            tables
+                .update_row("Factory", factory_id)
+                .set("poolCount", &delta.new_value);
+        })
 }
 
-pub fn tx_count_factory_entity_change(tables: &mut Tables, tx_count_deltas: &Deltas<DeltaBigInt>) {
+pub fn tx_count_factory_entity_change(tables: &mut Tables, tx_count_deltas: &Deltas<DeltaBigInt>, factory_id: &String) {
     for delta in tx_count_deltas.deltas.iter().filter(key_first_segment_in("factory")) {
         tables
-            .update_row("Factory", "0x1F98431c8aD98523631AE4a59f267346ea31F984")
+            .update_row("Factory", factory_id)
             .set("txCount", &delta.new_value);
     }
 }
 
-pub fn swap_volume_factory_entity_change(tables: &mut Tables, swaps_volume_deltas: &Deltas<DeltaBigDecimal>) {
+pub fn swap_volume_factory_entity_change(
+    tables: &mut Tables,
+    swaps_volume_deltas: &Deltas<DeltaBigDecimal>,
+    factory_id: &String,
+) {
     for delta in swaps_volume_deltas
         .deltas
         .iter()
@@ -95,12 +174,16 @@ pub fn swap_volume_factory_entity_change(tables: &mut Tables, swaps_volume_delta
         ]))
     {
         tables
-            .update_row("Factory", "0x1F98431c8aD98523631AE4a59f267346ea31F984")
+            .update_row("Factory", factory_id)
             .set(key::last_segment(&delta.key), &delta.new_value);
     }
 }
 
-pub fn tvl_factory_entity_change(tables: &mut Tables, derived_factory_tvl_deltas: &Deltas<DeltaBigDecimal>) {
+pub fn tvl_factory_entity_change(
+    tables: &mut Tables,
+    derived_factory_tvl_deltas: &Deltas<DeltaBigDecimal>,
+    factory_id: &String,
+) {
     for delta in derived_factory_tvl_deltas
         .deltas
         .iter()
@@ -113,11 +196,69 @@ pub fn tvl_factory_entity_change(tables: &mut Tables, derived_factory_tvl_deltas
         ]))
     {
         tables
-            .update_row("Factory", "0x1F98431c8aD98523631AE4a59f267346ea31F984")
+            .update_row("Factory", factory_id)
             .set(key::last_segment(&delta.key), &delta.new_value);
     }
 }
 
+pub fn protocol_fees_factory_entity_change(
+    tables: &mut Tables,
+    protocol_fees_deltas: &Deltas<DeltaBigDecimal>,
+    factory_id: &String,
+) {
+    for delta in protocol_fees_deltas
+        .deltas
+        .iter()
+        .filter(key_first_segment_in("factory"))
+        .filter(key_last_segments_in(vec![
+            "protocolFeesUSD",
+            "protocolFeesCollectedUSD",
+        ]))
+    {
+        tables
+            .update_row("Factory", factory_id)
+            .set(key::last_segment(&delta.key), &delta.new_value);
+    }
+}
+
+pub fn owner_changed_factory_entity_change(tables: &mut Tables, owner_changes: &Vec<factory_events::OwnerChanged>) {
+    for owner_change in owner_changes {
+        tables
+            .update_row("Factory", utils::factory_id(&owner_change.factory))
+            .set("owner", &format!("0x{}", owner_change.new_owner));
+    }
+}
+
+// -------------------
+//  Map FeeTier Entities
+// -------------------
+pub fn fee_amount_enabled_fee_tier_entity_change(
+    tables: &mut Tables,
+    fee_amounts_enabled: &Vec<factory_events::FeeAmountEnabled>,
+) {
+    for fee_amount in fee_amounts_enabled {
+        let factory_id = utils::factory_id(&fee_amount.factory);
+        tables
+            .create_row("FeeTier", format!("{factory_id}#{}", fee_amount.fee))
+            .set("factory", &factory_id)
+            .set_bigint("fee", &fee_amount.fee)
+            .set("tickSpacing", BigInt::from(fee_amount.tick_spacing))
+            .set("poolCount", BigInt::zero())
+            .set("createdAtTimestamp", fee_amount.timestamp)
+            .set("createdAtBlockNumber", fee_amount.block_number);
+    }
+}
+
+pub fn pool_count_fee_tier_entity_change(tables: &mut Tables, pool_count_deltas: &Deltas<DeltaBigInt>) {
+    for delta in pool_count_deltas.deltas.iter().filter(key_first_segment_in("feeTier")) {
+        let factory_id = utils::factory_id(key::segment(&delta.key, 1));
+        let fee = key::segment(&delta.key, 2);
+        tables
+            .update_row("FeeTier", format!("{factory_id}#{fee}"))
+            .set("poolCount", &delta.new_value);
+    }
+}
+
 // -------------------
 //  Map Pool Entities
 // -------------------
@@ -137,6 +278,7 @@ fn create_pool(tables: &mut Tables, pool: &Pool) {
         .set("token0", format!("0x{}", pool.token0.as_ref().unwrap().address))
         .set("token1", format!("0x{}", pool.token1.as_ref().unwrap().address))
         .set_bigint("feeTier", &pool.fee_tier)
+        .set("protocol", &pool.protocol)
         .set("liquidity", &bigint0)
         .set("sqrtPrice", &bigint0)
         .set("feeGrowthGlobal0X128", &bigint0)
@@ -150,10 +292,22 @@ fn create_pool(tables: &mut Tables, pool: &Pool) {
         .set("volumeUSD", &bigdecimal0)
         .set("untrackedVolumeUSD", &bigdecimal0)
         .set("feesUSD", &bigdecimal0)
+        .set("feesUSD7d", &bigdecimal0)
+        .set("feesUSD30d", &bigdecimal0)
+        .set("feeAPR7d", &bigdecimal0)
+        .set("feeAPR30d", &bigdecimal0)
         .set("txCount", &bigint0)
         .set("collectedFeesToken0", &bigdecimal0)
         .set("collectedFeesToken1", &bigdecimal0)
         .set("collectedFeesUSD", &bigdecimal0)
+        .set("feeProtocol0", &bigint0)
+        .set("feeProtocol1", &bigint0)
+        .set("protocolFeesToken0", &bigdecimal0)
+        .set("protocolFeesToken1", &bigdecimal0)
+        .set("protocolFeesUSD", &bigdecimal0)
+        .set("protocolFeesCollectedToken0", &bigdecimal0)
+        .set("protocolFeesCollectedToken1", &bigdecimal0)
+        .set("protocolFeesCollectedUSD", &bigdecimal0)
         .set("totalValueLockedToken0", &bigdecimal0)
         .set("totalValueLockedToken1", &bigdecimal0)
         .set("totalValueLockedETH", &bigdecimal0)
@@ -185,6 +339,8 @@ fn create_pool_windows_entity(
         .set("volumeToken1", BigDecimal::zero())
         .set("volumeUSD", BigDecimal::zero())
         .set("feesUSD", BigDecimal::zero())
+        .set("feeAPR", BigDecimal::zero())
+        .set("feesPerLiquidityUSD", BigDecimal::zero())
         .set("txCount", BigInt::zero())
         .set("open", BigDecimal::zero())
         .set("high", BigDecimal::zero())
@@ -314,6 +470,9 @@ pub fn swap_volume_pool_entity_change(tables: &mut Tables, swaps_volume_deltas:
             "volumeUSD" => "volumeUSD",
             "volumeUntrackedUSD" => "untrackedVolumeUSD",
             "feesUSD" => "feesUSD",
+            "collectedFeesToken0" => "collectedFeesToken0",
+            "collectedFeesToken1" => "collectedFeesToken1",
+            "collectedFeesUSD" => "collectedFeesUSD",
             "liquidityProviderCount" => "liquidityProviderCount",
             _ => continue,
         };
@@ -331,6 +490,46 @@ pub fn swap_volume_pool_entity_change(tables: &mut Tables, swaps_volume_deltas:
     }
 }
 
+pub fn fee_protocol_pool_entity_change(tables: &mut Tables, fee_protocols_deltas: &Deltas<DeltaInt64>) {
+    for delta in fee_protocols_deltas.deltas.iter().filter(key_first_segment_in("pool")) {
+        let pool_address = key::segment(&delta.key, 1);
+        tables
+            .update_row("Pool", &format!("0x{pool_address}"))
+            .set(key::last_segment(&delta.key), BigInt::from(delta.new_value));
+    }
+}
+
+pub fn protocol_fees_pool_entity_change(tables: &mut Tables, protocol_fees_deltas: &Deltas<DeltaBigDecimal>) {
+    for delta in protocol_fees_deltas.deltas.iter().filter(key_first_segment_in("pool")) {
+        let pool_address = key::segment(&delta.key, 1);
+        tables
+            .update_row("Pool", &format!("0x{pool_address}"))
+            .set(key::last_segment(&delta.key), &delta.new_value);
+    }
+}
+
+pub fn twaps_pool_entity_change(tables: &mut Tables, pool_twaps_deltas: &Deltas<DeltaBigDecimal>) {
+    for delta in pool_twaps_deltas.deltas.iter().filter(key_first_segment_in("pool")) {
+        let pool_address = key::segment(&delta.key, 1);
+        let field_name = key::last_segment(&delta.key);
+        let row = tables.update_row("Pool", &format!("0x{pool_address}"));
+        if field_name.starts_with("tickTwap") {
+            row.set(field_name, &delta.new_value.to_bigint());
+        } else {
+            row.set(field_name, &delta.new_value);
+        }
+    }
+}
+
+pub fn yields_pool_entity_change(tables: &mut Tables, pool_yields_deltas: &Deltas<DeltaBigDecimal>) {
+    for delta in pool_yields_deltas.deltas.iter().filter(key_first_segment_in("pool")) {
+        let pool_address = key::segment(&delta.key, 1);
+        tables
+            .update_row("Pool", &format!("0x{pool_address}"))
+            .set(key::last_segment(&delta.key), &delta.new_value);
+    }
+}
+
 // --------------------
 //  Map Token Entities
 // --------------------
@@ -388,6 +587,7 @@ fn add_token_entity_change(tables: &mut Tables, token: &Erc20Token) {
         .set("totalValueLockedUSD", &bigdecimal0)
         .set("totalValueLockedUSDUntracked", &bigdecimal0)
         .set("derivedETH", &bigdecimal0)
+        .set("pegDeviation", &bigdecimal0)
         .set("whitelistPools", &whitelist);
 }
 
@@ -492,6 +692,7 @@ pub fn derived_eth_prices_token_entity_change(
         // TODO: maybe change the field name on the key itself??
         let field_name: &str = match key::last_segment(&delta.key) {
             "eth" => "derivedETH",
+            "deviation" => "pegDeviation",
             _ => continue,
         };
 
@@ -501,6 +702,40 @@ pub fn derived_eth_prices_token_entity_change(
     }
 }
 
+// stable coins whose peg deviation crosses the `depeg_threshold`, either way
+pub fn depeg_events_entity_change(
+    tables: &mut Tables,
+    block_number: u64,
+    timestamp: i64,
+    derived_eth_prices_deltas: &Deltas<DeltaBigDecimal>,
+    config: &Config,
+) {
+    for delta in derived_eth_prices_deltas
+        .deltas
+        .iter()
+        .filter(key_first_segment_in("token"))
+        .filter(key_last_segment_in("deviation"))
+    {
+        let was_depegged = delta.old_value.absolute().gt(&config.depeg_threshold);
+        let depegged = delta.new_value.absolute().gt(&config.depeg_threshold);
+        if was_depegged == depegged {
+            continue;
+        }
+
+        let token_address = key::segment(&delta.key, 1);
+        tables
+            .create_row(
+                "DepegEvent",
+                format!("0x{token_address}-{block_number}-{}", delta.ordinal),
+            )
+            .set("token", format!("0x{token_address}"))
+            .set("blockNumber", block_number)
+            .set("timestamp", timestamp as u64)
+            .set("pegDeviation", &delta.new_value)
+            .set("depegged", depegged);
+    }
+}
+
 pub fn whitelist_token_entity_change(tables: &mut Tables, tokens_whitelist_pools_deltas: Deltas<DeltaArray<String>>) {
     for delta in tokens_whitelist_pools_deltas.deltas {
         let token_address = key::segment(&delta.key, 1);
@@ -525,8 +760,8 @@ pub fn create_tick_entity_change(tables: &mut Tables, ticks_created: &Vec<events
         let tick_idx = &tick.idx;
         let id = format!("0x{pool_address}#{tick_idx}");
 
-        // We cannot determine when a new Tick is created. If a given tick idx was initialized
-        // in the past. In the future the same tick idx can be re-used for an event.
+        // only emitted when a mint initializes the tick, an initialized tick keeps its outside values
+        // and volumes. A tick idx cleared in the past is created again with zeroed values.
         tables
             .update_row("Tick", &id)
             .set("poolAddress", format!("0x{}", &tick.pool_address))
@@ -548,37 +783,89 @@ pub fn create_tick_entity_change(tables: &mut Tables, ticks_created: &Vec<events
             .set("createdAtBlockNumber", tick.created_at_block_number)
             .set("liquidityProviderCount", &bigint0)
             .set("feeGrowthOutside0X128", &bigint0)
-            .set("feeGrowthOutside1X128", &bigint0);
+            .set("feeGrowthOutside1X128", &bigint0)
+            .set("tickCumulativeOutside", &bigint0)
+            .set("secondsPerLiquidityOutsideX128", &bigint0)
+            .set("secondsOutside", &bigint0);
     }
 }
 
-pub fn update_tick_entity_change(tables: &mut Tables, ticks_updated: &Vec<events::TickUpdated>) {
+// on-chain values of the ticks after each mint, burn and crossing, in ordinal order as a tick can be
+// crossed and updated in the same block
+pub fn update_tick_entity_change(
+    tables: &mut Tables,
+    ticks_updated: &Vec<events::TickUpdated>,
+    ticks_crossed: &Vec<events::TickCrossed>,
+) {
+    let mut changes: Vec<(u64, &String, &String, Vec<(&str, &String)>)> = vec![];
     for tick in ticks_updated {
-        let pool_address = &tick.pool_address;
-        let tick_idx = &tick.idx;
+        changes.push((
+            tick.log_ordinal,
+            &tick.pool_address,
+            &tick.idx,
+            vec![
+                ("feeGrowthOutside0X128", &tick.fee_growth_outside_0x_128),
+                ("feeGrowthOutside1X128", &tick.fee_growth_outside_1x_128),
+                ("liquidityGross", &tick.liquidity_gross),
+                ("liquidityNet", &tick.liquidity_net),
+                ("tickCumulativeOutside", &tick.tick_cumulative_outside),
+                (
+                    "secondsPerLiquidityOutsideX128",
+                    &tick.seconds_per_liquidity_outside_x128,
+                ),
+                ("secondsOutside", &tick.seconds_outside),
+            ],
+        ));
+    }
+    for tick in ticks_crossed {
+        changes.push((
+            tick.log_ordinal,
+            &tick.pool_address,
+            &tick.idx,
+            vec![
+                ("feeGrowthOutside0X128", &tick.fee_growth_outside_0x_128),
+                ("feeGrowthOutside1X128", &tick.fee_growth_outside_1x_128),
+                ("tickCumulativeOutside", &tick.tick_cumulative_outside),
+                (
+                    "secondsPerLiquidityOutsideX128",
+                    &tick.seconds_per_liquidity_outside_x128,
+                ),
+                ("secondsOutside", &tick.seconds_outside),
+            ],
+        ));
+    }
+    changes.sort_by(|x, y| x.0.cmp(&y.0));
+
+    for (_, pool_address, tick_idx, fields) in changes {
         let row = tables.update_row("Tick", format!("0x{pool_address}#{tick_idx}"));
-        if tick.fee_growth_outside_0x_128.len() != 0 {
-            row.set_bigint("feeGrowthOutside0X128", &tick.fee_growth_outside_0x_128);
-        }
-        if tick.fee_growth_outside_1x_128.len() != 0 {
-            row.set_bigint("feeGrowthOutside1X128", &tick.fee_growth_outside_1x_128);
+        for (field, value) in fields {
+            if value.len() != 0 {
+                row.set_bigint(field, value);
+            }
         }
     }
 }
 
-pub fn liquidities_tick_entity_change(tables: &mut Tables, ticks_liquidities_deltas: &Deltas<DeltaBigInt>) {
-    for delta in ticks_liquidities_deltas
-        .deltas
-        .iter()
-        .filter(key_first_segment_in("tick"))
-        .filter(key_last_segments_in(vec!["liquidityNet", "liquidityGross"]))
-    {
-        let pool_id = key::segment(&delta.key, 1);
-        let tick_idx = key::segment(&delta.key, 2);
+pub fn cleared_tick_entity_change(
+    tables: &mut Tables,
+    ticks_cleared: &Vec<events::TickCleared>,
+    ticks_created: &Vec<events::TickCreated>,
+) {
+    for tick in ticks_cleared {
+        let pool_address = &tick.pool_address;
+        let tick_idx = &tick.idx;
 
-        tables
-            .update_row("Tick", &format!("0x{pool_id}#{tick_idx}"))
-            .set(key::last_segment(&delta.key), &delta.new_value);
+        // the tick can be initialized again by a mint later in the same block
+        let recreated = ticks_created.iter().any(|created| {
+            created.pool_address == tick.pool_address
+                && created.idx == tick.idx
+                && created.log_ordinal > tick.log_ordinal
+        });
+        if recreated {
+            continue;
+        }
+
+        tables.delete_row("Tick", format!("0x{pool_address}#{tick_idx}"));
     }
 }
 
@@ -693,6 +980,7 @@ pub fn position_create_entity_change(tables: &mut Tables, positions: &Vec<events
         tables
             .create_row("Position", &position.token_id)
             .set("owner", &Hex(utils::ZERO_ADDRESS).to_string().into_bytes())
+            .set("closed", false)
             .set("pool", format!("0x{}", &position.pool))
             .set("token0", format!("0x{}", position.token0))
             .set("token1", format!("0x{}", position.token1))
@@ -705,6 +993,18 @@ pub fn position_create_entity_change(tables: &mut Tables, positions: &Vec<events
             .set("withdrawnToken1", &bigdecimal0)
             .set("collectedFeesToken0", &bigdecimal0)
             .set("collectedFeesToken1", &bigdecimal0)
+            .set("uncollectedFeesToken0", &bigdecimal0)
+            .set("uncollectedFeesToken1", &bigdecimal0)
+            .set("uncollectedFeesUSD", &bigdecimal0)
+            .set("amount0", &bigdecimal0)
+            .set("amount1", &bigdecimal0)
+            .set("amountUSD", &bigdecimal0)
+            .set("depositedUSD", &bigdecimal0)
+            .set("withdrawnUSD", &bigdecimal0)
+            .set("collectedUSD", &bigdecimal0)
+            .set("realizedPnlUSD", &bigdecimal0)
+            .set("unrealizedPnlUSD", &bigdecimal0)
+            .set("impermanentLossUSD", &bigdecimal0)
             .set("transaction", format!("0x{}", position.transaction))
             .set_bigint(
                 "feeGrowthInside0LastX128",
@@ -792,9 +1092,119 @@ pub fn collect_position_entity_change(tables: &mut Tables, positions: &Vec<event
 
 pub fn transfer_position_entity_change(tables: &mut Tables, positions: &Vec<events::TransferPosition>) {
     for position in positions {
+        let owner = hex::decode(&position.owner).unwrap();
+        let row = tables.update_row("Position", position.token_id.clone());
+
+        // the NFT is burned by transferring it to the zero address
+        if owner == utils::ZERO_ADDRESS {
+            row.set("closed", true);
+        }
+        row.set("owner", &owner);
+    }
+}
+
+pub fn uncollected_fees_position_entity_change(tables: &mut Tables, uncollected_fees_deltas: &Deltas<DeltaBigDecimal>) {
+    for delta in uncollected_fees_deltas
+        .deltas
+        .iter()
+        .filter(key_first_segment_in("position"))
+    {
+        let token_id = key::segment(&delta.key, 1);
+        let field_name = key::last_segment(&delta.key);
+        tables
+            .update_row("Position", token_id)
+            .set(field_name, &delta.new_value);
+    }
+}
+
+pub fn amounts_position_entity_change(tables: &mut Tables, positions_amounts_deltas: &Deltas<DeltaBigDecimal>) {
+    for delta in positions_amounts_deltas
+        .deltas
+        .iter()
+        .filter(key_first_segment_in("position"))
+    {
+        let token_id = key::segment(&delta.key, 1);
+        let field_name = key::last_segment(&delta.key);
+        tables
+            .update_row("Position", token_id)
+            .set(field_name, &delta.new_value);
+    }
+}
+
+pub fn accounting_position_entity_change(tables: &mut Tables, positions_accounting_deltas: &Deltas<DeltaBigDecimal>) {
+    for delta in positions_accounting_deltas
+        .deltas
+        .iter()
+        .filter(key_last_segments_in(vec![
+            "depositedUSD",
+            "withdrawnUSD",
+            "collectedUSD",
+        ]))
+    {
+        let token_id = key::segment(&delta.key, 1);
+        let field_name = key::last_segment(&delta.key);
+        tables
+            .update_row("Position", token_id)
+            .set(field_name, &delta.new_value);
+    }
+}
+
+// --------------------
+//  Map PoolPosition Entities
+// --------------------
+pub fn pool_positions_create_entity_change(
+    tables: &mut Tables,
+    positions: &Vec<events::PoolPositionUpdated>,
+    pool_position_updates_store: &StoreGetInt64,
+) {
+    for position in positions {
+        let key = format!("poolPosition:{}:{}", position.pool_address, position.position_key);
+        if !pool_position_updates_store
+            .get_at(position.log_ordinal, key)
+            .unwrap_or(0)
+            .eq(&1)
+        {
+            continue;
+        }
+
+        let bigint0 = BigInt::zero();
+        tables
+            .create_row(
+                "PoolPosition",
+                format!("0x{}#0x{}", position.pool_address, position.position_key),
+            )
+            .set("owner", &hex::decode(&position.owner).unwrap())
+            .set("pool", format!("0x{}", position.pool_address))
+            .set(
+                "tickLower",
+                format!("0x{}#{}", position.pool_address, position.tick_lower),
+            )
+            .set(
+                "tickUpper",
+                format!("0x{}#{}", position.pool_address, position.tick_upper),
+            )
+            .set("liquidity", &bigint0)
+            .set("feeGrowthInside0LastX128", &bigint0)
+            .set("feeGrowthInside1LastX128", &bigint0)
+            .set("tokensOwed0", &bigint0)
+            .set("tokensOwed1", &bigint0)
+            .set("transaction", format!("0x{}", position.transaction_id));
+    }
+}
+
+pub fn pool_position_states_entity_change(tables: &mut Tables, pool_position_states_deltas: &Deltas<DeltaBigInt>) {
+    for delta in pool_position_states_deltas
+        .deltas
+        .iter()
+        .filter(key_first_segment_in("poolPosition"))
+        .filter(operations_ne(Operation::Delete))
+    {
+        let pool_address = key::segment(&delta.key, 1);
+        let position_key = key::segment(&delta.key, 2);
+        let field_name = key::last_segment(&delta.key);
         tables
-            .update_row("Position", position.token_id.clone())
-            .set("owner", &hex::decode(&position.owner).unwrap());
+            .update_row("PoolPosition", format!("0x{pool_address}#0x{position_key}"))
+            .set(field_name, &delta.new_value);
     }
 }
 
@@ -823,6 +1233,9 @@ fn create_snapshot_position(tables: &mut Tables, id: &String, position: &events:
         .set_bigdecimal("withdrawnToken1", &"0".to_string())
         .set_bigdecimal("collectedFeesToken0", &"0".to_string())
         .set_bigdecimal("collectedFeesToken1", &"0".to_string())
+        .set_bigdecimal("realizedPnlUSD", &"0".to_string())
+        .set_bigdecimal("unrealizedPnlUSD", &"0".to_string())
+        .set_bigdecimal("impermanentLossUSD", &"0".to_string())
         .set("transaction", &format!("0x{}", &position.transaction))
         .set_bigint(
             "feeGrowthInside0LastX128",
@@ -955,6 +1368,36 @@ fn transfer_snapshot_position(tables: &mut Tables, id: &String, position: &event
         .set("owner", &hex::decode(&position.owner).unwrap());
 }
 
+// PnL of the positions as of the snapshots taken in the block
+pub fn pnl_snapshot_position_entity_change(
+    tables: &mut Tables,
+    block_number: u64,
+    events: &Events,
+    positions_amounts_deltas: &Deltas<DeltaBigDecimal>,
+) {
+    let mut snapshot_token_ids: HashSet<&str> = HashSet::new();
+    snapshot_token_ids.extend(events.created_positions.iter().map(|p| p.token_id.as_str()));
+    snapshot_token_ids.extend(events.increase_liquidity_positions.iter().map(|p| p.token_id.as_str()));
+    snapshot_token_ids.extend(events.decrease_liquidity_positions.iter().map(|p| p.token_id.as_str()));
+    snapshot_token_ids.extend(events.collect_positions.iter().map(|p| p.token_id.as_str()));
+    snapshot_token_ids.extend(events.transfer_positions.iter().map(|p| p.token_id.as_str()));
+
+    for delta in positions_amounts_deltas.deltas.iter().filter(key_last_segments_in(vec![
+        "realizedPnlUSD",
+        "unrealizedPnlUSD",
+        "impermanentLossUSD",
+    ])) {
+        let token_id = key::segment(&delta.key, 1);
+        if !snapshot_token_ids.contains(token_id) {
+            continue;
+        }
+        let field_name = key::last_segment(&delta.key);
+        tables
+            .update_row("PositionSnapshot", format!("{token_id}#{block_number}"))
+            .set(field_name, &delta.new_value);
+    }
+}
+
 fn fetch_and_update_snapshot_position(
     tables: &mut Tables,
     token_id: &String,
@@ -1017,13 +1460,14 @@ pub fn transaction_entity_change(tables: &mut Tables, transactions: &Vec<events:
 }
 
 // --------------------
-//  Map Swaps Mints Burns Entities
+//  Map Swaps Mints Burns Collects Entities
 // --------------------
 pub fn swaps_mints_burns_created_entity_change(
     tables: &mut Tables,
     pool_events: &Vec<events::PoolEvent>,
-    tx_count_store: StoreGetBigInt,
-    store_eth_prices: StoreGetBigDecimal,
+    tx_count_store: &StoreGetBigInt,
+    store_eth_prices: &StoreGetBigDecimal,
+    config: &Config,
 ) {
     for pool_event in pool_events {
         if pool_event.r#type.is_none() {
@@ -1071,6 +1515,7 @@ pub fn swaps_mints_burns_created_entity_change(
                         &amount0_abs,
                         &amount1_abs,
                         &bundle_eth_price, // get the value from the store_eth_price
+                        config,
                     )
                     .div(BigDecimal::from(2 as i32));
 
@@ -1089,6 +1534,7 @@ pub fn swaps_mints_burns_created_entity_change(
                         .set("amountUSD", &amount_total_usd_tracked)
                         .set("sqrtPriceX96", &BigInt::try_from(swap.sqrt_price.to_string()).unwrap())
                         .set("tick", &BigInt::try_from(swap.tick.to_string()).unwrap())
+                        .set("ticksCrossed", BigInt::from(swap.ticks_crossed))
                         .set("logIndex", pool_event.log_index);
                 }
                 MintEvent(mint) => {
@@ -1149,6 +1595,30 @@ pub fn swaps_mints_burns_created_entity_change(
                         .set_bigint("tickUpper", &burn.tick_upper)
                         .set("logIndex", pool_event.log_index);
                 }
+                CollectEvent(collect) => {
+                    let amount0: BigDecimal = BigDecimal::try_from(collect.amount_0.as_str()).unwrap();
+                    let amount1: BigDecimal = BigDecimal::try_from(collect.amount_1.as_str()).unwrap();
+
+                    let amount_usd: BigDecimal = utils::calculate_amount_usd(
+                        &amount0,
+                        &amount1,
+                        &token0_derived_eth_price,
+                        &token1_derived_eth_price,
+                        &bundle_eth_price,
+                    );
+                    tables
+                        .create_row("Collect", &event_primary_key)
+                        .set("transaction", format!("0x{transaction_id}"))
+                        .set("pool", format!("0x{pool_address}"))
+                        .set("timestamp", pool_event.timestamp)
+                        .set("owner", &hex::decode(&collect.owner).unwrap())
+                        .set("amount0", amount0)
+                        .set("amount1", amount1)
+                        .set("amountUSD", amount_usd)
+                        .set_bigint("tickLower", &collect.tick_lower)
+                        .set_bigint("tickUpper", &collect.tick_upper)
+                        .set("logIndex", pool_event.log_index);
+                }
             };
         }
     }
@@ -1157,21 +1627,51 @@ pub fn swaps_mints_burns_created_entity_change(
 // --------------------
 //  Map Flashes Entities
 // --------------------
-// pub fn flashes_update_pool_fee_entity_change(tables: &mut Tables, flashes: Vec<events::Flash>) {
-//     // TODO: wut? flash updates would affect `fee_growth_global_0x_128` and `fee_growth_global_1x_128`?
-//     //  it's the business of TickUpdate, not of Flashes. Don't flashes produce some such updates?
-//     for flash in flashes {
-//         // tables.update_row("Pool", flash.pool_address.as_str());
-//         // .set(
-//         //     "feeGrowthGlobal0X128",
-//         //     BigInt::from(flash.fee_growth_global_0x_128.unwrap()),
-//         // )
-//         // .set(
-//         //     "feeGrowthGlobal1X128",
-//         //     BigInt::from(flash.fee_growth_global_1x_128.unwrap()),
-//         // );
-//     }
-// }
+pub fn flashes_created_entity_change(
+    tables: &mut Tables,
+    flashes: &Vec<events::Flash>,
+    store_eth_prices: &StoreGetBigDecimal,
+) {
+    for flash in flashes {
+        let ord = flash.log_ordinal;
+        let token0_addr = &flash.token0;
+        let token1_addr = &flash.token1;
+        let transaction_id = &flash.transaction_id;
+
+        let token0_derived_eth_price = store_eth_prices
+            .get_at(ord, format!("token:{token0_addr}:dprice:eth"))
+            .unwrap_or_default();
+        let token1_derived_eth_price = store_eth_prices
+            .get_at(ord, format!("token:{token1_addr}:dprice:eth"))
+            .unwrap_or_default();
+        let bundle_eth_price = store_eth_prices.get_at(ord, "bundle").unwrap_or_default();
+
+        let amount0 = BigDecimal::try_from(flash.amount_0.as_str()).unwrap();
+        let amount1 = BigDecimal::try_from(flash.amount_1.as_str()).unwrap();
+
+        let amount_usd: BigDecimal = utils::calculate_amount_usd(
+            &amount0,
+            &amount1,
+            &token0_derived_eth_price,
+            &token1_derived_eth_price,
+            &bundle_eth_price,
+        );
+
+        tables
+            .create_row("Flash", format!("0x{transaction_id}#{}", flash.log_index))
+            .set("transaction", format!("0x{transaction_id}"))
+            .set("timestamp", flash.timestamp)
+            .set("pool", format!("0x{}", flash.pool_address))
+            .set("sender", &hex::decode(&flash.sender).unwrap())
+            .set("recipient", &hex::decode(&flash.recipient).unwrap())
+            .set("amount0", amount0)
+            .set("amount1", amount1)
+            .set("amountUSD", amount_usd)
+            .set_bigdecimal("amount0Paid", &flash.paid_0)
+            .set_bigdecimal("amount1Paid", &flash.paid_1)
+            .set("logIndex", flash.log_index);
+    }
+}
 
 // --------------------
 //  Map Uniswap Day Data Entities
@@ -1312,6 +1812,7 @@ pub fn pool_windows_update(
     derived_tvl_deltas: &Deltas<DeltaBigDecimal>,
     min_windows_deltas: &Deltas<DeltaBigDecimal>,
     max_windows_deltas: &Deltas<DeltaBigDecimal>,
+    pool_yields_deltas: &Deltas<DeltaBigDecimal>,
 ) {
     tx_count_pool_windows(&mut tables, &tx_count_deltas);
     mint_burn_prices_pool_windows(&mut tables, timestamp, &events.pool_events, &store_prices);
@@ -1324,6 +1825,7 @@ pub fn pool_windows_update(
     swap_volume_pool_windows(&mut tables, &swaps_volume_deltas);
     fee_growth_global_x128_pool_windows(&mut tables, timestamp, &events.fee_growth_global_updates);
     total_value_locked_usd_pool_windows(&mut tables, &derived_tvl_deltas);
+    yields_pool_windows(&mut tables, &pool_yields_deltas);
 }
 
 pub fn tx_count_pool_windows(tables: &mut Tables, tx_count_deltas: &Deltas<DeltaBigInt>) {
@@ -1386,6 +1888,9 @@ pub fn mint_burn_prices_pool_windows(
                 events::pool_event::Type::Swap(_) => {
                     continue; // the swap event will be taken care of by the prices_pool_windows
                 }
+                events::pool_event::Type::Collect(_) => {
+                    continue; // collecting doesn't move the pool price
+                }
                 _ => {}
             }
 
@@ -1613,6 +2118,21 @@ pub fn total_value_locked_usd_pool_windows(tables: &mut Tables, derived_tvl_delt
     }
 }
 
+pub fn yields_pool_windows(tables: &mut Tables, pool_yields_deltas: &Deltas<DeltaBigDecimal>) {
+    for delta in pool_yields_deltas
+        .deltas
+        .iter()
+        .filter(key_first_segments_in(vec!["PoolDayData", "PoolHourData"]))
+        .filter(operations_ne(Operation::Delete))
+    {
+        let (table_name, time_id, pool_address) = key::pool_windows_id_fields(&delta.key);
+
+        tables
+            .update_row(table_name, format!("0x{pool_address}-{time_id}"))
+            .set(key::last_segment(&delta.key), &delta.new_value);
+    }
+}
+
 // ---------------------------------
 //  Map Token Day/Hour Data Entities
 // ---------------------------------
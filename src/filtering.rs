@@ -1,10 +1,11 @@
+use crate::config::Config;
 use crate::math::compute_price_from_tick_idx;
-use crate::pb::uniswap::events;
+use crate::pb::uniswap::{events, OracleObservation, OracleState};
 use crate::storage::position_manager::PositionManagerStorage;
-use crate::storage::uniswap_v3_pool::UniswapPoolStorage;
-use crate::utils::NON_FUNGIBLE_POSITION_MANAGER;
-use crate::{abi, math, utils, BurnEvent, EventTrait, MintEvent, Pool, SwapEvent};
+use crate::storage::uniswap_v3_pool::{position_key, UniswapPoolStorage};
+use crate::{abi, math, utils, BurnEvent, CollectEvent, EventTrait, MintEvent, Pool, SwapEvent};
 use substreams::prelude::{BigDecimal, BigInt};
+use substreams::store::{StoreGet, StoreGetBigInt, StoreGetProto};
 use substreams::{log, Hex};
 use substreams_ethereum::block_view::CallView;
 use substreams_ethereum::pb::eth::v2::{Call, Log, StorageChange, TransactionTrace};
@@ -13,18 +14,18 @@ pub fn extract_pool_events_and_positions(
     pool_events: &mut Vec<events::PoolEvent>,
     ticks_created: &mut Vec<events::TickCreated>,
     ticks_updated: &mut Vec<events::TickUpdated>,
+    ticks_crossed: &mut Vec<events::TickCrossed>,
+    ticks_cleared: &mut Vec<events::TickCleared>,
     created_positions: &mut Vec<events::CreatedPosition>,
-    increase_liquidity_positions: &mut Vec<events::IncreaseLiquidityPosition>,
-    decrease_liquidity_positions: &mut Vec<events::DecreaseLiquidityPosition>,
-    collect_positions: &mut Vec<events::CollectPosition>,
-    transfer_positions: &mut Vec<events::TransferPosition>,
     transaction_id: &String,
     origin: &String,
     log: &Log,
     call_view: &CallView,
     pool: &Pool,
+    ticks_liquidity_net_store: &StoreGetBigInt,
     timestamp_seconds: u64,
     block_number: u64,
+    config: &Config,
 ) {
     let common_tick_updated = events::TickUpdated {
         log_ordinal: log.ordinal,
@@ -35,7 +36,7 @@ pub fn extract_pool_events_and_positions(
 
     if let Some(swap) = abi::pool::events::Swap::match_and_decode(log) {
         log::info!("SWAP: transaction: {}", transaction_id.to_string());
-        if !pool.should_handle_swap() {
+        if !pool.should_handle_swap(config) {
             return;
         }
 
@@ -47,6 +48,43 @@ pub fn extract_pool_events_and_positions(
         let amount0 = swap.amount0.to_decimal(token0.decimals);
         let amount1 = swap.amount1.to_decimal(token1.decimals);
 
+        let storage =
+            UniswapPoolStorage::with_layout(&call_view.call.storage_changes, &log.address, pool.storage_layout());
+        let (crossed_ticks, zero_for_one) = match storage.slot0().tick() {
+            Some((tick_before, tick_after)) => (
+                storage.crossed_ticks(tick_before.to_i32(), tick_after.to_i32(), pool.tick_spacing),
+                tick_after < tick_before,
+            ),
+            None => (vec![], false),
+        };
+
+        for crossed_tick in &crossed_ticks {
+            let tick_idx = BigInt::from(*crossed_tick);
+            let tick = storage.ticks(&tick_idx);
+            let liquidity_net = ticks_liquidity_net_store
+                .get_at(log.ordinal, format!("tick:{}:{tick_idx}:liquidityNet", pool.address))
+                .unwrap_or(BigInt::zero());
+            ticks_crossed.push(events::TickCrossed {
+                pool_address: pool.address.to_string(),
+                transaction_id: transaction_id.to_string(),
+                idx: tick_idx.to_string(),
+                zero_for_one,
+                liquidity_delta: if zero_for_one {
+                    liquidity_net.neg().to_string()
+                } else {
+                    liquidity_net.to_string()
+                },
+                fee_growth_outside_0x_128: bigint_if_some(tick.fee_growth_outside_0_x128()),
+                fee_growth_outside_1x_128: bigint_if_some(tick.fee_growth_outside_1_x128()),
+                tick_cumulative_outside: bigint_if_some(tick.tick_cumulative_outside()),
+                seconds_per_liquidity_outside_x128: bigint_if_some(tick.seconds_per_liquidity_outside_x128()),
+                seconds_outside: bigint_if_some(tick.seconds_outside()),
+                log_ordinal: log.ordinal,
+                timestamp: timestamp_seconds,
+                ..Default::default()
+            });
+        }
+
         pool_events.push(events::PoolEvent {
             log_ordinal: log.ordinal,
             log_index: log.block_index as u64,
@@ -66,23 +104,12 @@ pub fn extract_pool_events_and_positions(
                 sqrt_price: swap.sqrt_price_x96.into(),
                 liquidity: swap.liquidity.into(),
                 tick: swap.tick.into(),
+                ticks_crossed: crossed_ticks.len() as u32,
             })),
         });
-
-        //TODO: verify if a swap changes the fee growth inside 0x128 and 1x128
-        if let Some(position_manager_contract_call) = call_view.parent() {
-            extract_positions(
-                pool,
-                increase_liquidity_positions,
-                decrease_liquidity_positions,
-                collect_positions,
-                transfer_positions,
-                &position_manager_contract_call,
-            );
-        }
     } else if let Some(mint) = abi::pool::events::Mint::match_and_decode(log) {
         log::info!("MINT: transaction: {}", transaction_id.to_string());
-        if !pool.should_handle_mint_and_burn() {
+        if !pool.should_handle_mint_and_burn(config) {
             return;
         }
 
@@ -122,37 +149,28 @@ pub fn extract_pool_events_and_positions(
             ..Default::default()
         };
 
-        let mut lower_tick = common_tick.clone();
-        let (price0, price1) = prices_from_tick_index(mint.tick_lower.to_i32());
-        lower_tick.idx = mint.tick_lower.as_ref().into();
-        lower_tick.price0 = price0.into();
-        lower_tick.price1 = price1.into();
-        ticks_created.push(lower_tick);
-
-        let mut upper_tick = common_tick.clone();
-        let (price0, price1) = prices_from_tick_index(mint.tick_upper.to_i32());
-        upper_tick.idx = mint.tick_upper.as_ref().into();
-        upper_tick.price0 = price0.into();
-        upper_tick.price1 = price1.into();
-        ticks_created.push(upper_tick);
-
-        let storage = UniswapPoolStorage::new(&call_view.call.storage_changes, &log.address);
-
-        ticks_updated.push(events::TickUpdated {
-            idx: mint.tick_upper.as_ref().into(),
-            fee_growth_outside_0x_128: bigint_if_some(storage.ticks(&mint.tick_upper).fee_growth_outside_0_x128()),
-            fee_growth_outside_1x_128: bigint_if_some(storage.ticks(&mint.tick_upper).fee_growth_outside_1_x128()),
-            ..common_tick_updated.clone()
-        });
-        ticks_updated.push(events::TickUpdated {
-            idx: mint.tick_lower.as_ref().into(),
-            fee_growth_outside_0x_128: bigint_if_some(storage.ticks(&mint.tick_lower).fee_growth_outside_0_x128()),
-            fee_growth_outside_1x_128: bigint_if_some(storage.ticks(&mint.tick_lower).fee_growth_outside_1_x128()),
-            ..common_tick_updated.clone()
-        });
+        let storage =
+            UniswapPoolStorage::with_layout(&call_view.call.storage_changes, &log.address, pool.storage_layout());
+
+        // a tick is only created when the mint initializes it, minting onto an initialized tick keeps
+        // its outside values and volumes
+        for tick_idx in [&mint.tick_lower, &mint.tick_upper] {
+            if storage.ticks(tick_idx).initialized() != Some((false, true)) {
+                continue;
+            }
+            let mut tick = common_tick.clone();
+            let (price0, price1) = prices_from_tick_index(tick_idx.to_i32());
+            tick.idx = tick_idx.as_ref().into();
+            tick.price0 = price0.into();
+            tick.price1 = price1.into();
+            ticks_created.push(tick);
+        }
+
+        ticks_updated.push(tick_updated(&storage, &mint.tick_upper, &common_tick_updated));
+        ticks_updated.push(tick_updated(&storage, &mint.tick_lower, &common_tick_updated));
 
         if let Some(position_manager_contract_call) = call_view.parent() {
-            if position_manager_contract_call.address != NON_FUNGIBLE_POSITION_MANAGER {
+            if position_manager_contract_call.address != config.position_manager {
                 return;
             }
 
@@ -196,19 +214,10 @@ pub fn extract_pool_events_and_positions(
                     fee_growth_inside1_last_x128,
                 });
             }
-
-            extract_positions(
-                pool,
-                increase_liquidity_positions,
-                decrease_liquidity_positions,
-                collect_positions,
-                transfer_positions,
-                &position_manager_contract_call,
-            );
         }
     } else if let Some(burn) = abi::pool::events::Burn::match_and_decode(log) {
         log::info!("BURN: transaction: {}", transaction_id.to_string());
-        if !pool.should_handle_mint_and_burn() {
+        if !pool.should_handle_mint_and_burn(config) {
             return;
         }
 
@@ -241,43 +250,107 @@ pub fn extract_pool_events_and_positions(
             })),
         });
 
-        let storage = UniswapPoolStorage::new(&call_view.call.storage_changes, &log.address);
+        let storage =
+            UniswapPoolStorage::with_layout(&call_view.call.storage_changes, &log.address, pool.storage_layout());
 
-        ticks_updated.push(events::TickUpdated {
-            idx: burn.tick_upper.as_ref().into(),
-            fee_growth_outside_0x_128: bigint_if_some(storage.ticks(&burn.tick_upper).fee_growth_outside_0_x128()),
-            fee_growth_outside_1x_128: bigint_if_some(storage.ticks(&burn.tick_upper).fee_growth_outside_1_x128()),
-            ..common_tick_updated.clone()
-        });
-        ticks_updated.push(events::TickUpdated {
-            idx: burn.tick_lower.as_ref().into(),
-            fee_growth_outside_0x_128: bigint_if_some(storage.ticks(&burn.tick_lower).fee_growth_outside_0_x128()),
-            fee_growth_outside_1x_128: bigint_if_some(storage.ticks(&burn.tick_lower).fee_growth_outside_1_x128()),
-            ..common_tick_updated.clone()
+        ticks_updated.push(tick_updated(&storage, &burn.tick_upper, &common_tick_updated));
+        ticks_updated.push(tick_updated(&storage, &burn.tick_lower, &common_tick_updated));
+
+        // a tick no longer referenced by any position gets deleted from the `ticks` mapping
+        for tick_idx in [&burn.tick_upper, &burn.tick_lower] {
+            if storage.ticks(tick_idx).initialized() == Some((true, false)) {
+                ticks_cleared.push(events::TickCleared {
+                    pool_address: pool.address.to_string(),
+                    transaction_id: transaction_id.to_string(),
+                    idx: tick_idx.into(),
+                    log_ordinal: log.ordinal,
+                    timestamp: timestamp_seconds,
+                });
+            }
+        }
+    } else if let Some(collect) = abi::pool::events::Collect::match_and_decode(log) {
+        log::info!("COLLECT: transaction: {}", transaction_id.to_string());
+        if !pool.should_handle_mint_and_burn(config) {
+            return;
+        }
+
+        let token0 = pool.token0.as_ref().unwrap();
+        let token1 = pool.token1.as_ref().unwrap();
+
+        let amount0 = collect.amount0.to_decimal(token0.decimals);
+        let amount1 = collect.amount1.to_decimal(token1.decimals);
+
+        pool_events.push(events::PoolEvent {
+            log_ordinal: log.ordinal,
+            log_index: log.block_index as u64,
+            pool_address: pool.address.to_string(),
+            token0: token0.address.clone(),
+            token1: token1.address.clone(),
+            fee: pool.fee_tier.clone(),
+            transaction_id: transaction_id.to_string(),
+            timestamp: timestamp_seconds,
+            created_at_block_number: block_number,
+            r#type: Some(CollectEvent(events::pool_event::Collect {
+                owner: Hex(&collect.owner).to_string(),
+                recipient: Hex(&collect.recipient).to_string(),
+                amount_0: amount0.into(),
+                amount_1: amount1.into(),
+                tick_lower: collect.tick_lower.as_ref().into(),
+                tick_upper: collect.tick_upper.as_ref().into(),
+            })),
         });
+    }
+}
 
-        if let Some(position_manager_contract_call) = call_view.parent() {
-            extract_positions(
-                pool,
-                increase_liquidity_positions,
-                decrease_liquidity_positions,
-                collect_positions,
-                transfer_positions,
-                &position_manager_contract_call,
-            );
+// on-chain state of the tick after the call, fields which did not change are left empty
+// liquidityNet of the ticks of a mint or burn after the log, None when the burn cleared the tick
+pub fn extract_ticks_liquidity_net(
+    log: &Log,
+    storage_changes: &Vec<StorageChange>,
+    pool: &Pool,
+) -> Vec<(BigInt, Option<BigInt>)> {
+    let (tick_lower, tick_upper) = if let Some(mint) = abi::pool::events::Mint::match_and_decode(log) {
+        (mint.tick_lower, mint.tick_upper)
+    } else if let Some(burn) = abi::pool::events::Burn::match_and_decode(log) {
+        (burn.tick_lower, burn.tick_upper)
+    } else {
+        return vec![];
+    };
+
+    let storage = UniswapPoolStorage::with_layout(storage_changes, &log.address, pool.storage_layout());
+    let mut ticks = vec![];
+    for tick_idx in [tick_lower, tick_upper] {
+        let tick = storage.ticks(&tick_idx);
+        if tick.initialized() == Some((true, false)) {
+            ticks.push((tick_idx, None));
+        } else if let Some((_, liquidity_net)) = tick.liquidity_net() {
+            ticks.push((tick_idx, Some(liquidity_net)));
         }
-    } else if let Some(_collect) = abi::pool::events::Collect::match_and_decode(log) {
-        if let Some(position_manager_contract_call) = call_view.parent() {
-            extract_positions(
-                pool,
-                increase_liquidity_positions,
-                decrease_liquidity_positions,
-                collect_positions,
-                transfer_positions,
-                &position_manager_contract_call,
-            );
-        };
     }
+    ticks
+}
+
+fn tick_updated(
+    storage: &UniswapPoolStorage,
+    tick_idx: &BigInt,
+    common_tick_updated: &events::TickUpdated,
+) -> events::TickUpdated {
+    let tick = storage.ticks(tick_idx);
+    events::TickUpdated {
+        idx: tick_idx.into(),
+        fee_growth_outside_0x_128: bigint_if_some(tick.fee_growth_outside_0_x128()),
+        fee_growth_outside_1x_128: bigint_if_some(tick.fee_growth_outside_1_x128()),
+        liquidity_gross: bigint_if_some(tick.liquidity_gross()),
+        liquidity_net: bigint_if_some(tick.liquidity_net()),
+        tick_cumulative_outside: bigint_if_some(tick.tick_cumulative_outside()),
+        seconds_per_liquidity_outside_x128: bigint_if_some(tick.seconds_per_liquidity_outside_x128()),
+        seconds_outside: bigint_if_some(tick.seconds_outside()),
+        ..common_tick_updated.clone()
+    }
+}
+
+fn new_value_if_some(input: Option<(BigInt, BigInt)>) -> Option<String> {
+    input.map(|(_, new_value)| new_value.to_string())
 }
 
 fn bigint_if_some(input: Option<(BigInt, BigInt)>) -> String {
@@ -299,6 +372,7 @@ pub fn extract_pool_liquidities(
     log: &Log,
     storage_changes: &Vec<StorageChange>,
     pool: &Pool,
+    config: &Config,
 ) {
     let pool_address = &pool.address;
     let token0 = &pool.token0().address;
@@ -307,10 +381,10 @@ pub fn extract_pool_liquidities(
         return;
     }
 
-    let storage = UniswapPoolStorage::new(storage_changes, &log.address);
+    let storage = UniswapPoolStorage::with_layout(storage_changes, &log.address, pool.storage_layout());
 
     if let Some(_) = abi::pool::events::Swap::match_and_decode(&log) {
-        if !pool.should_handle_swap() {
+        if !pool.should_handle_swap(config) {
             return;
         }
         let value = bigint_if_some(storage.liquidity());
@@ -325,7 +399,7 @@ pub fn extract_pool_liquidities(
             });
         }
     } else if let Some(_) = abi::pool::events::Mint::match_and_decode(&log) {
-        if !pool.should_handle_mint_and_burn() {
+        if !pool.should_handle_mint_and_burn(config) {
             return;
         }
         let value = bigint_if_some(storage.liquidity());
@@ -340,7 +414,7 @@ pub fn extract_pool_liquidities(
             });
         }
     } else if let Some(_) = abi::pool::events::Burn::match_and_decode(&log) {
-        if !pool.should_handle_mint_and_burn() {
+        if !pool.should_handle_mint_and_burn(config) {
             return;
         }
         let value = bigint_if_some(storage.liquidity());
@@ -362,35 +436,293 @@ pub fn extract_fee_growth_update(
     log: &Log,
     storage_changes: &Vec<StorageChange>,
     pool: &Pool,
+    config: &Config,
 ) {
     let mut do_extract = false;
     if let Some(_) = abi::pool::events::Swap::match_and_decode(&log) {
-        if !pool.should_handle_swap() {
+        if !pool.should_handle_swap(config) {
             return;
         }
         do_extract = true;
     } else if let Some(_) = abi::pool::events::Mint::match_and_decode(&log) {
-        if !pool.should_handle_mint_and_burn() {
+        if !pool.should_handle_mint_and_burn(config) {
             return;
         }
         do_extract = true
     } else if let Some(_) = abi::pool::events::Burn::match_and_decode(&log) {
-        if !pool.should_handle_mint_and_burn() {
+        if !pool.should_handle_mint_and_burn(config) {
             return;
         }
         do_extract = true;
     } else if let Some(_) = abi::pool::events::Flash::match_and_decode(&log) {
-        do_extract = false; //TODO: handle this later when we process flashes
+        do_extract = true;
     }
     if do_extract {
         fee_growth_updates.append(&mut utils::extract_pool_fee_growth_global_updates(
             log.ordinal,
             &log.address,
             storage_changes,
+            pool.storage_layout(),
         ));
     }
 }
 
+// The feeGrowthOutside of a tick is written on mints and burns referencing it and when a swap crosses it.
+// The fee growth inside of the range touched by a mint or burn is the one the pool computes from its
+// feeGrowthGlobal, the feeGrowthOutside of the range's ticks and the current tick, and writes in the position.
+// The ranges left untouched are covered by map_fee_growth_inside.
+pub fn extract_fee_growth_inside_and_outside(
+    fee_growth_inside_updates: &mut Vec<events::FeeGrowthInside>,
+    fee_growth_outside_updates: &mut Vec<events::FeeGrowthOutside>,
+    log: &Log,
+    storage_changes: &Vec<StorageChange>,
+    pool: &Pool,
+    config: &Config,
+) {
+    let storage = UniswapPoolStorage::with_layout(storage_changes, &log.address, pool.storage_layout());
+
+    let mut ranges: Vec<(Vec<u8>, i32, i32)> = vec![];
+    let mut ticks: Vec<i32> = vec![];
+    if let Some(swap) = abi::pool::events::Swap::match_and_decode(log) {
+        if !pool.should_handle_swap(config) {
+            return;
+        }
+        if let Some((tick_before, _)) = storage.slot0().tick() {
+            ticks = storage.crossed_ticks(tick_before.to_i32(), swap.tick.to_i32(), pool.tick_spacing);
+        }
+    } else if let Some(mint) = abi::pool::events::Mint::match_and_decode(log) {
+        if !pool.should_handle_mint_and_burn(config) {
+            return;
+        }
+        ranges.push((mint.owner, mint.tick_lower.to_i32(), mint.tick_upper.to_i32()));
+        ticks = vec![mint.tick_lower.to_i32(), mint.tick_upper.to_i32()];
+    } else if let Some(burn) = abi::pool::events::Burn::match_and_decode(log) {
+        if !pool.should_handle_mint_and_burn(config) {
+            return;
+        }
+        ranges.push((burn.owner, burn.tick_lower.to_i32(), burn.tick_upper.to_i32()));
+        ticks = vec![burn.tick_lower.to_i32(), burn.tick_upper.to_i32()];
+    }
+
+    for (owner, tick_lower, tick_upper) in ranges {
+        let position = storage.positions(&owner, tick_lower, tick_upper);
+        let fee_growths = [
+            position.fee_growth_inside_0_last_x128(),
+            position.fee_growth_inside_1_last_x128(),
+        ];
+        for (token_idx, fee_growth) in fee_growths.into_iter().enumerate() {
+            if let Some((_, new_value)) = fee_growth {
+                fee_growth_inside_updates.push(events::FeeGrowthInside {
+                    pool_address: pool.address.to_string(),
+                    tick_lower,
+                    tick_upper,
+                    ordinal: log.ordinal,
+                    new_value: new_value.into(),
+                    token_idx: token_idx as i32,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    for tick_idx in ticks {
+        let tick = storage.ticks(&BigInt::from(tick_idx));
+        let fee_growths = [tick.fee_growth_outside_0_x128(), tick.fee_growth_outside_1_x128()];
+        for (token_idx, fee_growth) in fee_growths.into_iter().enumerate() {
+            if let Some((_, new_value)) = fee_growth {
+                fee_growth_outside_updates.push(events::FeeGrowthOutside {
+                    pool_address: pool.address.to_string(),
+                    tick_idx,
+                    ordinal: log.ordinal,
+                    new_value: new_value.into(),
+                    token_idx: token_idx as i32,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+}
+
+// positions minted directly on the pool, e.g. by vaults or other liquidity managers, the ones owned
+// by the NonfungiblePositionManager are tracked per token id through the `CreatedPosition` events
+pub fn extract_pool_positions(
+    pool_positions_updated: &mut Vec<events::PoolPositionUpdated>,
+    log: &Log,
+    storage_changes: &Vec<StorageChange>,
+    pool: &Pool,
+    transaction_id: &String,
+    timestamp_seconds: u64,
+    config: &Config,
+) {
+    let (owner, tick_lower, tick_upper) = if let Some(mint) = abi::pool::events::Mint::match_and_decode(log) {
+        if !pool.should_handle_mint_and_burn(config) {
+            return;
+        }
+        (mint.owner, mint.tick_lower.to_i32(), mint.tick_upper.to_i32())
+    } else if let Some(burn) = abi::pool::events::Burn::match_and_decode(log) {
+        if !pool.should_handle_mint_and_burn(config) {
+            return;
+        }
+        (burn.owner, burn.tick_lower.to_i32(), burn.tick_upper.to_i32())
+    } else if let Some(collect) = abi::pool::events::Collect::match_and_decode(log) {
+        (collect.owner, collect.tick_lower.to_i32(), collect.tick_upper.to_i32())
+    } else {
+        return;
+    };
+
+    if owner == config.position_manager {
+        return;
+    }
+
+    let storage = UniswapPoolStorage::with_layout(storage_changes, &log.address, pool.storage_layout());
+    let position = storage.positions(&owner, tick_lower, tick_upper);
+
+    pool_positions_updated.push(events::PoolPositionUpdated {
+        pool_address: pool.address.to_string(),
+        owner: Hex(&owner).to_string(),
+        tick_lower: tick_lower.to_string(),
+        tick_upper: tick_upper.to_string(),
+        position_key: Hex(position_key(&owner, tick_lower, tick_upper)).to_string(),
+        transaction_id: transaction_id.to_string(),
+        log_ordinal: log.ordinal,
+        timestamp: timestamp_seconds,
+        liquidity: new_value_if_some(position.liquidity()),
+        fee_growth_inside0_last_x128: new_value_if_some(position.fee_growth_inside_0_last_x128()),
+        fee_growth_inside1_last_x128: new_value_if_some(position.fee_growth_inside_1_last_x128()),
+        tokens_owed0: new_value_if_some(position.tokens_owed0()),
+        tokens_owed1: new_value_if_some(position.tokens_owed1()),
+    });
+}
+
+pub fn extract_fee_protocols(fee_protocols: &mut Vec<events::FeeProtocol>, log: &Log, pool: &Pool) {
+    if let Some(event) = abi::pool::events::SetFeeProtocol::match_and_decode(log) {
+        log::info!("SET FEE PROTOCOL: pool: {}", pool.address);
+        fee_protocols.push(events::FeeProtocol {
+            pool_address: pool.address.to_string(),
+            fee_protocol_0: event.fee_protocol0_new.to_u64() as u32,
+            fee_protocol_1: event.fee_protocol1_new.to_u64() as u32,
+            log_ordinal: log.ordinal,
+        });
+    }
+}
+
+pub fn extract_protocol_fees(
+    protocol_fees: &mut Vec<events::ProtocolFees>,
+    log: &Log,
+    storage_changes: &Vec<StorageChange>,
+    pool: &Pool,
+    config: &Config,
+) {
+    // the protocol's cut of the fees is only accrued on swaps and flashes, a collectProtocol
+    // lowers the balance and is tracked separately with the amounts of the CollectProtocol event
+    if abi::pool::events::Swap::match_and_decode(log).is_some() {
+        if !pool.should_handle_swap(config) {
+            return;
+        }
+    } else if abi::pool::events::Flash::match_and_decode(log).is_none() {
+        return;
+    }
+
+    let storage = UniswapPoolStorage::with_layout(storage_changes, &log.address, pool.storage_layout());
+    let accrued0 = accrued_if_some(storage.protocol_fees_token0());
+    let accrued1 = accrued_if_some(storage.protocol_fees_token1());
+    if accrued0 == BigInt::zero() && accrued1 == BigInt::zero() {
+        return;
+    }
+
+    let token0 = pool.token0.as_ref().unwrap();
+    let token1 = pool.token1.as_ref().unwrap();
+    protocol_fees.push(events::ProtocolFees {
+        pool_address: pool.address.to_string(),
+        token0: token0.address.clone(),
+        token1: token1.address.clone(),
+        amount_0: accrued0.to_decimal(token0.decimals).into(),
+        amount_1: accrued1.to_decimal(token1.decimals).into(),
+        log_ordinal: log.ordinal,
+    });
+}
+
+pub fn extract_protocol_collects(protocol_collects: &mut Vec<events::CollectProtocol>, log: &Log, pool: &Pool) {
+    if let Some(event) = abi::pool::events::CollectProtocol::match_and_decode(log) {
+        log::info!("COLLECT PROTOCOL: pool: {}", pool.address);
+        let token0 = pool.token0.as_ref().unwrap();
+        let token1 = pool.token1.as_ref().unwrap();
+        protocol_collects.push(events::CollectProtocol {
+            pool_address: pool.address.to_string(),
+            token0: token0.address.clone(),
+            token1: token1.address.clone(),
+            sender: Hex(&event.sender).to_string(),
+            recipient: Hex(&event.recipient).to_string(),
+            amount_0: event.amount0.to_decimal(token0.decimals).into(),
+            amount_1: event.amount1.to_decimal(token1.decimals).into(),
+            log_ordinal: log.ordinal,
+        });
+    }
+}
+
+fn accrued_if_some(input: Option<(BigInt, BigInt)>) -> BigInt {
+    match input {
+        Some((old_value, new_value)) if new_value > old_value => new_value - old_value,
+        _ => BigInt::zero(),
+    }
+}
+
+pub fn extract_oracle_observations(
+    observations: &mut Vec<OracleObservation>,
+    oracle_states: &mut Vec<OracleState>,
+    log: &Log,
+    storage_changes: &Vec<StorageChange>,
+    pool: &Pool,
+) {
+    if Hex(&log.address).to_string() != pool.address {
+        return;
+    }
+
+    let storage = UniswapPoolStorage::with_layout(storage_changes, &log.address, pool.storage_layout());
+
+    for observation_idx in storage.observation_indexes() {
+        let observation = storage.observations(&observation_idx);
+        let block_timestamp = match observation.block_timestamp() {
+            Some((_, new_value)) => new_value,
+            None => continue,
+        };
+
+        observations.push(OracleObservation {
+            pool_address: pool.address.to_string(),
+            index: observation_idx.to_u64() as u32,
+            block_timestamp: block_timestamp.to_u64(),
+            tick_cumulative: bigint_if_some(observation.tick_cumulative()),
+            seconds_per_liquidity_cumulative_x128: bigint_if_some(observation.seconds_per_liquidity_cumulative_x128()),
+            initialized: observation
+                .initialized()
+                .map(|(_, new_value)| new_value)
+                .unwrap_or(false),
+            log_ordinal: log.ordinal,
+        });
+    }
+
+    let slot0 = storage.slot0();
+    let oracle_state = OracleState {
+        pool_address: pool.address.to_string(),
+        observation_index: slot0
+            .observation_index()
+            .map(|(_, new_value)| new_value.to_u64() as u32),
+        observation_cardinality: slot0
+            .observation_cardinality()
+            .map(|(_, new_value)| new_value.to_u64() as u32),
+        observation_cardinality_next: slot0
+            .observation_cardinality_next()
+            .map(|(_, new_value)| new_value.to_u64() as u32),
+        log_ordinal: log.ordinal,
+    };
+    if oracle_state.observation_index.is_some()
+        || oracle_state.observation_cardinality.is_some()
+        || oracle_state.observation_cardinality_next.is_some()
+    {
+        oracle_states.push(oracle_state);
+    }
+}
+
 pub fn extract_pool_sqrt_prices(pool_sqrt_prices: &mut Vec<events::PoolSqrtPrice>, log: &Log, pool_address: &String) {
     if let Some(event) = abi::pool::events::Initialize::match_and_decode(log) {
         pool_sqrt_prices.push(events::PoolSqrtPrice {
@@ -422,6 +754,8 @@ pub fn extract_transactions(
     if abi::pool::events::Burn::match_and_decode(log).is_some()
         || abi::pool::events::Mint::match_and_decode(log).is_some()
         || abi::pool::events::Swap::match_and_decode(log).is_some()
+        || abi::pool::events::Flash::match_and_decode(log).is_some()
+        || abi::pool::events::Collect::match_and_decode(log).is_some()
         || abi::positionmanager::events::IncreaseLiquidity::match_and_decode(log).is_some()
         || abi::positionmanager::events::Collect::match_and_decode(log).is_some()
         || abi::positionmanager::events::DecreaseLiquidity::match_and_decode(log).is_some()
@@ -440,110 +774,130 @@ pub fn extract_transactions(
     }
 }
 
-fn extract_positions(
-    pool: &Pool,
+// events of the NonfungiblePositionManager, looked at independently of the pool logs so that the
+// transfers and burns of position NFTs happening outside of any pool call are captured too
+pub fn extract_position_manager_events(
     increase_liquidity_positions: &mut Vec<events::IncreaseLiquidityPosition>,
     decrease_liquidity_positions: &mut Vec<events::DecreaseLiquidityPosition>,
     collect_positions: &mut Vec<events::CollectPosition>,
     transfer_positions: &mut Vec<events::TransferPosition>,
+    log: &Log,
     call: &Call,
+    transaction_trace: &TransactionTrace,
+    pools_store: &StoreGetProto<Pool>,
+    config: &Config,
 ) {
-    for log in call.logs.iter() {
-        if log.address != NON_FUNGIBLE_POSITION_MANAGER {
-            return;
-        }
-
-        let mut fee_growth_inside0_last_x128 = None;
-        let mut fee_growth_inside1_last_x128 = None;
+    if log.address != config.position_manager {
+        return;
+    }
 
-        let manager_storage = PositionManagerStorage::new(&call.storage_changes, &call.address);
+    if let Some(event) = abi::positionmanager::events::Transfer::match_and_decode(log) {
+        transfer_positions.push(events::TransferPosition {
+            token_id: event.token_id.to_string(),
+            owner: Hex(&event.to).to_string(),
+            log_ordinal: log.ordinal,
+        });
+        return;
+    }
 
-        if let Some(event) = abi::positionmanager::events::IncreaseLiquidity::match_and_decode(log) {
-            if let Some((_old_value, new_value)) =
-                manager_storage.positions(&event.token_id).fee_growth_inside0last_x128()
-            {
-                fee_growth_inside0_last_x128 = Some(new_value.to_string());
-            }
+    // increasing, decreasing liquidity and collecting all go through the position's pool,
+    // which gives us the decimals of the amounts
+    let pool = match position_manager_pool(call, transaction_trace, pools_store) {
+        Some(pool) => pool,
+        None => return,
+    };
+    let manager_storage = PositionManagerStorage::new(&call.storage_changes, &call.address);
+
+    if let Some(event) = abi::positionmanager::events::IncreaseLiquidity::match_and_decode(log) {
+        let position = manager_storage.positions(&event.token_id);
+        increase_liquidity_positions.push(events::IncreaseLiquidityPosition {
+            token_id: event.token_id.to_string(),
+            liquidity: event.liquidity.to_string(),
+            deposited_token0: event.amount0.to_decimal(pool.token0().decimals).to_string(),
+            deposited_token1: event.amount1.to_decimal(pool.token1().decimals).to_string(),
+            fee_growth_inside0_last_x128: new_value_if_some(position.fee_growth_inside0last_x128()),
+            fee_growth_inside1_last_x128: new_value_if_some(position.fee_growth_inside1last_x128()),
+            total_liquidity: new_value_if_some(position.liquidity()),
+            tokens_owed0: new_value_if_some(position.tokens_owed0()),
+            tokens_owed1: new_value_if_some(position.tokens_owed1()),
+            log_ordinal: log.ordinal,
+        });
+    } else if let Some(event) = abi::positionmanager::events::DecreaseLiquidity::match_and_decode(log) {
+        let position = manager_storage.positions(&event.token_id);
+        decrease_liquidity_positions.push(events::DecreaseLiquidityPosition {
+            token_id: event.token_id.to_string(),
+            liquidity: event.liquidity.to_string(),
+            withdrawn_token0: event.amount0.to_decimal(pool.token0().decimals).to_string(),
+            withdrawn_token1: event.amount1.to_decimal(pool.token1().decimals).to_string(),
+            fee_growth_inside0_last_x128: new_value_if_some(position.fee_growth_inside0last_x128()),
+            fee_growth_inside1_last_x128: new_value_if_some(position.fee_growth_inside1last_x128()),
+            total_liquidity: new_value_if_some(position.liquidity()),
+            tokens_owed0: new_value_if_some(position.tokens_owed0()),
+            tokens_owed1: new_value_if_some(position.tokens_owed1()),
+            log_ordinal: log.ordinal,
+        });
+    } else if let Some(event) = abi::positionmanager::events::Collect::match_and_decode(log) {
+        let position = manager_storage.positions(&event.token_id);
+        collect_positions.push(events::CollectPosition {
+            token_id: event.token_id.to_string(),
+            collected_fees_token0: event.amount0.to_decimal(pool.token0().decimals).to_string(),
+            collected_fees_token1: event.amount1.to_decimal(pool.token1().decimals).to_string(),
+            fee_growth_inside0_last_x128: new_value_if_some(position.fee_growth_inside0last_x128()),
+            fee_growth_inside1_last_x128: new_value_if_some(position.fee_growth_inside1last_x128()),
+            total_liquidity: new_value_if_some(position.liquidity()),
+            tokens_owed0: new_value_if_some(position.tokens_owed0()),
+            tokens_owed1: new_value_if_some(position.tokens_owed1()),
+            log_ordinal: log.ordinal,
+        });
+    }
+}
 
-            if let Some((_old_value, new_value)) =
-                manager_storage.positions(&event.token_id).fee_growth_inside1last_x128()
-            {
-                fee_growth_inside1_last_x128 = Some(new_value.to_string());
-            }
+// the pool called by the position manager while handling `call`
+fn position_manager_pool(
+    call: &Call,
+    transaction_trace: &TransactionTrace,
+    pools_store: &StoreGetProto<Pool>,
+) -> Option<Pool> {
+    transaction_trace
+        .calls
+        .iter()
+        .filter(|child| child.parent_index == call.index)
+        .find_map(|child| pools_store.get_last(format!("pool:{}", Hex(&child.address))))
+}
 
-            increase_liquidity_positions.push(events::IncreaseLiquidityPosition {
-                token_id: event.token_id.to_string(),
-                liquidity: event.liquidity.to_string(),
-                deposited_token0: event.amount0.to_decimal(pool.token0().decimals).to_string(),
-                deposited_token1: event.amount1.to_decimal(pool.token1().decimals).to_string(),
-                fee_growth_inside0_last_x128,
-                fee_growth_inside1_last_x128,
-                log_ordinal: log.ordinal,
-            });
-        } else if let Some(event) = abi::positionmanager::events::DecreaseLiquidity::match_and_decode(log) {
-            if let Some((_old_value, new_value)) =
-                manager_storage.positions(&event.token_id).fee_growth_inside0last_x128()
-            {
-                fee_growth_inside0_last_x128 = Some(new_value.to_string());
-            }
+pub fn extract_flashes(
+    flashes: &mut Vec<events::Flash>,
+    log: &Log,
+    storage_changes: &Vec<StorageChange>,
+    pool: &Pool,
+    transaction_id: &String,
+    timestamp_seconds: u64,
+) {
+    if let Some(flash) = abi::pool::events::Flash::match_and_decode(log) {
+        log::info!("FLASH: transaction: {}", transaction_id.to_string());
+        let token0 = pool.token0.as_ref().unwrap();
+        let token1 = pool.token1.as_ref().unwrap();
 
-            if let Some((_old_value, new_value)) =
-                manager_storage.positions(&event.token_id).fee_growth_inside1last_x128()
-            {
-                fee_growth_inside1_last_x128 = Some(new_value.to_string());
-            }
-            decrease_liquidity_positions.push(events::DecreaseLiquidityPosition {
-                token_id: event.token_id.to_string(),
-                liquidity: event.liquidity.to_string(),
-                withdrawn_token0: event.amount0.to_decimal(pool.token0().decimals).to_string(),
-                withdrawn_token1: event.amount1.to_decimal(pool.token1().decimals).to_string(),
-                fee_growth_inside0_last_x128,
-                fee_growth_inside1_last_x128,
-                log_ordinal: log.ordinal,
-            });
-        } else if let Some(event) = abi::positionmanager::events::Collect::match_and_decode(log) {
-            if let Some((_old_value, new_value)) =
-                manager_storage.positions(&event.token_id).fee_growth_inside0last_x128()
-            {
-                fee_growth_inside0_last_x128 = Some(new_value.to_string());
-            }
+        // the fees paid for the flash are accrued to the fee growth globals, we read the
+        // new values straight from the pool storage instead of doing an `eth_call`
+        let storage = UniswapPoolStorage::with_layout(storage_changes, &log.address, pool.storage_layout());
 
-            if let Some((_old_value, new_value)) =
-                manager_storage.positions(&event.token_id).fee_growth_inside1last_x128()
-            {
-                fee_growth_inside1_last_x128 = Some(new_value.to_string());
-            }
-            collect_positions.push(events::CollectPosition {
-                token_id: event.token_id.to_string(),
-                collected_fees_token0: event.amount0.to_decimal(pool.token0().decimals).to_string(),
-                collected_fees_token1: event.amount1.to_decimal(pool.token1().decimals).to_string(),
-                fee_growth_inside0_last_x128,
-                fee_growth_inside1_last_x128,
-                log_ordinal: log.ordinal,
-            });
-        } else if let Some(event) = abi::positionmanager::events::Transfer::match_and_decode(log) {
-            transfer_positions.push(events::TransferPosition {
-                token_id: event.token_id.to_string(),
-                owner: Hex(&event.to).to_string(),
-                log_ordinal: log.ordinal,
-            });
-        }
+        flashes.push(events::Flash {
+            pool_address: pool.address.to_string(),
+            fee_growth_global_0x_128: bigint_if_some(storage.fee_growth_global0x128()),
+            fee_growth_global_1x_128: bigint_if_some(storage.fee_growth_global1x128()),
+            log_ordinal: log.ordinal,
+            log_index: log.block_index as u64,
+            transaction_id: transaction_id.to_string(),
+            timestamp: timestamp_seconds,
+            token0: token0.address.clone(),
+            token1: token1.address.clone(),
+            sender: Hex(&flash.sender).to_string(),
+            recipient: Hex(&flash.recipient).to_string(),
+            amount_0: flash.amount0.to_decimal(token0.decimals).into(),
+            amount_1: flash.amount1.to_decimal(token1.decimals).into(),
+            paid_0: flash.paid0.to_decimal(token0.decimals).into(),
+            paid_1: flash.paid1.to_decimal(token1.decimals).into(),
+        });
     }
 }
-
-// pub fn extract_flashes(flashes: &mut Vec<events::Flash>, log: &Log) {
-//     if abi::pool::events::Flash::match_log(&log) {
-//         let pool_address: String = Hex(&log.address).to_string();
-//
-//         // FIXME: kill those `rpc` calls here!
-//         log::info!("pool_address: {}", pool_address);
-//         let (fee_growth_global_0x_128, fee_growth_global_1x_128) = rpc::fee_growth_global_x128_call(&pool_address);
-//
-//         flashes.push(events::Flash {
-//             pool_address,
-//             fee_growth_global_0x_128: fee_growth_global_0x_128.to_string(),
-//             fee_growth_global_1x_128: fee_growth_global_1x_128.to_string(),
-//             log_ordinal: log.ordinal,
-//         });
-//     }
-// }
@@ -0,0 +1,539 @@
+use num_bigint::{BigInt, BigUint};
+use num_traits::{One, ToPrimitive, Zero};
+use std::ops::Div;
+use std::str::FromStr;
+use substreams::scalar::BigDecimal;
+
+// Integer ports of the parts of the v3-core `TickMath` and `SqrtPriceMath` libraries and of the v3-periphery
+// `LiquidityAmounts` library the modules need, working on the Q64.96 square root prices of the pools
+// (`sqrtPriceX96`). The arithmetic is exact, so the results are the ones of the contracts down to their
+// rounding, and inputs the contracts revert on panic.
+
+pub const MIN_TICK: i32 = -887272;
+pub const MAX_TICK: i32 = -MIN_TICK;
+
+// `TickMath` multipliers, 2^128 / sqrt(1.0001)^(2^i) for the bit i of the absolute tick
+const TICK_RATIOS: [&str; 20] = [
+    "fffcb933bd6fad37aa2d162d1a594001",
+    "fff97272373d413259a46990580e213a",
+    "fff2e50f5f656932ef12357cf3c7fdcc",
+    "ffe5caca7e10e4e61c3624eaa0941cd0",
+    "ffcb9843d60f6159c9db58835c926644",
+    "ff973b41fa98c081472e6896dfb254c0",
+    "ff2ea16466c96a3843ec78b326b52861",
+    "fe5dee046a99a2a811c461f1969c3053",
+    "fcbe86c7900a88aedcffc83b479aa3a4",
+    "f987a7253ac413176f2b074cf7815e54",
+    "f3392b0822b70005940c7a398e4b70f3",
+    "e7159475a2c29b7443b29c7fa6e889d9",
+    "d097f3bdfd2022b8845ad8f792aa5825",
+    "a9f746462d870fdf8a65dc1f90e061e5",
+    "70d869a156d2a1b890bb3df62baf32f7",
+    "31be135f97d08fd981231505542fcfa6",
+    "9aa508b5b7a84e1c677de54f3e99bc9",
+    "5d6af8dedb81196699c329225ee604",
+    "2216e584f5fa1ea926041bedfe98",
+    "48a170391f7dc42444e8fa2",
+];
+
+pub fn min_sqrt_ratio() -> BigUint {
+    BigUint::from(4295128739u64)
+}
+
+pub fn max_sqrt_ratio() -> BigUint {
+    uint("1461446703485210103287273052203988822378723970342")
+}
+
+// -------------------
+//  TickMath
+// -------------------
+
+// TickMath.getSqrtRatioAtTick, sqrt(1.0001^tick) as a Q64.96
+pub fn get_sqrt_ratio_at_tick(tick: i32) -> BigUint {
+    let abs_tick = tick.unsigned_abs();
+    assert!(abs_tick <= MAX_TICK as u32, "tick {tick} out of range");
+
+    let mut ratio = if abs_tick & 0x1 != 0 {
+        hex_uint(TICK_RATIOS[0])
+    } else {
+        BigUint::one() << 128
+    };
+    for (bit, tick_ratio) in TICK_RATIOS.iter().enumerate().skip(1) {
+        if abs_tick & (1 << bit) != 0 {
+            ratio = (ratio * hex_uint(tick_ratio)) >> 128;
+        }
+    }
+    if tick > 0 {
+        ratio = uint256_max() / ratio;
+    }
+
+    // back from Q128.128 to Q64.96, rounding up so that the tick of the result is the given tick
+    let round_up = !(&ratio % (BigUint::one() << 32)).is_zero();
+    let sqrt_price_x96 = ratio >> 32;
+    if round_up {
+        sqrt_price_x96 + BigUint::one()
+    } else {
+        sqrt_price_x96
+    }
+}
+
+// TickMath.getTickAtSqrtRatio, the greatest tick whose sqrt ratio is at most the given Q64.96 sqrt price
+pub fn get_tick_at_sqrt_ratio(sqrt_price_x96: &BigUint) -> i32 {
+    assert!(
+        sqrt_price_x96 >= &min_sqrt_ratio() && sqrt_price_x96 < &max_sqrt_ratio(),
+        "sqrt price {sqrt_price_x96} out of range"
+    );
+
+    // log2 of the Q128.128 ratio as a Q64.64, its integer part from the most significant bit and 14 bits of
+    // its fractional part from the successive squares of the ratio normalized to [1, 2)
+    let ratio: BigUint = sqrt_price_x96 << 32;
+    let msb = ratio.bits() - 1;
+    let mut r = if msb >= 128 {
+        &ratio >> (msb - 127)
+    } else {
+        &ratio << (127 - msb)
+    };
+    let mut log_2 = BigInt::from(msb as i64 - 128) << 64;
+    for shift in (50..64).rev() {
+        r = (&r * &r) >> 127;
+        let f: BigUint = &r >> 128;
+        if !f.is_zero() {
+            log_2 += BigInt::one() << shift;
+            r >>= 1;
+        }
+    }
+
+    // log base sqrt(1.0001) as a Q128.128 and the bounds of its error, the shifts round towards negative infinity
+    let log_sqrt10001 = log_2 * BigInt::from_str("255738958999603826347141").unwrap();
+    let tick_low = (&log_sqrt10001 - BigInt::from_str("3402992956809132418596140100660247210").unwrap()) >> 128;
+    let tick_high = (&log_sqrt10001 + BigInt::from_str("291339464771989622907027621153398088495").unwrap()) >> 128;
+    let tick_low = tick_low.to_i32().unwrap();
+    let tick_high = tick_high.to_i32().unwrap();
+
+    if tick_low == tick_high || get_sqrt_ratio_at_tick(tick_high) > *sqrt_price_x96 {
+        tick_low
+    } else {
+        tick_high
+    }
+}
+
+// -------------------
+//  SqrtPriceMath
+// -------------------
+
+// SqrtPriceMath.getAmount0Delta, amount of token0 between two sqrt prices for the liquidity
+pub fn get_amount0_delta(
+    sqrt_ratio_a_x96: &BigUint,
+    sqrt_ratio_b_x96: &BigUint,
+    liquidity: &BigUint,
+    round_up: bool,
+) -> BigUint {
+    let (sqrt_ratio_a_x96, sqrt_ratio_b_x96) = sorted(sqrt_ratio_a_x96, sqrt_ratio_b_x96);
+    assert!(!sqrt_ratio_a_x96.is_zero());
+
+    let numerator1 = liquidity.clone() << 96;
+    let numerator2 = sqrt_ratio_b_x96 - sqrt_ratio_a_x96;
+    if round_up {
+        div_rounding_up(
+            &mul_div_rounding_up(&numerator1, &numerator2, sqrt_ratio_b_x96),
+            sqrt_ratio_a_x96,
+        )
+    } else {
+        mul_div(&numerator1, &numerator2, sqrt_ratio_b_x96) / sqrt_ratio_a_x96
+    }
+}
+
+// SqrtPriceMath.getAmount1Delta, amount of token1 between two sqrt prices for the liquidity
+pub fn get_amount1_delta(
+    sqrt_ratio_a_x96: &BigUint,
+    sqrt_ratio_b_x96: &BigUint,
+    liquidity: &BigUint,
+    round_up: bool,
+) -> BigUint {
+    let (sqrt_ratio_a_x96, sqrt_ratio_b_x96) = sorted(sqrt_ratio_a_x96, sqrt_ratio_b_x96);
+    let difference = sqrt_ratio_b_x96 - sqrt_ratio_a_x96;
+    if round_up {
+        mul_div_rounding_up(liquidity, &difference, &q96())
+    } else {
+        mul_div(liquidity, &difference, &q96())
+    }
+}
+
+// -------------------
+//  LiquidityAmounts
+// -------------------
+
+// LiquidityAmounts.getAmount0ForLiquidity
+pub fn get_amount0_for_liquidity(
+    sqrt_ratio_a_x96: &BigUint,
+    sqrt_ratio_b_x96: &BigUint,
+    liquidity: &BigUint,
+) -> BigUint {
+    let (sqrt_ratio_a_x96, sqrt_ratio_b_x96) = sorted(sqrt_ratio_a_x96, sqrt_ratio_b_x96);
+    mul_div(
+        &(liquidity.clone() << 96),
+        &(sqrt_ratio_b_x96 - sqrt_ratio_a_x96),
+        sqrt_ratio_b_x96,
+    ) / sqrt_ratio_a_x96
+}
+
+// LiquidityAmounts.getAmount1ForLiquidity
+pub fn get_amount1_for_liquidity(
+    sqrt_ratio_a_x96: &BigUint,
+    sqrt_ratio_b_x96: &BigUint,
+    liquidity: &BigUint,
+) -> BigUint {
+    let (sqrt_ratio_a_x96, sqrt_ratio_b_x96) = sorted(sqrt_ratio_a_x96, sqrt_ratio_b_x96);
+    mul_div(liquidity, &(sqrt_ratio_b_x96 - sqrt_ratio_a_x96), &q96())
+}
+
+// LiquidityAmounts.getAmountsForLiquidity, the amounts of token0 and token1 of the liquidity at the price
+pub fn get_amounts_for_liquidity(
+    sqrt_price_x96: &BigUint,
+    sqrt_ratio_a_x96: &BigUint,
+    sqrt_ratio_b_x96: &BigUint,
+    liquidity: &BigUint,
+) -> (BigUint, BigUint) {
+    let (sqrt_ratio_a_x96, sqrt_ratio_b_x96) = sorted(sqrt_ratio_a_x96, sqrt_ratio_b_x96);
+    if sqrt_price_x96 <= sqrt_ratio_a_x96 {
+        (
+            get_amount0_for_liquidity(sqrt_ratio_a_x96, sqrt_ratio_b_x96, liquidity),
+            BigUint::zero(),
+        )
+    } else if sqrt_price_x96 < sqrt_ratio_b_x96 {
+        (
+            get_amount0_for_liquidity(sqrt_price_x96, sqrt_ratio_b_x96, liquidity),
+            get_amount1_for_liquidity(sqrt_ratio_a_x96, sqrt_price_x96, liquidity),
+        )
+    } else {
+        (
+            BigUint::zero(),
+            get_amount1_for_liquidity(sqrt_ratio_a_x96, sqrt_ratio_b_x96, liquidity),
+        )
+    }
+}
+
+// sqrt price of the price multiplied by numerator / denominator, within the sqrt ratios of the ticks
+pub fn scale_sqrt_price(sqrt_price_x96: &BigUint, numerator: u32, denominator: u32) -> BigUint {
+    let sqrt_price_x96 = (sqrt_price_x96 * sqrt_price_x96 * numerator / denominator).sqrt();
+    sqrt_price_x96.clamp(min_sqrt_ratio(), max_sqrt_ratio())
+}
+
+// amounts of token0 and token1 a swap can take from the liquidity between the price and the upper,
+// respectively lower, sqrt ratio, crossing the initialized ticks in between. `ticks` are the initialized
+// ticks of the pool in ascending order with their liquidityNet.
+pub fn get_amounts_in_range(
+    sqrt_price_x96: &BigUint,
+    tick_current: i32,
+    liquidity: &BigUint,
+    ticks: &Vec<(i32, BigInt)>,
+    sqrt_ratio_lower_x96: &BigUint,
+    sqrt_ratio_upper_x96: &BigUint,
+) -> (BigUint, BigUint) {
+    let to_liquidity = |liquidity: &BigInt| liquidity.to_biguint().unwrap_or_default();
+
+    let mut amount0 = BigUint::zero();
+    let mut sqrt_price = sqrt_price_x96.clone();
+    let mut active_liquidity = BigInt::from(liquidity.clone());
+    for (tick, liquidity_net) in ticks.iter().filter(|(tick, _)| *tick > tick_current) {
+        let sqrt_ratio_x96 = get_sqrt_ratio_at_tick(*tick);
+        if sqrt_ratio_x96 >= *sqrt_ratio_upper_x96 {
+            break;
+        }
+        amount0 += get_amount0_delta(&sqrt_price, &sqrt_ratio_x96, &to_liquidity(&active_liquidity), false);
+        sqrt_price = sqrt_ratio_x96;
+        active_liquidity += liquidity_net;
+    }
+    if *sqrt_ratio_upper_x96 > sqrt_price {
+        amount0 += get_amount0_delta(
+            &sqrt_price,
+            sqrt_ratio_upper_x96,
+            &to_liquidity(&active_liquidity),
+            false,
+        );
+    }
+
+    let mut amount1 = BigUint::zero();
+    let mut sqrt_price = sqrt_price_x96.clone();
+    let mut active_liquidity = BigInt::from(liquidity.clone());
+    for (tick, liquidity_net) in ticks.iter().rev().filter(|(tick, _)| *tick <= tick_current) {
+        let sqrt_ratio_x96 = get_sqrt_ratio_at_tick(*tick);
+        if sqrt_ratio_x96 <= *sqrt_ratio_lower_x96 {
+            break;
+        }
+        amount1 += get_amount1_delta(&sqrt_ratio_x96, &sqrt_price, &to_liquidity(&active_liquidity), false);
+        sqrt_price = sqrt_ratio_x96;
+        active_liquidity -= liquidity_net;
+    }
+    if *sqrt_ratio_lower_x96 < sqrt_price {
+        amount1 += get_amount1_delta(
+            sqrt_ratio_lower_x96,
+            &sqrt_price,
+            &to_liquidity(&active_liquidity),
+            false,
+        );
+    }
+
+    (amount0, amount1)
+}
+
+// -------------------
+//  Conversions
+// -------------------
+
+// price of token0 in token1 of a sqrt price, in raw token units, i.e. sqrtPriceX96^2 / 2^192
+pub fn sqrt_price_x96_to_price(sqrt_price_x96: &BigUint) -> BigDecimal {
+    let numerator = BigDecimal::from_str(&(sqrt_price_x96 * sqrt_price_x96).to_string()).unwrap();
+    let denominator = BigDecimal::from_str(&(BigUint::one() << 192).to_string()).unwrap();
+    numerator.div(denominator)
+}
+
+pub fn parse_uint(value: &str) -> BigUint {
+    BigUint::from_str(value).expect(&format!("{value} isn't an unsigned integer"))
+}
+
+pub fn to_uint(value: &substreams::scalar::BigInt) -> BigUint {
+    parse_uint(&value.to_string())
+}
+
+pub fn to_int(value: &substreams::scalar::BigInt) -> BigInt {
+    BigInt::from_str(&value.to_string()).expect(&format!("{value} isn't an integer"))
+}
+
+pub fn to_big_int(value: &BigUint) -> substreams::scalar::BigInt {
+    substreams::scalar::BigInt::try_from(value.to_string()).unwrap()
+}
+
+// -------------------
+//  FullMath and UnsafeMath
+// -------------------
+
+fn mul_div(a: &BigUint, b: &BigUint, denominator: &BigUint) -> BigUint {
+    let result = a * b / denominator;
+    assert!(result <= uint256_max(), "mul_div overflow");
+    result
+}
+
+fn mul_div_rounding_up(a: &BigUint, b: &BigUint, denominator: &BigUint) -> BigUint {
+    let product = a * b;
+    let result = div_rounding_up(&product, denominator);
+    assert!(result <= uint256_max(), "mul_div overflow");
+    result
+}
+
+fn div_rounding_up(a: &BigUint, b: &BigUint) -> BigUint {
+    let quotient = a / b;
+    if (a % b).is_zero() {
+        quotient
+    } else {
+        quotient + BigUint::one()
+    }
+}
+
+fn sorted<'a>(a: &'a BigUint, b: &'a BigUint) -> (&'a BigUint, &'a BigUint) {
+    if a > b {
+        (b, a)
+    } else {
+        (a, b)
+    }
+}
+
+fn q96() -> BigUint {
+    BigUint::one() << 96
+}
+
+fn uint256_max() -> BigUint {
+    (BigUint::one() << 256) - BigUint::one()
+}
+
+fn uint(value: &str) -> BigUint {
+    BigUint::from_str(value).unwrap()
+}
+
+fn hex_uint(value: &str) -> BigUint {
+    BigUint::parse_bytes(value.as_bytes(), 16).unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fixed_point::*;
+
+    const E18: u64 = 1_000_000_000_000_000_000;
+
+    fn int(value: &str) -> BigInt {
+        BigInt::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_sqrt_ratio_at_tick_bounds() {
+        assert_eq!(min_sqrt_ratio(), get_sqrt_ratio_at_tick(MIN_TICK));
+        assert_eq!(max_sqrt_ratio(), get_sqrt_ratio_at_tick(MAX_TICK));
+        assert_eq!(BigUint::one() << 96, get_sqrt_ratio_at_tick(0));
+    }
+
+    #[test]
+    fn test_tick_at_sqrt_ratio_bounds() {
+        assert_eq!(MIN_TICK, get_tick_at_sqrt_ratio(&min_sqrt_ratio()));
+        assert_eq!(MIN_TICK, get_tick_at_sqrt_ratio(&(min_sqrt_ratio() + BigUint::one())));
+        assert_eq!(
+            MAX_TICK - 1,
+            get_tick_at_sqrt_ratio(&(max_sqrt_ratio() - BigUint::one()))
+        );
+        assert_eq!(0, get_tick_at_sqrt_ratio(&(BigUint::one() << 96)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_tick_at_sqrt_ratio_below_min() {
+        get_tick_at_sqrt_ratio(&(min_sqrt_ratio() - BigUint::one()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_tick_at_sqrt_ratio_at_max() {
+        get_tick_at_sqrt_ratio(&max_sqrt_ratio());
+    }
+
+    #[test]
+    fn test_tick_at_sqrt_ratio_round_trip() {
+        for tick in [
+            MIN_TICK,
+            MIN_TICK + 1,
+            -887220,
+            -16200,
+            -60,
+            -1,
+            0,
+            1,
+            60,
+            257820,
+            887220,
+            MAX_TICK - 1,
+        ] {
+            let sqrt_ratio = get_sqrt_ratio_at_tick(tick);
+            assert_eq!(tick, get_tick_at_sqrt_ratio(&sqrt_ratio));
+            if tick > MIN_TICK {
+                // just below the sqrt ratio of a tick is the tick before
+                assert_eq!(tick - 1, get_tick_at_sqrt_ratio(&(sqrt_ratio - BigUint::one())));
+            }
+        }
+        assert_eq!(
+            MAX_TICK - 1,
+            get_tick_at_sqrt_ratio(&(get_sqrt_ratio_at_tick(MAX_TICK) - BigUint::one()))
+        );
+    }
+
+    #[test]
+    fn test_sqrt_ratio_at_tick() {
+        assert_eq!(uint("79232123823359799118286999568"), get_sqrt_ratio_at_tick(1));
+        assert_eq!(uint("79224201403219477170569942574"), get_sqrt_ratio_at_tick(-1));
+        assert_eq!(
+            uint("31411584497546210966172950056294283"),
+            get_sqrt_ratio_at_tick(257820)
+        );
+        assert_eq!(uint("35246714534369728900268601106"), get_sqrt_ratio_at_tick(-16200));
+    }
+
+    #[test]
+    fn test_amount_deltas() {
+        let sqrt_ratio_a = get_sqrt_ratio_at_tick(-60);
+        let sqrt_ratio_b = get_sqrt_ratio_at_tick(60);
+        let liquidity = BigUint::from(E18);
+
+        assert_eq!(
+            BigUint::from(5999709018652707u64),
+            get_amount0_delta(&sqrt_ratio_b, &sqrt_ratio_a, &liquidity, true)
+        );
+        assert_eq!(
+            BigUint::from(5999709018652706u64),
+            get_amount0_delta(&sqrt_ratio_a, &sqrt_ratio_b, &liquidity, false)
+        );
+        assert_eq!(
+            BigUint::from(5999709018652707u64),
+            get_amount1_delta(&sqrt_ratio_a, &sqrt_ratio_b, &liquidity, true)
+        );
+    }
+
+    #[test]
+    fn test_liquidity_amounts() {
+        let sqrt_price = BigUint::one() << 96;
+        let sqrt_ratio_a = get_sqrt_ratio_at_tick(-60);
+        let sqrt_ratio_b = get_sqrt_ratio_at_tick(60);
+        let amount = BigUint::from(E18);
+
+        assert_eq!(
+            (BigUint::from(2995354955910780u64), BigUint::from(2995354955910780u64)),
+            get_amounts_for_liquidity(&sqrt_price, &sqrt_ratio_a, &sqrt_ratio_b, &amount)
+        );
+        assert_eq!(
+            (BigUint::from(5999709018652706u64), BigUint::zero()),
+            get_amounts_for_liquidity(&get_sqrt_ratio_at_tick(-120), &sqrt_ratio_a, &sqrt_ratio_b, &amount)
+        );
+        assert_eq!(
+            (BigUint::zero(), BigUint::from(5999709018652706u64)),
+            get_amounts_for_liquidity(&get_sqrt_ratio_at_tick(120), &sqrt_ratio_a, &sqrt_ratio_b, &amount)
+        );
+    }
+
+    #[test]
+    fn test_scale_sqrt_price() {
+        let sqrt_price = BigUint::one() << 96;
+        assert_eq!(sqrt_price, scale_sqrt_price(&sqrt_price, 10000, 10000));
+        assert_eq!(
+            uint("79623317895830914510639640423"),
+            scale_sqrt_price(&sqrt_price, 10100, 10000)
+        );
+        assert_eq!(
+            uint("78831026366734652303669917531"),
+            scale_sqrt_price(&sqrt_price, 9900, 10000)
+        );
+        assert_eq!(max_sqrt_ratio(), scale_sqrt_price(&max_sqrt_ratio(), 20000, 10000));
+    }
+
+    #[test]
+    fn test_amounts_in_range() {
+        let sqrt_price = BigUint::one() << 96;
+        let liquidity = BigUint::from(E18);
+        let ticks = vec![(-60, int("1000000000000000000")), (60, int("-1000000000000000000"))];
+
+        // the whole position
+        assert_eq!(
+            (BigUint::from(2995354955910780u64), BigUint::from(2995354955910780u64)),
+            get_amounts_in_range(
+                &sqrt_price,
+                0,
+                &liquidity,
+                &ticks,
+                &get_sqrt_ratio_at_tick(-1200),
+                &get_sqrt_ratio_at_tick(1200)
+            )
+        );
+
+        // within the position
+        let sqrt_ratio_lower = get_sqrt_ratio_at_tick(-30);
+        let sqrt_ratio_upper = get_sqrt_ratio_at_tick(30);
+        assert_eq!(
+            (
+                get_amount0_delta(&sqrt_price, &sqrt_ratio_upper, &liquidity, false),
+                get_amount1_delta(&sqrt_ratio_lower, &sqrt_price, &liquidity, false)
+            ),
+            get_amounts_in_range(&sqrt_price, 0, &liquidity, &ticks, &sqrt_ratio_lower, &sqrt_ratio_upper)
+        );
+
+        // crossing the upper tick of a second position starting at the current tick
+        let ticks = vec![
+            (-60, int("1000000000000000000")),
+            (0, int("1000000000000000000")),
+            (60, int("-1000000000000000000")),
+            (120, int("-1000000000000000000")),
+        ];
+        let liquidity = BigUint::from(2 * E18);
+        let sqrt_ratio_60 = get_sqrt_ratio_at_tick(60);
+        let sqrt_ratio_upper = get_sqrt_ratio_at_tick(90);
+        assert_eq!(
+            get_amount0_delta(&sqrt_price, &sqrt_ratio_60, &liquidity, false)
+                + get_amount0_delta(&sqrt_ratio_60, &sqrt_ratio_upper, &BigUint::from(E18), false),
+            get_amounts_in_range(&sqrt_price, 0, &liquidity, &ticks, &sqrt_price, &sqrt_ratio_upper).0
+        );
+    }
+}
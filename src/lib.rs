@@ -2,40 +2,49 @@ extern crate core;
 
 pub mod abi;
 mod ast;
+mod config;
 mod db;
 mod eth;
 mod filtering;
+mod fixed_point;
 mod key;
 mod math;
+mod oracle;
 mod pb;
 mod price;
 mod rpc;
+mod seed;
 mod storage;
-mod ticks_idx;
 mod utils;
 
+use crate::config::Config;
 use crate::ethpb::v2::{Block, StorageChange};
 use crate::pb::uniswap;
 use crate::pb::uniswap::events::pool_event::Type;
-use crate::pb::uniswap::events::pool_event::Type::{Burn as BurnEvent, Mint as MintEvent, Swap as SwapEvent};
+use crate::pb::uniswap::events::pool_event::Type::{
+    Burn as BurnEvent, Collect as CollectEvent, Mint as MintEvent, Swap as SwapEvent,
+};
 use crate::pb::uniswap::events::position_event::Type::{
     CollectPosition, CreatedPosition, DecreaseLiquidityPosition, IncreaseLiquidityPosition, TransferPosition,
 };
 use crate::pb::uniswap::events::{PoolSqrtPrice, PositionEvent};
-use crate::pb::uniswap::{events, Events};
-use crate::pb::uniswap::{Erc20Token, Erc20Tokens, Pool, Pools};
-use crate::price::WHITELIST_TOKENS;
-use crate::utils::{ERROR_POOL, UNISWAP_V3_FACTORY};
+use crate::pb::uniswap::{events, factory_events, Events, FactoryEvents};
+use crate::pb::uniswap::{
+    pool_liquidity_depth, Erc20Token, Erc20Tokens, FeeGrowthInsides, OracleObservations, Pool, PoolLiquidityDepth,
+    PoolLiquidityDepths, Pools, SeedSnapshot, TicksCrossed,
+};
 use pb::sinkfiles::Lines;
-use std::ops::{Div, Mul, Sub};
+use std::collections::HashSet;
+use std::ops::{Add, Div, Mul, Sub};
 use substreams::errors::Error;
-use substreams::key::key_first_segment_in;
+use substreams::key::{key_first_segment_in, key_first_segments_in, key_last_segment_in, operations_eq, operations_ne};
 use substreams::pb::substreams::{store_delta, Clock};
 use substreams::prelude::*;
 use substreams::scalar::{BigDecimal, BigInt};
 use substreams::store::{
-    DeltaArray, DeltaBigDecimal, DeltaBigInt, DeltaProto, StoreAddBigDecimal, StoreAddBigInt, StoreAppend,
-    StoreGetBigDecimal, StoreGetBigInt, StoreGetProto, StoreGetRaw, StoreSetBigDecimal, StoreSetBigInt, StoreSetProto,
+    DeltaArray, DeltaBigDecimal, DeltaBigInt, DeltaInt64, DeltaProto, StoreAddBigDecimal, StoreAddBigInt, StoreAppend,
+    StoreGetArray, StoreGetBigDecimal, StoreGetBigInt, StoreGetProto, StoreGetRaw, StoreSetBigDecimal, StoreSetBigInt,
+    StoreSetProto,
 };
 use substreams::{log, Hex};
 use substreams_entity_change::pb::entity::EntityChanges;
@@ -43,34 +52,36 @@ use substreams_entity_change::tables::Tables;
 use substreams_ethereum::{pb::eth as ethpb, Event as EventTrait};
 
 #[substreams::handlers::map]
-pub fn map_pools_created(block: Block) -> Result<Pools, Error> {
+pub fn map_pools_created(params: String, block: Block) -> Result<Pools, Error> {
     use abi::factory::events::PoolCreated;
 
+    let config = Config::get(&params);
+
     Ok(Pools {
         pools: block
-            .events::<PoolCreated>(&[&UNISWAP_V3_FACTORY])
+            .events::<PoolCreated>(&config.factory_addresses())
             .filter_map(|(event, log)| {
                 log::info!("pool addr: {}", Hex(&event.pool));
 
-                if event.pool == ERROR_POOL {
-                    return None;
-                }
+                let factory = config.pool_factory(log.address())?;
 
                 let token0_address = Hex(&event.token0).to_string();
                 let token1_address = Hex(&event.token1).to_string();
 
-                //todo: question regarding the ignore_pool line. In the
-                // uniswap-v3 subgraph, they seem to bail out when they
-                // match the addr, should we do the same ?
+                if config.is_denylisted(&Hex(&event.pool).to_string(), &token0_address, &token1_address) {
+                    return None;
+                }
+
                 Some(Pool {
                     address: Hex(&log.data()[44..64]).to_string(),
                     transaction_id: Hex(&log.receipt.transaction.hash).to_string(),
                     created_at_block_number: block.number,
                     created_at_timestamp: block.timestamp_seconds(),
                     fee_tier: event.fee.to_string(),
+                    protocol: factory.protocol.clone(),
+                    factory: Hex(log.address()).to_string(),
                     tick_spacing: event.tick_spacing.into(),
                     log_ordinal: log.ordinal(),
-                    ignore_pool: event.pool == ERROR_POOL,
                     token0: Some(match rpc::create_uniswap_token(&token0_address) {
                         Some(mut token) => {
                             token.total_supply = rpc::token_total_supply_call(&token0_address)
@@ -102,8 +113,53 @@ pub fn map_pools_created(block: Block) -> Result<Pools, Error> {
     })
 }
 
+#[substreams::handlers::map]
+pub fn map_factory_events(params: String, block: Block) -> Result<FactoryEvents, Error> {
+    use abi::factory::events::{FeeAmountEnabled, OwnerChanged};
+
+    let config = Config::get(&params);
+    Ok(FactoryEvents {
+        owner_changes: block
+            .events::<OwnerChanged>(&config.factory_addresses())
+            .map(|(event, log)| {
+                log::info!("factory owner changed to: {}", Hex(&event.new_owner));
+                factory_events::OwnerChanged {
+                    old_owner: Hex(&event.old_owner).to_string(),
+                    new_owner: Hex(&event.new_owner).to_string(),
+                    log_ordinal: log.ordinal(),
+                    factory: Hex(log.address()).to_string(),
+                }
+            })
+            .collect(),
+        fee_amounts_enabled: block
+            .events::<FeeAmountEnabled>(&config.factory_addresses())
+            .map(|(event, log)| {
+                log::info!("fee amount enabled: {} tick spacing: {}", event.fee, event.tick_spacing);
+                factory_events::FeeAmountEnabled {
+                    fee: event.fee.to_string(),
+                    tick_spacing: event.tick_spacing.into(),
+                    log_ordinal: log.ordinal(),
+                    timestamp: block.timestamp_seconds(),
+                    block_number: block.number,
+                    factory: Hex(log.address()).to_string(),
+                }
+            })
+            .collect(),
+    })
+}
+
+// Snapshot of the stores seeding a deployment which starts after the factory deployment, only
+// emitted on the block it seeds, see `seed.rs`.
+#[substreams::handlers::map]
+pub fn map_seed_snapshot(params: String, clock: Clock) -> Result<SeedSnapshot, Error> {
+    Ok(seed::parse(&params, clock.number))
+}
+
 #[substreams::handlers::store]
-pub fn store_pools_created(pools: Pools, store: StoreSetProto<Pool>) {
+pub fn store_pools_created(seed: SeedSnapshot, pools: Pools, store: StoreSetProto<Pool>) {
+    for pool in seed.pools {
+        store.set(0, format!("pool:{}", pool.address), &pool);
+    }
     for pool in pools.pools {
         let pool_address = &pool.address;
         store.set(pool.log_ordinal, format!("pool:{pool_address}"), &pool);
@@ -111,7 +167,18 @@ pub fn store_pools_created(pools: Pools, store: StoreSetProto<Pool>) {
 }
 
 #[substreams::handlers::store]
-pub fn store_tokens(pools: Pools, store: StoreAddInt64) {
+pub fn store_tokens(seed: SeedSnapshot, pools: Pools, store: StoreAddInt64) {
+    // the tokens of the seeded pools already exist
+    for pool in seed.pools {
+        store.add_many(
+            0,
+            &vec![
+                format!("token:{}", pool.token0_ref().address()),
+                format!("token:{}", pool.token1_ref().address()),
+            ],
+            1,
+        );
+    }
     for pool in pools.pools {
         let token0_addr = pool.token0_ref().address();
         let token1_addr = pool.token1_ref().address();
@@ -125,22 +192,73 @@ pub fn store_tokens(pools: Pools, store: StoreAddInt64) {
 }
 
 #[substreams::handlers::store]
-pub fn store_pool_count(pools: Pools, store: StoreAddBigInt) {
+pub fn store_pool_count(seed: SeedSnapshot, pools: Pools, store: StoreAddBigInt) {
+    for pool in seed.pools {
+        store.add_many(
+            0,
+            &vec![
+                format!("factory:poolCount"),
+                format!("feeTier:{}:{}:poolCount", pool.factory, pool.fee_tier),
+            ],
+            &BigInt::one(),
+        )
+    }
+    for pool in pools.pools {
+        store.add_many(
+            pool.log_ordinal,
+            &vec![
+                format!("factory:poolCount"),
+                format!("feeTier:{}:{}:poolCount", pool.factory, pool.fee_tier),
+            ],
+            &BigInt::one(),
+        )
+    }
+}
+
+// Counts the updates of the `Factory` and `Bundle` singletons, `graph_out` creates them on their first
+// update instead of at a given block so that any start block and chain works. The pools update the
+// `Factory` of the first factory, which holds the totals of all of them, and the owner changes the one
+// of the emitting factory.
+#[substreams::handlers::store]
+pub fn store_singletons(
+    params: String,
+    seed: SeedSnapshot,
+    pools: Pools,
+    factory_events: FactoryEvents,
+    eth_prices_deltas: Deltas<DeltaBigDecimal>,
+    store: StoreAddInt64,
+) {
+    let factory_key = format!("factory:{}", Config::get(&params).factory_address());
+    if !seed.pools.is_empty() {
+        store.add(0, &factory_key, 1);
+    }
     for pool in pools.pools {
-        store.add(pool.log_ordinal, format!("factory:poolCount"), &BigInt::one())
+        store.add(pool.log_ordinal, &factory_key, 1);
+    }
+    for owner_change in factory_events.owner_changes {
+        store.add(owner_change.log_ordinal, format!("factory:{}", owner_change.factory), 1);
+    }
+    for delta in eth_prices_deltas.deltas.iter().filter(key_first_segment_in("bundle")) {
+        store.add(delta.ordinal, "bundle", 1);
     }
 }
 
 #[substreams::handlers::map]
-pub fn map_tokens_whitelist_pools(pools: Pools) -> Result<Erc20Tokens, Error> {
+pub fn map_tokens_whitelist_pools(params: String, seed: SeedSnapshot, pools: Pools) -> Result<Erc20Tokens, Error> {
+    let config = Config::get(&params);
     let mut tokens = vec![];
 
-    for pool in pools.pools {
+    // the seeded pools are the whitelist pools of their tokens as well, used for the derived prices
+    for pool in seed.pools.into_iter().chain(pools.pools) {
+        if config.is_denylisted_pool(&pool) {
+            continue;
+        }
+
         let mut token0 = pool.token0();
         let mut token1 = pool.token1();
 
-        let token0_whitelisted = WHITELIST_TOKENS.contains(&token0.address.as_str());
-        let token1_whitelisted = WHITELIST_TOKENS.contains(&token1.address.as_str());
+        let token0_whitelisted = config.is_whitelisted(&token0.address);
+        let token1_whitelisted = config.is_whitelisted(&token1.address);
 
         if token0_whitelisted {
             log::info!("adding pool: {} to token: {}", pool.address, token1.address);
@@ -166,21 +284,39 @@ pub fn store_tokens_whitelist_pools(tokens: Erc20Tokens, output_append: StoreApp
 }
 
 #[substreams::handlers::map]
-pub fn map_extract_data_types(block: Block, pools_store: StoreGetProto<Pool>) -> Result<Events, Error> {
-    _map_extract_data_types(block, pools_store)
+pub fn map_extract_data_types(
+    params: String,
+    block: Block,
+    pools_store: StoreGetProto<Pool>,          /* store_pools_created */
+    ticks_liquidity_net_store: StoreGetBigInt, /* store_ticks_liquidity_net */
+) -> Result<Events, Error> {
+    _map_extract_data_types(block, pools_store, ticks_liquidity_net_store, Config::get(&params))
 }
 
-pub fn _map_extract_data_types(block: Block, pools_store: StoreGetProto<Pool>) -> Result<Events, Error> {
+pub fn _map_extract_data_types(
+    block: Block,
+    pools_store: StoreGetProto<Pool>,
+    ticks_liquidity_net_store: StoreGetBigInt,
+    config: &Config,
+) -> Result<Events, Error> {
     let mut events = Events::default();
 
     let mut pool_sqrt_prices: Vec<events::PoolSqrtPrice> = vec![];
     let mut pool_liquidities: Vec<events::PoolLiquidity> = vec![];
     let mut fee_growth_global_updates: Vec<events::FeeGrowthGlobal> = vec![];
+    let mut fee_growth_inside_updates: Vec<events::FeeGrowthInside> = vec![];
+    let mut fee_growth_outside_updates: Vec<events::FeeGrowthOutside> = vec![];
     let mut pool_events: Vec<events::PoolEvent> = vec![];
     let mut transactions: Vec<events::Transaction> = vec![];
-    // let mut flashes: Vec<events::Flash> = vec![];
+    let mut flashes: Vec<events::Flash> = vec![];
+    let mut fee_protocol_updates: Vec<events::FeeProtocol> = vec![];
+    let mut protocol_fees_updates: Vec<events::ProtocolFees> = vec![];
+    let mut protocol_collects: Vec<events::CollectProtocol> = vec![];
     let mut ticks_created: Vec<events::TickCreated> = vec![];
     let mut ticks_updated: Vec<events::TickUpdated> = vec![];
+    let mut ticks_crossed: Vec<events::TickCrossed> = vec![];
+    let mut ticks_cleared: Vec<events::TickCleared> = vec![];
+    let mut pool_positions_updated: Vec<events::PoolPositionUpdated> = vec![];
 
     let mut positions_created: Vec<events::CreatedPosition> = vec![];
     let mut positions_increase_liquidity: Vec<events::IncreaseLiquidityPosition> = vec![];
@@ -201,41 +337,99 @@ pub fn _map_extract_data_types(block: Block, pools_store: StoreGetProto<Pool>) -
             }
             let pool = pool_opt.unwrap();
             filtering::extract_pool_sqrt_prices(&mut pool_sqrt_prices, log, pool_address);
-            filtering::extract_pool_liquidities(&mut pool_liquidities, log, &call_view.call.storage_changes, &pool);
+            filtering::extract_pool_liquidities(
+                &mut pool_liquidities,
+                log,
+                &call_view.call.storage_changes,
+                &pool,
+                config,
+            );
             filtering::extract_fee_growth_update(
                 &mut fee_growth_global_updates,
                 log,
                 &call_view.call.storage_changes,
                 &pool,
+                config,
+            );
+            filtering::extract_fee_growth_inside_and_outside(
+                &mut fee_growth_inside_updates,
+                &mut fee_growth_outside_updates,
+                log,
+                &call_view.call.storage_changes,
+                &pool,
+                config,
             );
 
             filtering::extract_pool_events_and_positions(
                 &mut pool_events,
                 &mut ticks_created,
                 &mut ticks_updated,
+                &mut ticks_crossed,
+                &mut ticks_cleared,
                 &mut positions_created,
-                &mut positions_increase_liquidity,
-                &mut positions_decrease_liquidity,
-                &mut positions_collect,
-                &mut positions_transfer,
                 &transactions_id,
                 &Hex(&trx.from).to_string(),
                 log,
                 &call_view,
                 &pool,
+                &ticks_liquidity_net_store,
                 timestamp,
                 block.number,
+                config,
             );
 
             filtering::extract_transactions(&mut transactions, log, &trx, timestamp, block.number);
 
-            // filtering::extract_flashes(&mut flashes, &log);
+            filtering::extract_flashes(
+                &mut flashes,
+                log,
+                &call_view.call.storage_changes,
+                &pool,
+                &transactions_id,
+                timestamp,
+            );
+
+            filtering::extract_pool_positions(
+                &mut pool_positions_updated,
+                log,
+                &call_view.call.storage_changes,
+                &pool,
+                &transactions_id,
+                timestamp,
+                config,
+            );
+
+            filtering::extract_fee_protocols(&mut fee_protocol_updates, log, &pool);
+            filtering::extract_protocol_fees(
+                &mut protocol_fees_updates,
+                log,
+                &call_view.call.storage_changes,
+                &pool,
+                config,
+            );
+            filtering::extract_protocol_collects(&mut protocol_collects, log, &pool);
+        }
+
+        for (log, call_view) in trx.logs_with_calls() {
+            filtering::extract_position_manager_events(
+                &mut positions_increase_liquidity,
+                &mut positions_decrease_liquidity,
+                &mut positions_collect,
+                &mut positions_transfer,
+                log,
+                &call_view.call,
+                &trx,
+                &pools_store,
+                config,
+            );
         }
     }
 
     events.pool_sqrt_prices = pool_sqrt_prices;
     events.pool_liquidities = pool_liquidities;
     events.fee_growth_global_updates = fee_growth_global_updates;
+    events.fee_growth_inside_updates = fee_growth_inside_updates;
+    events.fee_growth_outside_updates = fee_growth_outside_updates;
     events.pool_events = pool_events;
     events.transactions = transactions;
     events.created_positions = positions_created;
@@ -243,13 +437,48 @@ pub fn _map_extract_data_types(block: Block, pools_store: StoreGetProto<Pool>) -
     events.decrease_liquidity_positions = positions_decrease_liquidity;
     events.collect_positions = positions_collect;
     events.transfer_positions = positions_transfer;
-    // events.flashes = flashes;
+    events.flashes = flashes;
+    events.fee_protocol_updates = fee_protocol_updates;
+    events.protocol_fees_updates = protocol_fees_updates;
+    events.protocol_collects = protocol_collects;
     events.ticks_created = ticks_created;
     events.ticks_updated = ticks_updated;
+    events.ticks_crossed = ticks_crossed;
+    events.ticks_cleared = ticks_cleared;
+    events.pool_positions_updated = pool_positions_updated;
 
     Ok(events)
 }
 
+#[substreams::handlers::map]
+pub fn map_pool_oracle_observations(
+    block: Block,
+    pools_store: StoreGetProto<Pool>,
+) -> Result<OracleObservations, Error> {
+    let mut observations = vec![];
+    let mut states = vec![];
+
+    for trx in block.transactions() {
+        for (log, call_view) in trx.logs_with_calls() {
+            let pool_address = &Hex(&log.address).to_string();
+            let pool = match pools_store.get_last(format!("pool:{pool_address}")) {
+                None => continue,
+                Some(pool) => pool,
+            };
+
+            filtering::extract_oracle_observations(
+                &mut observations,
+                &mut states,
+                log,
+                &call_view.call.storage_changes,
+                &pool,
+            );
+        }
+    }
+
+    Ok(OracleObservations { observations, states })
+}
+
 fn collect_transaction_strings<T>(field: &[T], transaction_strings: &mut Vec<String>)
 where
     T: serde::Serialize,
@@ -261,8 +490,13 @@ where
 }
 
 #[substreams::handlers::map]
-pub fn jsonl_out(block: Block, pools_store: StoreGetProto<Pool>) -> Result<Lines, substreams::errors::Error> {
-    let results = _map_extract_data_types(block, pools_store);
+pub fn jsonl_out(
+    params: String,
+    block: Block,
+    pools_store: StoreGetProto<Pool>,          /* store_pools_created */
+    ticks_liquidity_net_store: StoreGetBigInt, /* store_ticks_liquidity_net */
+) -> Result<Lines, substreams::errors::Error> {
+    let results = _map_extract_data_types(block, pools_store, ticks_liquidity_net_store, Config::get(&params));
     let events = match results {
         Ok(e) => e,
         Err(err) => panic!("{}", err.to_string()),
@@ -278,22 +512,28 @@ pub fn jsonl_out(block: Block, pools_store: StoreGetProto<Pool>) -> Result<Lines
     collect_transaction_strings(&events.pool_events, &mut events_json);
     collect_transaction_strings(&events.transactions, &mut events_json);
     collect_transaction_strings(&events.flashes, &mut events_json);
+    collect_transaction_strings(&events.fee_protocol_updates, &mut events_json);
+    collect_transaction_strings(&events.protocol_fees_updates, &mut events_json);
+    collect_transaction_strings(&events.protocol_collects, &mut events_json);
     collect_transaction_strings(&events.ticks_created, &mut events_json);
     collect_transaction_strings(&events.ticks_updated, &mut events_json);
+    collect_transaction_strings(&events.ticks_crossed, &mut events_json);
+    collect_transaction_strings(&events.ticks_cleared, &mut events_json);
     collect_transaction_strings(&events.created_positions, &mut events_json);
     collect_transaction_strings(&events.increase_liquidity_positions, &mut events_json);
     collect_transaction_strings(&events.decrease_liquidity_positions, &mut events_json);
     collect_transaction_strings(&events.collect_positions, &mut events_json);
     collect_transaction_strings(&events.transfer_positions, &mut events_json);
 
-    let lines: Lines = Lines {
-        lines: events_json,
-    };
+    let lines: Lines = Lines { lines: events_json };
     Ok(lines)
 }
 
 #[substreams::handlers::store]
-pub fn store_pool_sqrt_price(events: Events, store: StoreSetProto<PoolSqrtPrice>) {
+pub fn store_pool_sqrt_price(seed: SeedSnapshot, events: Events, store: StoreSetProto<PoolSqrtPrice>) {
+    for sqrt_price in seed.pool_sqrt_prices {
+        store.set(0, format!("pool:{}", sqrt_price.pool_address), &sqrt_price);
+    }
     for sqrt_price in events.pool_sqrt_prices {
         let pool_address = &sqrt_price.pool_address;
         store.set(sqrt_price.ordinal, format!("pool:{pool_address}"), &sqrt_price)
@@ -301,7 +541,17 @@ pub fn store_pool_sqrt_price(events: Events, store: StoreSetProto<PoolSqrtPrice>
 }
 
 #[substreams::handlers::store]
-pub fn store_prices(clock: Clock, events: Events, pools_store: StoreGetProto<Pool>, store: StoreSetBigDecimal) {
+pub fn store_prices(
+    seed: SeedSnapshot,
+    clock: Clock,
+    events: Events,
+    pools_store: StoreGetProto<Pool>,
+    store: StoreSetBigDecimal,
+) {
+    for entry in seed.prices {
+        store.set(0, &entry.key, &BigDecimal::try_from(entry.value.as_str()).unwrap());
+    }
+
     let timestamp_seconds = clock.timestamp.unwrap().seconds;
     let day_id: i64 = timestamp_seconds / 86400;
     let hour_id: i64 = timestamp_seconds / 3600;
@@ -334,11 +584,11 @@ pub fn store_prices(clock: Clock, events: Events, pools_store: StoreGetProto<Poo
                     token1.address
                 );
 
-                let sqrt_price = BigDecimal::try_from(sqrt_price_update.sqrt_price).unwrap();
+                let sqrt_price = fixed_point::parse_uint(&sqrt_price_update.sqrt_price);
                 log::debug!("sqrtPrice: {}", sqrt_price.to_string());
 
                 let tokens_price: (BigDecimal, BigDecimal) =
-                    price::sqrt_price_x96_to_token_prices(sqrt_price, &token0, &token1);
+                    price::sqrt_price_x96_to_token_prices(&sqrt_price, &token0, &token1);
                 log::debug!("token prices: {} {}", tokens_price.0, tokens_price.1);
 
                 let token0_addr = &token0.address;
@@ -347,7 +597,7 @@ pub fn store_prices(clock: Clock, events: Events, pools_store: StoreGetProto<Poo
                     sqrt_price_update.ordinal,
                     &vec![
                         format!("pool:{pool_address}:{token0_addr}:token0"),
-                        format!("pair:{token0_addr}:{token1_addr}"), // used for find_eth_per_token
+                        format!("pair:{token0_addr}:{token1_addr}"),
                     ],
                     &tokens_price.0,
                 );
@@ -356,7 +606,7 @@ pub fn store_prices(clock: Clock, events: Events, pools_store: StoreGetProto<Poo
                     sqrt_price_update.ordinal,
                     &vec![
                         format!("pool:{pool_address}:{token1_addr}:token1"),
-                        format!("pair:{token1_addr}:{token0_addr}"), // used for find_eth_per_token
+                        format!("pair:{token1_addr}:{token0_addr}"),
                     ],
                     &tokens_price.1,
                 );
@@ -391,7 +641,136 @@ pub fn store_prices(clock: Clock, events: Events, pools_store: StoreGetProto<Poo
 }
 
 #[substreams::handlers::store]
-pub fn store_pool_liquidities(clock: Clock, events: Events, store: StoreSetBigInt) {
+pub fn store_pool_oracle_observations(oracle_observations: OracleObservations, output: StoreSetBigInt) {
+    for state in oracle_observations.states {
+        let ord = state.log_ordinal;
+        let pool_address = &state.pool_address;
+        if let Some(observation_index) = state.observation_index {
+            output.set(
+                ord,
+                format!("pool:{pool_address}:observationIndex"),
+                &BigInt::from(observation_index),
+            );
+        }
+        if let Some(observation_cardinality) = state.observation_cardinality {
+            output.set(
+                ord,
+                format!("pool:{pool_address}:observationCardinality"),
+                &BigInt::from(observation_cardinality),
+            );
+        }
+        if let Some(observation_cardinality_next) = state.observation_cardinality_next {
+            output.set(
+                ord,
+                format!("pool:{pool_address}:observationCardinalityNext"),
+                &BigInt::from(observation_cardinality_next),
+            );
+        }
+    }
+
+    for observation in oracle_observations.observations {
+        let ord = observation.log_ordinal;
+        let pool_address = &observation.pool_address;
+        let index = observation.index;
+
+        output.set(
+            ord,
+            format!("pool:{pool_address}:observation:{index}:blockTimestamp"),
+            &BigInt::from(observation.block_timestamp),
+        );
+        output.set(
+            ord,
+            format!("pool:{pool_address}:observation:{index}:tickCumulative"),
+            &BigInt::try_from(observation.tick_cumulative).unwrap(),
+        );
+        output.set(
+            ord,
+            format!("pool:{pool_address}:observation:{index}:secondsPerLiquidityCumulativeX128"),
+            &BigInt::try_from(observation.seconds_per_liquidity_cumulative_x128).unwrap(),
+        );
+        output.set(
+            ord,
+            format!("pool:{pool_address}:observation:{index}:initialized"),
+            &BigInt::from(observation.initialized as i32),
+        );
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_pool_twaps(
+    clock: Clock,
+    oracle_observations: OracleObservations, /* map_pool_oracle_observations */
+    window_ids_deltas: Deltas<DeltaInt64>,   /* store_window_ids */
+    seed_index_store: StoreGetArray<String>, /* store_seed_index */
+    oracle_store: StoreGetBigInt,            /* store_pool_oracle_observations */
+    pool_sqrt_price_store: StoreGetProto<PoolSqrtPrice>, /* store_pool_sqrt_price */
+    pools_store: StoreGetProto<Pool>,        /* store_pools_created */
+    output: StoreSetBigDecimal,
+) {
+    let timestamp = clock.timestamp.unwrap().seconds;
+
+    // (ordinal, pool address)
+    let mut updates: Vec<(u64, String)> = vec![];
+
+    // the windows slide without new observations, every pool is recomputed on the first block of an hour
+    if window_ids_deltas
+        .deltas
+        .iter()
+        .any(|delta| delta.old_value != delta.new_value)
+    {
+        for pool_address in seed_index_store.get_last("pools").unwrap_or_default() {
+            updates.push((0, pool_address));
+        }
+    }
+
+    for observation in oracle_observations.observations {
+        // growing the cardinality pre-fills the new slots with uninitialized observations
+        if !observation.initialized {
+            continue;
+        }
+        updates.push((observation.log_ordinal, observation.pool_address));
+    }
+
+    for (ord, pool_address) in updates {
+        let tick = match pool_sqrt_price_store.get_at(ord, format!("pool:{pool_address}")) {
+            Some(sqrt_price) => sqrt_price.tick.parse::<i32>().unwrap(),
+            None => continue,
+        };
+        let pool = pools_store.must_get_last(format!("pool:{pool_address}"));
+
+        for (window_name, window) in oracle::TWAP_WINDOWS {
+            let twap_tick = match oracle::twap_tick(ord, &pool_address, timestamp, tick, window, &oracle_store) {
+                Some(tick) => tick,
+                None => continue,
+            };
+            let (token0_price, token1_price) =
+                price::tick_to_token_prices(twap_tick, pool.token0_ref(), pool.token1_ref());
+
+            output.set(
+                ord,
+                format!("pool:{pool_address}:tickTwap{window_name}"),
+                &BigDecimal::from(twap_tick),
+            );
+            output.set(
+                ord,
+                format!("pool:{pool_address}:token0PriceTwap{window_name}"),
+                &token0_price,
+            );
+            output.set(
+                ord,
+                format!("pool:{pool_address}:token1PriceTwap{window_name}"),
+                &token1_price,
+            );
+        }
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_pool_liquidities(seed: SeedSnapshot, clock: Clock, events: Events, store: StoreSetBigInt) {
+    for entry in seed.pool_liquidities {
+        store.set(0, &entry.key, &BigInt::try_from(&entry.value).unwrap());
+    }
+
     let timestamp_seconds = clock.timestamp.unwrap().seconds;
     let day_id: i64 = timestamp_seconds / 86400;
     let hour_id: i64 = timestamp_seconds / 3600;
@@ -420,13 +799,24 @@ pub fn store_pool_liquidities(clock: Clock, events: Events, store: StoreSetBigIn
 }
 
 #[substreams::handlers::store]
-pub fn store_total_tx_counts(clock: Clock, events: Events, output: StoreAddBigInt) {
+pub fn store_total_tx_counts(
+    params: String,
+    seed: SeedSnapshot, /* map_seed_snapshot */
+    clock: Clock,
+    events: Events, /* map_extract_data_types */
+    output: StoreAddBigInt,
+) {
+    let config = Config::get(&params);
+    for entry in seed.tx_counts {
+        output.add(0, &entry.key, &BigInt::try_from(&entry.value).unwrap());
+    }
+
     let timestamp_seconds = clock.timestamp.unwrap().seconds;
     let day_id = timestamp_seconds / 86400;
     let hour_id = timestamp_seconds / 3600;
     let prev_day_id = day_id - 1;
     let prev_hour_id = hour_id - 1;
-    let factory_addr = Hex(UNISWAP_V3_FACTORY);
+    let factory_addr = config.factory_address();
 
     output.delete_prefix(0, &format!("UniswapDayData:{prev_day_id}:"));
     output.delete_prefix(0, &format!("PoolDayData:{prev_day_id}:"));
@@ -463,7 +853,10 @@ pub fn store_total_tx_counts(clock: Clock, events: Events, output: StoreAddBigIn
  * STORE NATIVE AMOUNTS -> spits out any mint, swap and burn amounts
  */
 #[substreams::handlers::store]
-pub fn store_native_amounts(events: Events, store: StoreSetBigDecimal) {
+pub fn store_native_amounts(seed: SeedSnapshot, events: Events, store: StoreSetBigDecimal) {
+    for entry in seed.native_amounts {
+        store.set(0, &entry.key, &BigDecimal::try_from(entry.value.as_str()).unwrap());
+    }
     for pool_event in events.pool_events {
         log::info!(
             "transaction_id: {} and type of pool event {:?}",
@@ -501,6 +894,8 @@ pub fn store_native_amounts(events: Events, store: StoreSetBigDecimal) {
 
 #[substreams::handlers::store]
 pub fn store_eth_prices(
+    params: String,
+    seed: SeedSnapshot, /* map_seed_snapshot */
     clock: Clock,
     events: Events,                                /* map_extract_data_types */
     pools_store: StoreGetProto<Pool>,              /* store_pools_created */
@@ -510,12 +905,17 @@ pub fn store_eth_prices(
     pool_liquidities_store: StoreGetBigInt,        /* store_pool_liquidities */
     output: StoreSetBigDecimal,
 ) {
+    let config = Config::get(&params);
     let timestamp_seconds = clock.timestamp.unwrap().seconds;
     let day_id = timestamp_seconds / 86400;
     let hour_id = timestamp_seconds / 3600;
     let prev_day_id = day_id - 1;
     let prev_hour_id = hour_id - 1;
 
+    for entry in seed.eth_prices {
+        output.set(0, &entry.key, &BigDecimal::try_from(entry.value.as_str()).unwrap());
+    }
+
     output.delete_prefix(0, &format!("TokenDayData:{prev_day_id}:"));
     output.delete_prefix(0, &format!("TokenHourData:{prev_hour_id}:"));
 
@@ -536,8 +936,16 @@ pub fn store_eth_prices(
         token0.log();
         token1.log();
 
-        let bundle_eth_price_usd = price::get_eth_price_in_usd(&prices_store, ord);
-        log::info!("bundle_eth_price_usd: {}", bundle_eth_price_usd);
+        let eth_price_sources = price::eth_price_sources(
+            ord,
+            &pools_store,
+            &pool_liquidities_store,
+            &total_native_amount_store,
+            &prices_store,
+            config,
+        );
+        let bundle_eth_price_usd = price::get_eth_price_in_usd(&eth_price_sources);
+        log::info!("bundle_eth_price_usd: {:?}", bundle_eth_price_usd);
 
         let token0_derived_eth_price: BigDecimal = price::find_eth_per_token(
             ord,
@@ -548,6 +956,7 @@ pub fn store_eth_prices(
             &tokens_whitelist_pools_store,
             &total_native_amount_store,
             &prices_store,
+            config,
         );
         log::info!(format!(
             "token 0 {token0_addr} derived eth price: {token0_derived_eth_price}"
@@ -562,12 +971,12 @@ pub fn store_eth_prices(
             &tokens_whitelist_pools_store,
             &total_native_amount_store,
             &prices_store,
+            config,
         );
         log::info!(format!(
             "token 1 {token1_addr} derived eth price: {token1_derived_eth_price}"
         ));
 
-        output.set(ord, "bundle", &bundle_eth_price_usd);
         output.set(
             ord,
             format!("token:{token0_addr}:dprice:eth"),
@@ -579,6 +988,38 @@ pub fn store_eth_prices(
             &token1_derived_eth_price,
         );
 
+        // without any priced pool the bundle keeps its last price, and so do the USD prices
+        let bundle_eth_price_usd = match bundle_eth_price_usd {
+            None => continue,
+            Some(price) => price,
+        };
+        output.set(ord, "bundle", &bundle_eth_price_usd);
+        // the weight of each of the `eth_price_pools` in the bundle price, zero when it has no price
+        for pool_address in &config.eth_price_pools {
+            let weight = eth_price_sources
+                .iter()
+                .find(|source| source.pool_address.eq(pool_address))
+                .map(|source| source.weight.clone())
+                .unwrap_or(BigDecimal::zero());
+            output.set(ord, format!("bundle:pool:{pool_address}"), &weight);
+        }
+
+        if config.price_stable_coins {
+            for (token_addr, derived_eth_price) in [
+                (token0_addr, &token0_derived_eth_price),
+                (token1_addr, &token1_derived_eth_price),
+            ] {
+                if !config.is_stable_coin(token_addr) || derived_eth_price.eq(&BigDecimal::zero()) {
+                    continue;
+                }
+                output.set(
+                    ord,
+                    format!("token:{token_addr}:peg:deviation"),
+                    &price::peg_deviation(derived_eth_price, &bundle_eth_price_usd),
+                );
+            }
+        }
+
         let token0_price_usd = token0_derived_eth_price.clone().mul(bundle_eth_price_usd.clone());
         let token1_price_usd = token1_derived_eth_price.clone().mul(bundle_eth_price_usd);
 
@@ -612,6 +1053,7 @@ pub fn store_eth_prices(
 
 #[substreams::handlers::store]
 pub fn store_swaps_volume(
+    params: String,
     clock: Clock,
     events: Events,
     store_pool: StoreGetProto<Pool>,
@@ -619,6 +1061,7 @@ pub fn store_swaps_volume(
     store_eth_prices: StoreGetBigDecimal,
     output: StoreAddBigDecimal,
 ) {
+    let config = Config::get(&params);
     let timestamp_seconds = clock.timestamp.unwrap().seconds;
     let day_id = timestamp_seconds / 86400;
     let hour_id = timestamp_seconds / 3600;
@@ -649,12 +1092,8 @@ pub fn store_swaps_volume(
             ),
             SwapEvent(swap) => {
                 log::info!("transaction: {}", pool.transaction_id);
-                let eth_price_in_usd: BigDecimal = match store_eth_prices.get_at(ord, "bundle") {
-                    None => {
-                        panic!("bundle eth price not found")
-                    }
-                    Some(price) => price,
-                };
+                // the bundle is only set once a stable coin pool has a price, the USD volumes are zero until then
+                let eth_price_in_usd = store_eth_prices.get_at(ord, "bundle").unwrap_or(BigDecimal::zero());
 
                 let token0_derived_eth_price =
                     match store_eth_prices.get_at(ord, format!("token:{token0_addr}:dprice:eth")) {
@@ -685,6 +1124,7 @@ pub fn store_swaps_volume(
                     &token0_derived_eth_price,
                     &token1_derived_eth_price,
                     &eth_price_in_usd,
+                    config,
                 );
 
                 log::info!("volumeAmounts.eth {}", volume_amounts.delta_tvl_eth);
@@ -801,15 +1241,115 @@ pub fn store_swaps_volume(
                 );
                 output.add(ord, format!("factory:totalFeesETH"), &fee_eth);
             }
+            CollectEvent(collect) => {
+                let amount0 = BigDecimal::try_from(collect.amount_0).unwrap();
+                let amount1 = BigDecimal::try_from(collect.amount_1).unwrap();
+                let amount_usd =
+                    utils::get_amount_usd(ord, token0_addr, token1_addr, &amount0, &amount1, &store_eth_prices);
+
+                output.add(ord, format!("pool:{pool_address}:collectedFeesToken0"), &amount0);
+                output.add(ord, format!("pool:{pool_address}:collectedFeesToken1"), &amount1);
+                output.add(ord, format!("pool:{pool_address}:collectedFeesUSD"), &amount_usd);
+            }
             _ => {}
         }
     }
 }
 
 #[substreams::handlers::store]
-pub fn store_token_tvl(events: Events, output: StoreAddBigDecimal) {
+pub fn store_fee_protocols(events: Events, output: StoreSetInt64) {
+    for fee_protocol in events.fee_protocol_updates {
+        let pool_address = &fee_protocol.pool_address;
+        output.set(
+            fee_protocol.log_ordinal,
+            format!("pool:{pool_address}:feeProtocol0"),
+            &(fee_protocol.fee_protocol_0 as i64),
+        );
+        output.set(
+            fee_protocol.log_ordinal,
+            format!("pool:{pool_address}:feeProtocol1"),
+            &(fee_protocol.fee_protocol_1 as i64),
+        );
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_protocol_fees(
+    events: Events,
+    store_eth_prices: StoreGetBigDecimal, /* store_eth_prices */
+    output: StoreAddBigDecimal,
+) {
+    for protocol_fees in events.protocol_fees_updates {
+        let ord = protocol_fees.log_ordinal;
+        let pool_address = &protocol_fees.pool_address;
+        let amount0 = BigDecimal::try_from(protocol_fees.amount_0).unwrap();
+        let amount1 = BigDecimal::try_from(protocol_fees.amount_1).unwrap();
+        let amount_usd = utils::get_amount_usd(
+            ord,
+            &protocol_fees.token0,
+            &protocol_fees.token1,
+            &amount0,
+            &amount1,
+            &store_eth_prices,
+        );
+
+        output.add(ord, format!("pool:{pool_address}:protocolFeesToken0"), &amount0);
+        output.add(ord, format!("pool:{pool_address}:protocolFeesToken1"), &amount1);
+        output.add_many(
+            ord,
+            &vec![
+                format!("pool:{pool_address}:protocolFeesUSD"),
+                format!("factory:protocolFeesUSD"),
+            ],
+            &amount_usd,
+        );
+    }
+
+    for collect in events.protocol_collects {
+        let ord = collect.log_ordinal;
+        let pool_address = &collect.pool_address;
+        let amount0 = BigDecimal::try_from(collect.amount_0).unwrap();
+        let amount1 = BigDecimal::try_from(collect.amount_1).unwrap();
+        let amount_usd = utils::get_amount_usd(
+            ord,
+            &collect.token0,
+            &collect.token1,
+            &amount0,
+            &amount1,
+            &store_eth_prices,
+        );
+
+        output.add(
+            ord,
+            format!("pool:{pool_address}:protocolFeesCollectedToken0"),
+            &amount0,
+        );
+        output.add(
+            ord,
+            format!("pool:{pool_address}:protocolFeesCollectedToken1"),
+            &amount1,
+        );
+        output.add_many(
+            ord,
+            &vec![
+                format!("pool:{pool_address}:protocolFeesCollectedUSD"),
+                format!("factory:protocolFeesCollectedUSD"),
+            ],
+            &amount_usd,
+        );
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_token_tvl(seed: SeedSnapshot, events: Events, output: StoreAddBigDecimal) {
+    for entry in seed.token_tvl {
+        output.add(0, &entry.key, &BigDecimal::try_from(entry.value.as_str()).unwrap());
+    }
     for pool_event in events.pool_events {
-        let token_amounts = pool_event.get_amounts().unwrap();
+        let token_amounts = match pool_event.get_amounts() {
+            Some(amounts) => amounts,
+            None => continue,
+        };
         let pool_address = pool_event.pool_address.to_string();
         let token0_addr = pool_event.token0.to_string();
         let token1_addr = pool_event.token1.to_string();
@@ -837,6 +1377,8 @@ pub fn store_token_tvl(events: Events, output: StoreAddBigDecimal) {
 
 #[substreams::handlers::store]
 pub fn store_derived_tvl(
+    params: String,
+    seed: SeedSnapshot, /* map_seed_snapshot */
     clock: Clock,
     events: Events,
     token_total_value_locked: StoreGetBigDecimal, /* store_token_tvl  */
@@ -844,12 +1386,18 @@ pub fn store_derived_tvl(
     eth_prices_store: StoreGetBigDecimal,
     output: StoreSetBigDecimal,
 ) {
+    let config = Config::get(&params);
     let timestamp_seconds = clock.timestamp.unwrap().seconds;
     let day_id: i64 = timestamp_seconds / 86400;
     let hour_id: i64 = timestamp_seconds / 3600;
     let prev_day_id = day_id - 1;
     let prev_hour_id = hour_id - 1;
 
+    // the deltas of the seeded pools add up to the factory's TVL in store_derived_factory_tvl
+    for entry in seed.derived_tvl {
+        output.set(0, &entry.key, &BigDecimal::try_from(entry.value.as_str()).unwrap());
+    }
+
     output.delete_prefix(0, &format!("PoolDayData:{prev_day_id}:"));
     output.delete_prefix(0, &format!("PoolHourData:{prev_hour_id}:"));
     output.delete_prefix(0, &format!("TokenDayData:{prev_day_id}:"));
@@ -902,6 +1450,7 @@ pub fn store_derived_tvl(
             &token0_derive_eth,
             &token1_derive_eth,
             &eth_price_usd,
+            config,
         );
         // let amounts_for_token = utils::get_adjusted_amounts(
         //     token0_addr,
@@ -1023,7 +1572,7 @@ fn calculate_diff(delta: &DeltaBigDecimal) -> BigDecimal {
 }
 
 #[substreams::handlers::store]
-pub fn store_ticks_liquidities(clock: Clock, events: Events, output: StoreAddBigInt) {
+pub fn store_ticks_liquidities(seed: SeedSnapshot, clock: Clock, events: Events, output: StoreAddBigInt) {
     let timestamp_seconds = clock.timestamp.unwrap().seconds;
     let day_id = timestamp_seconds / 86400;
     let hour_id = timestamp_seconds / 3600;
@@ -1033,72 +1582,1185 @@ pub fn store_ticks_liquidities(clock: Clock, events: Events, output: StoreAddBig
     output.delete_prefix(0, &format!("TickDayData:{prev_day_id}:"));
     output.delete_prefix(0, &format!("TickHourData:{prev_hour_id}:"));
 
+    // the snapshot sums of the windows the seed block is still in, the others are deleted on the next block
+    for entry in seed.tick_liquidities {
+        output.add(0, entry.key, &BigInt::try_from(entry.value).unwrap());
+    }
+
+    // (ordinal, keys, delta), the keys are prefixes to delete when there is no delta
+    let mut changes: Vec<(u64, Vec<String>, Option<BigInt>)> = vec![];
+
     for event in events.pool_events {
         let pool = event.pool_address;
-        match event.r#type.unwrap() {
-            Type::Mint(mint) => {
-                let tick_lower = &mint.tick_lower;
-                let tick_upper = &mint.tick_upper;
-                output.add_many(
-                    event.log_ordinal,
-                    &vec![
-                        format!("tick:{pool}:{tick_lower}:liquidityGross"),
-                        format!("tick:{pool}:{tick_lower}:liquidityNet"),
-                        format!("tick:{pool}:{tick_upper}:liquidityGross"),
-                        format!("TickDayData:{day_id}:{pool}:{tick_lower}:liquidityGross"),
-                        format!("TickDayData:{day_id}:{pool}:{tick_lower}:liquidityNet"),
-                        format!("TickDayData:{day_id}:{pool}:{tick_upper}:liquidityGross"),
-                        format!("TickHourData:{hour_id}:{pool}:{tick_lower}:liquidityGross"),
-                        format!("TickHourData:{hour_id}:{pool}:{tick_lower}:liquidityNet"),
-                        format!("TickHourData:{hour_id}:{pool}:{tick_upper}:liquidityGross"),
-                    ],
-                    &BigInt::try_from(mint.amount.clone()).unwrap(),
-                );
-                output.add_many(
-                    event.log_ordinal,
-                    &vec![
-                        format!("tick:{pool}:{tick_upper}:liquidityNet"),
-                        format!("TickDayData:{day_id}:{pool}:{tick_upper}:liquidityNet"),
-                        format!("TickHourData:{hour_id}:{pool}:{tick_upper}:liquidityNet"),
-                    ],
-                    &BigInt::try_from(mint.amount.clone()).unwrap().neg(),
+        let (tick_lower, tick_upper, amount) = match event.r#type.unwrap() {
+            Type::Mint(mint) => (mint.tick_lower, mint.tick_upper, BigInt::try_from(mint.amount).unwrap()),
+            Type::Burn(burn) => (
+                burn.tick_lower,
+                burn.tick_upper,
+                BigInt::try_from(burn.amount).unwrap().neg(),
+            ),
+            _ => continue,
+        };
+        changes.push((
+            event.log_ordinal,
+            vec![
+                format!("TickDayData:{day_id}:{pool}:{tick_lower}:liquidityGross"),
+                format!("TickDayData:{day_id}:{pool}:{tick_lower}:liquidityNet"),
+                format!("TickDayData:{day_id}:{pool}:{tick_upper}:liquidityGross"),
+                format!("TickHourData:{hour_id}:{pool}:{tick_lower}:liquidityGross"),
+                format!("TickHourData:{hour_id}:{pool}:{tick_lower}:liquidityNet"),
+                format!("TickHourData:{hour_id}:{pool}:{tick_upper}:liquidityGross"),
+            ],
+            Some(amount.clone()),
+        ));
+        changes.push((
+            event.log_ordinal,
+            vec![
+                format!("TickDayData:{day_id}:{pool}:{tick_upper}:liquidityNet"),
+                format!("TickHourData:{hour_id}:{pool}:{tick_upper}:liquidityNet"),
+            ],
+            Some(amount.neg()),
+        ));
+    }
+
+    for tick in events.ticks_cleared {
+        let pool = tick.pool_address;
+        let tick_idx = tick.idx;
+        changes.push((
+            tick.log_ordinal,
+            vec![
+                format!("TickDayData:{day_id}:{pool}:{tick_idx}:"),
+                format!("TickHourData:{hour_id}:{pool}:{tick_idx}:"),
+            ],
+            None,
+        ));
+    }
+
+    // the clear of a tick comes after the burn emptying it, the sort is stable
+    changes.sort_by(|x, y| x.0.cmp(&y.0));
+    for (ordinal, keys, delta) in changes {
+        match delta {
+            Some(delta) => output.add_many(ordinal, &keys, &delta),
+            None => keys.iter().for_each(|key| output.delete_prefix(ordinal as i64, key)),
+        }
+    }
+}
+
+// liquidityNet of the initialized ticks, keyed like in store_ticks. Swaps don't write the liquidityNet of the
+// ticks they cross, map_extract_data_types reads it here to give their liquidity delta, so this store is built
+// from the block itself.
+#[substreams::handlers::store]
+pub fn store_ticks_liquidity_net(
+    seed: SeedSnapshot,               /* map_seed_snapshot */
+    block: Block,                     /* sf.ethereum.type.v2.Block */
+    pools_store: StoreGetProto<Pool>, /* store_pools_created */
+    output: StoreSetBigInt,
+) {
+    for entry in seed.ticks {
+        if entry.key.ends_with(":liquidityNet") {
+            output.set(0, entry.key, &BigInt::try_from(entry.value).unwrap());
+        }
+    }
+
+    for trx in block.transactions() {
+        for (log, call_view) in trx.logs_with_calls() {
+            let pool_address = Hex(&log.address).to_string();
+            let pool = match pools_store.get_last(format!("pool:{pool_address}")) {
+                Some(pool) => pool,
+                None => continue,
+            };
+
+            let ticks = filtering::extract_ticks_liquidity_net(log, &call_view.call.storage_changes, &pool);
+            for (tick_idx, liquidity_net) in ticks {
+                let key = format!("tick:{pool_address}:{tick_idx}:liquidityNet");
+                match liquidity_net {
+                    Some(liquidity_net) => output.set(log.ordinal, key, &liquidity_net),
+                    None => output.delete_prefix(log.ordinal as i64, &key),
+                }
+            }
+        }
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_ticks(seed: SeedSnapshot, events: Events, output: StoreSetBigInt) {
+    // (ordinal, key, value), a cleared tick has all its keys deleted
+    let mut changes: Vec<(u64, String, Option<String>)> = vec![];
+
+    for entry in seed.ticks {
+        changes.push((0, entry.key, Some(entry.value)));
+    }
+
+    for tick in events.ticks_updated {
+        let prefix = format!("tick:{}:{}", tick.pool_address, tick.idx);
+        for (field, value) in [
+            ("liquidityGross", tick.liquidity_gross),
+            ("liquidityNet", tick.liquidity_net),
+            ("feeGrowthOutside0X128", tick.fee_growth_outside_0x_128),
+            ("feeGrowthOutside1X128", tick.fee_growth_outside_1x_128),
+            ("tickCumulativeOutside", tick.tick_cumulative_outside),
+            (
+                "secondsPerLiquidityOutsideX128",
+                tick.seconds_per_liquidity_outside_x128,
+            ),
+            ("secondsOutside", tick.seconds_outside),
+        ] {
+            if value.len() != 0 {
+                changes.push((tick.log_ordinal, format!("{prefix}:{field}"), Some(value)));
+            }
+        }
+    }
+
+    for tick in events.ticks_crossed {
+        let prefix = format!("tick:{}:{}", tick.pool_address, tick.idx);
+        for (field, value) in [
+            ("feeGrowthOutside0X128", tick.fee_growth_outside_0x_128),
+            ("feeGrowthOutside1X128", tick.fee_growth_outside_1x_128),
+            ("tickCumulativeOutside", tick.tick_cumulative_outside),
+            (
+                "secondsPerLiquidityOutsideX128",
+                tick.seconds_per_liquidity_outside_x128,
+            ),
+            ("secondsOutside", tick.seconds_outside),
+        ] {
+            if value.len() != 0 {
+                changes.push((tick.log_ordinal, format!("{prefix}:{field}"), Some(value)));
+            }
+        }
+    }
+
+    for tick in events.ticks_cleared {
+        changes.push((
+            tick.log_ordinal,
+            format!("tick:{}:{}:", tick.pool_address, tick.idx),
+            None,
+        ));
+    }
+
+    // a cleared tick can be initialized again later in the block, the sort is stable so
+    // the clear of a burn still comes after the updates of the same burn
+    changes.sort_by(|x, y| x.0.cmp(&y.0));
+    for (ordinal, key, value) in changes {
+        match value {
+            Some(value) => output.set(ordinal, key, &BigInt::try_from(value).unwrap()),
+            None => output.delete_prefix(ordinal as i64, &key),
+        }
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_pool_fee_growth_globals(events: Events, output: StoreSetBigInt) {
+    for update in events.fee_growth_global_updates {
+        let pool_address = &update.pool_address;
+        let token_idx = update.token_idx;
+        output.set(
+            update.ordinal,
+            format!("pool:{pool_address}:feeGrowthGlobal{token_idx}X128"),
+            &BigInt::try_from(&update.new_value).unwrap(),
+        );
+    }
+}
+
+// Number of NFT positions with liquidity of each pool
+#[substreams::handlers::store]
+pub fn store_pool_open_positions(
+    positions_fee_state_deltas: Deltas<DeltaBigInt>, /* store_positions_fee_state */
+    positions_store: StoreGetProto<PositionEvent>,   /* store_positions */
+    output: StoreAddInt64,
+) {
+    for delta in positions_fee_state_deltas
+        .deltas
+        .iter()
+        .filter(key_last_segment_in("liquidity"))
+    {
+        let opened = delta.old_value == BigInt::zero() && delta.new_value > BigInt::zero();
+        let closed = delta.old_value > BigInt::zero() && delta.new_value == BigInt::zero();
+        if !opened && !closed {
+            continue;
+        }
+
+        let token_id = key::segment(&delta.key, 1);
+        if let Some(PositionEvent {
+            r#type: Some(CreatedPosition(position)),
+        }) = positions_store.get_last(format!("position_created:{token_id}"))
+        {
+            output.add(
+                delta.ordinal,
+                format!("pool:{}:openPositions", position.pool),
+                if opened { 1 } else { -1 },
+            );
+        }
+    }
+}
+
+// Index of the open NFT positions of each pool. A store can't remove an item from a list, a position is
+// appended when it gets liquidity and the list of a pool is dropped once none of its positions has any left,
+// readers skip the positions closed in the meantime, see `moved_open_positions`.
+#[substreams::handlers::store]
+pub fn store_pool_positions(
+    positions_fee_state_deltas: Deltas<DeltaBigInt>, /* store_positions_fee_state */
+    pool_open_positions_deltas: Deltas<DeltaInt64>,  /* store_pool_open_positions */
+    positions_store: StoreGetProto<PositionEvent>,   /* store_positions */
+    output: StoreAppend<String>,
+) {
+    // (ordinal, pool, token id to append or None to drop the list of the pool)
+    let mut changes: Vec<(u64, String, Option<String>)> = vec![];
+    for delta in positions_fee_state_deltas
+        .deltas
+        .iter()
+        .filter(key_last_segment_in("liquidity"))
+        .filter(|delta| delta.old_value == BigInt::zero() && delta.new_value > BigInt::zero())
+    {
+        let token_id = key::segment(&delta.key, 1);
+        if let Some(PositionEvent {
+            r#type: Some(CreatedPosition(position)),
+        }) = positions_store.get_last(format!("position_created:{token_id}"))
+        {
+            changes.push((delta.ordinal, position.pool, Some(token_id.to_string())));
+        }
+    }
+    for delta in pool_open_positions_deltas.deltas.iter() {
+        let pool_address = key::segment(&delta.key, 1).to_string();
+        if delta.new_value == 0 {
+            changes.push((delta.ordinal, pool_address, None));
+        }
+    }
+
+    changes.sort_by(|x, y| x.0.cmp(&y.0));
+    for (ordinal, pool_address, token_id) in changes {
+        let key = format!("pool:{pool_address}:positions");
+        match token_id {
+            Some(token_id) => output.append(ordinal, key, token_id),
+            None => output.delete_prefix(ordinal as i64, &key),
+        }
+    }
+}
+
+// Last hour in which the price or the fee growth of each pool moved, keyed by `pool:{pool}`
+#[substreams::handlers::store]
+pub fn store_pool_moved_hours(clock: Clock, events: Events, output: StoreSetInt64) {
+    let hour_id = clock.timestamp.unwrap().seconds / 3600;
+
+    let mut pools: Vec<(u64, String)> = vec![];
+    pools.extend(
+        events
+            .pool_sqrt_prices
+            .into_iter()
+            .map(|sqrt_price| (sqrt_price.ordinal, sqrt_price.pool_address)),
+    );
+    pools.extend(
+        events
+            .fee_growth_global_updates
+            .into_iter()
+            .map(|update| (update.ordinal, update.pool_address)),
+    );
+    // the first move of each pool in the block
+    pools.sort_by(|x, y| x.1.cmp(&y.1).then(x.0.cmp(&y.0)));
+    pools.dedup_by(|x, y| x.1 == y.1);
+    pools.sort_by(|x, y| x.0.cmp(&y.0));
+
+    for (ordinal, pool_address) in pools {
+        output.set(ordinal, format!("pool:{pool_address}"), &hour_id);
+    }
+}
+
+// Pools whose price or fee growth moved during each hour, keyed by `hour:{hour_id}:pools`, each pool being
+// appended once on its first move of the hour. The lists of the hours before the previous one are deleted.
+#[substreams::handlers::store]
+pub fn store_moved_pools(
+    clock: Clock,
+    pool_moved_hours_deltas: Deltas<DeltaInt64>, /* store_pool_moved_hours */
+    output: StoreAppend<String>,
+) {
+    let hour_id = clock.timestamp.unwrap().seconds / 3600;
+    output.delete_prefix(0, &format!("hour:{}:", hour_id - 2));
+
+    for delta in pool_moved_hours_deltas
+        .deltas
+        .iter()
+        .filter(|delta| delta.old_value != delta.new_value)
+    {
+        output.append(
+            delta.ordinal,
+            format!("hour:{}:pools", delta.new_value),
+            key::segment(&delta.key, 1).to_string(),
+        );
+    }
+}
+
+// token ids of the open NFT positions of the pools whose price or fee growth moved during the hour which just
+// ended, the others keep the amounts and fees computed on their last move. Skips the closed positions and the
+// duplicates left in the index of store_pool_positions.
+fn moved_open_positions(
+    window_ids_deltas: &Deltas<DeltaInt64>,
+    moved_pools_store: &StoreGetArray<String>,
+    pool_positions_store: &StoreGetArray<String>,
+    positions_fee_state_store: &StoreGetBigInt,
+) -> Vec<String> {
+    let ended_hour_id = match window_ids_deltas
+        .deltas
+        .iter()
+        .find(|delta| delta.key == "hour" && delta.old_value != delta.new_value)
+    {
+        Some(delta) => delta.old_value,
+        None => return vec![],
+    };
+
+    let mut pool_addresses = moved_pools_store
+        .get_last(format!("hour:{ended_hour_id}:pools"))
+        .unwrap_or_default();
+    pool_addresses.sort();
+    pool_addresses.dedup();
+
+    let mut token_ids: Vec<String> = vec![];
+    for pool_address in pool_addresses {
+        // dropped once the pool has no open position left
+        if let Some(pool_token_ids) = pool_positions_store.get_last(format!("pool:{pool_address}:positions")) {
+            token_ids.extend(pool_token_ids);
+        }
+    }
+    token_ids.sort();
+    token_ids.dedup();
+
+    token_ids
+        .into_iter()
+        .filter(|token_id| {
+            positions_fee_state_store
+                .get_last(format!("position:{token_id}:liquidity"))
+                .unwrap_or(BigInt::zero())
+                .gt(&BigInt::zero())
+        })
+        .collect()
+}
+
+#[substreams::handlers::store]
+pub fn store_positions_fee_state(seed: SeedSnapshot, events: Events, output: StoreSetBigInt) {
+    for entry in seed.position_fee_states {
+        output.set(0, entry.key, &BigInt::try_from(entry.value).unwrap());
+    }
+
+    // (ordinal, token id, values of seed::POSITION_FEE_STATE_FIELDS)
+    let mut changes: Vec<(u64, String, [Option<String>; 5])> = vec![];
+    for p in events.created_positions {
+        let values = [
+            None,
+            p.fee_growth_inside0_last_x128,
+            p.fee_growth_inside1_last_x128,
+            None,
+            None,
+        ];
+        changes.push((p.log_ordinal, p.token_id, values));
+    }
+    for p in events.increase_liquidity_positions {
+        let values = [
+            p.total_liquidity,
+            p.fee_growth_inside0_last_x128,
+            p.fee_growth_inside1_last_x128,
+            p.tokens_owed0,
+            p.tokens_owed1,
+        ];
+        changes.push((p.log_ordinal, p.token_id, values));
+    }
+    for p in events.decrease_liquidity_positions {
+        let values = [
+            p.total_liquidity,
+            p.fee_growth_inside0_last_x128,
+            p.fee_growth_inside1_last_x128,
+            p.tokens_owed0,
+            p.tokens_owed1,
+        ];
+        changes.push((p.log_ordinal, p.token_id, values));
+    }
+    for p in events.collect_positions {
+        let values = [
+            p.total_liquidity,
+            p.fee_growth_inside0_last_x128,
+            p.fee_growth_inside1_last_x128,
+            p.tokens_owed0,
+            p.tokens_owed1,
+        ];
+        changes.push((p.log_ordinal, p.token_id, values));
+    }
+
+    changes.sort_by(|x, y| x.0.cmp(&y.0));
+    for (ordinal, token_id, values) in changes {
+        for (field, value) in seed::POSITION_FEE_STATE_FIELDS.iter().zip(values) {
+            if let Some(value) = value {
+                output.set(
+                    ordinal,
+                    format!("position:{token_id}:{field}"),
+                    &BigInt::try_from(value).unwrap(),
                 );
             }
-            Type::Burn(burn) => {
-                let tick_lower = &burn.tick_lower;
-                let tick_upper = &burn.tick_upper;
-                output.add_many(
-                    event.log_ordinal,
-                    &vec![
-                        format!("tick:{pool}:{tick_lower}:liquidityGross"),
-                        format!("tick:{pool}:{tick_lower}:liquidityNet"),
-                        format!("tick:{pool}:{tick_upper}:liquidityGross"),
-                        format!("TickDayData:{day_id}:{pool}:{tick_lower}:liquidityGross"),
-                        format!("TickDayData:{day_id}:{pool}:{tick_lower}:liquidityNet"),
-                        format!("TickDayData:{day_id}:{pool}:{tick_upper}:liquidityGross"),
-                        format!("TickHourData:{hour_id}:{pool}:{tick_lower}:liquidityGross"),
-                        format!("TickHourData:{hour_id}:{pool}:{tick_lower}:liquidityNet"),
-                        format!("TickHourData:{hour_id}:{pool}:{tick_upper}:liquidityGross"),
-                    ],
-                    &BigInt::try_from(&burn.amount).unwrap().neg(),
+        }
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_pool_position_updates(events: Events, output: StoreAddInt64) {
+    for position in events.pool_positions_updated {
+        output.add(
+            position.log_ordinal,
+            format!("poolPosition:{}:{}", position.pool_address, position.position_key),
+            1,
+        );
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_pool_position_states(events: Events, output: StoreSetBigInt) {
+    for position in events.pool_positions_updated {
+        let prefix = format!("poolPosition:{}:{}", position.pool_address, position.position_key);
+        for (field, value) in [
+            ("liquidity", position.liquidity),
+            ("feeGrowthInside0LastX128", position.fee_growth_inside0_last_x128),
+            ("feeGrowthInside1LastX128", position.fee_growth_inside1_last_x128),
+            ("tokensOwed0", position.tokens_owed0),
+            ("tokensOwed1", position.tokens_owed1),
+        ] {
+            if let Some(value) = value {
+                output.set(
+                    position.log_ordinal,
+                    format!("{prefix}:{field}"),
+                    &BigInt::try_from(value).unwrap(),
                 );
-                output.add_many(
-                    event.log_ordinal,
-                    &vec![
-                        format!("tick:{pool}:{tick_upper}:liquidityNet"),
-                        format!("TickDayData:{day_id}:{pool}:{tick_upper}:liquidityNet"),
-                        format!("TickHourData:{hour_id}:{pool}:{tick_upper}:liquidityNet"),
-                    ],
-                    &BigInt::try_from(&burn.amount).unwrap(),
+            }
+        }
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_positions_uncollected_fees(
+    events: Events,                                      /* map_extract_data_types */
+    window_ids_deltas: Deltas<DeltaInt64>,               /* store_window_ids */
+    positions_store: StoreGetProto<PositionEvent>,       /* store_positions */
+    positions_fee_state_store: StoreGetBigInt,           /* store_positions_fee_state */
+    pool_positions_store: StoreGetArray<String>,         /* store_pool_positions */
+    pool_fee_growth_globals_store: StoreGetBigInt,       /* store_pool_fee_growth_globals */
+    ticks_store: StoreGetBigInt,                         /* store_ticks */
+    pool_sqrt_price_store: StoreGetProto<PoolSqrtPrice>, /* store_pool_sqrt_price */
+    pools_store: StoreGetProto<Pool>,                    /* store_pools_created */
+    eth_prices_store: StoreGetBigDecimal,                /* store_eth_prices */
+    moved_pools_store: StoreGetArray<String>,            /* store_moved_pools */
+    output: StoreSetBigDecimal,
+) {
+    // positions whose liquidity or fees owed changed
+    let mut token_ids: Vec<String> = vec![];
+    token_ids.extend(events.increase_liquidity_positions.into_iter().map(|p| p.token_id));
+    token_ids.extend(events.decrease_liquidity_positions.into_iter().map(|p| p.token_id));
+    token_ids.extend(events.collect_positions.into_iter().map(|p| p.token_id));
+
+    // the fees earned by the swaps since, on the first block of an hour for the open positions of the pools
+    // whose fee growth moved during the hour
+    token_ids.extend(moved_open_positions(
+        &window_ids_deltas,
+        &moved_pools_store,
+        &pool_positions_store,
+        &positions_fee_state_store,
+    ));
+
+    token_ids.sort();
+    token_ids.dedup();
+
+    for token_id in token_ids {
+        let position = match positions_store.get_last(format!("position_created:{token_id}")) {
+            Some(PositionEvent {
+                r#type: Some(CreatedPosition(position)),
+            }) => position,
+            _ => continue,
+        };
+
+        let pool_address = &position.pool;
+        let tick_current = match pool_sqrt_price_store.get_last(format!("pool:{pool_address}")) {
+            Some(sqrt_price) => sqrt_price.tick.parse::<i32>().unwrap(),
+            None => continue,
+        };
+        let pool = pools_store.must_get_last(format!("pool:{pool_address}"));
+        let tick_lower = position.tick_lower.parse::<i32>().unwrap();
+        let tick_upper = position.tick_upper.parse::<i32>().unwrap();
+
+        let get_position = |field: &str| {
+            positions_fee_state_store
+                .get_last(format!("position:{token_id}:{field}"))
+                .unwrap_or(BigInt::zero())
+        };
+        let liquidity = get_position("liquidity");
+
+        let mut uncollected_fees: Vec<BigDecimal> = vec![];
+        for (token_idx, token) in [(0, pool.token0_ref()), (1, pool.token1_ref())] {
+            let fee_growth_inside = utils::get_fee_growth_inside(
+                pool_address,
+                tick_current,
+                tick_lower,
+                tick_upper,
+                token_idx,
+                &pool_fee_growth_globals_store,
+                &ticks_store,
+            );
+            let fees = math::uncollected_fees(
+                &liquidity,
+                &fee_growth_inside,
+                &get_position(&format!("feeGrowthInside{token_idx}LastX128")),
+                &get_position(&format!("tokensOwed{token_idx}")),
+            );
+            uncollected_fees.push(fees.to_decimal(token.decimals));
+        }
+
+        let token0_derived_eth_price = eth_prices_store
+            .get_last(format!("token:{}:dprice:eth", position.token0))
+            .unwrap_or(BigDecimal::zero());
+        let token1_derived_eth_price = eth_prices_store
+            .get_last(format!("token:{}:dprice:eth", position.token1))
+            .unwrap_or(BigDecimal::zero());
+        let bundle_eth_price = eth_prices_store.get_last("bundle").unwrap_or(BigDecimal::zero());
+        let uncollected_fees_usd = utils::calculate_amount_usd(
+            &uncollected_fees[0],
+            &uncollected_fees[1],
+            &token0_derived_eth_price,
+            &token1_derived_eth_price,
+            &bundle_eth_price,
+        );
+
+        output.set(
+            0,
+            format!("position:{token_id}:uncollectedFeesToken0"),
+            &uncollected_fees[0],
+        );
+        output.set(
+            0,
+            format!("position:{token_id}:uncollectedFeesToken1"),
+            &uncollected_fees[1],
+        );
+        output.set(
+            0,
+            format!("position:{token_id}:uncollectedFeesUSD"),
+            &uncollected_fees_usd,
+        );
+    }
+}
+
+// Running totals of the value put into and taken out of the NFT positions, in USD at the prices of each
+// event. A seeded position only accounts for its events after the seed.
+#[substreams::handlers::store]
+pub fn store_positions_accounting(
+    events: Events,                                /* map_extract_data_types */
+    positions_store: StoreGetProto<PositionEvent>, /* store_positions */
+    eth_prices_store: StoreGetBigDecimal,          /* store_eth_prices */
+    output: StoreAddBigDecimal,
+) {
+    let get_position = |token_id: &String| match positions_store.get_last(format!("position_created:{token_id}")) {
+        Some(PositionEvent {
+            r#type: Some(CreatedPosition(position)),
+        }) => Some(position),
+        _ => None,
+    };
+    let amount_usd_at = |ordinal: u64, position: &events::CreatedPosition, amount0: &String, amount1: &String| {
+        let get_price = |key: String| eth_prices_store.get_at(ordinal, key).unwrap_or(BigDecimal::zero());
+        utils::calculate_amount_usd(
+            &BigDecimal::try_from(amount0.as_str()).unwrap(),
+            &BigDecimal::try_from(amount1.as_str()).unwrap(),
+            &get_price(format!("token:{}:dprice:eth", position.token0)),
+            &get_price(format!("token:{}:dprice:eth", position.token1)),
+            &get_price("bundle".to_string()),
+        )
+    };
+
+    for event in events.increase_liquidity_positions {
+        let token_id = &event.token_id;
+        let position = match get_position(token_id) {
+            Some(position) => position,
+            None => continue,
+        };
+        let ord = event.log_ordinal;
+        let deposited_usd = amount_usd_at(ord, &position, &event.deposited_token0, &event.deposited_token1);
+
+        output.add(
+            ord,
+            format!("position:{token_id}:depositedLiquidity"),
+            &BigDecimal::try_from(event.liquidity.as_str()).unwrap(),
+        );
+        output.add(
+            ord,
+            format!("position:{token_id}:depositedAmount0"),
+            &BigDecimal::try_from(event.deposited_token0.as_str()).unwrap(),
+        );
+        output.add(
+            ord,
+            format!("position:{token_id}:depositedAmount1"),
+            &BigDecimal::try_from(event.deposited_token1.as_str()).unwrap(),
+        );
+        output.add(ord, format!("position:{token_id}:depositedUSD"), &deposited_usd);
+    }
+
+    for event in events.decrease_liquidity_positions {
+        let token_id = &event.token_id;
+        let position = match get_position(token_id) {
+            Some(position) => position,
+            None => continue,
+        };
+        let ord = event.log_ordinal;
+        let withdrawn_usd = amount_usd_at(ord, &position, &event.withdrawn_token0, &event.withdrawn_token1);
+
+        output.add(
+            ord,
+            format!("position:{token_id}:withdrawnLiquidity"),
+            &BigDecimal::try_from(event.liquidity.as_str()).unwrap(),
+        );
+        output.add(ord, format!("position:{token_id}:withdrawnUSD"), &withdrawn_usd);
+    }
+
+    // a collect pays out the fees along with the principal withdrawn before it
+    for event in events.collect_positions {
+        let token_id = &event.token_id;
+        let position = match get_position(token_id) {
+            Some(position) => position,
+            None => continue,
+        };
+        let ord = event.log_ordinal;
+        let collected_usd = amount_usd_at(
+            ord,
+            &position,
+            &event.collected_fees_token0,
+            &event.collected_fees_token1,
+        );
+
+        output.add(ord, format!("position:{token_id}:collectedUSD"), &collected_usd);
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_window_ids(clock: Clock, output: StoreSetInt64) {
+    let timestamp_seconds = clock.timestamp.unwrap().seconds;
+    output.set(0, "day", &(timestamp_seconds / 86400));
+    output.set(0, "hour", &(timestamp_seconds / 3600));
+}
+
+#[substreams::handlers::store]
+pub fn store_positions_amounts(
+    events: Events,                                       /* map_extract_data_types */
+    window_ids_deltas: Deltas<DeltaInt64>,                /* store_window_ids */
+    positions_store: StoreGetProto<PositionEvent>,        /* store_positions */
+    positions_fee_state_store: StoreGetBigInt,            /* store_positions_fee_state */
+    pool_positions_store: StoreGetArray<String>,          /* store_pool_positions */
+    pool_sqrt_price_store: StoreGetProto<PoolSqrtPrice>,  /* store_pool_sqrt_price */
+    pools_store: StoreGetProto<Pool>,                     /* store_pools_created */
+    eth_prices_store: StoreGetBigDecimal,                 /* store_eth_prices */
+    positions_accounting_store: StoreGetBigDecimal,       /* store_positions_accounting */
+    positions_uncollected_fees_store: StoreGetBigDecimal, /* store_positions_uncollected_fees */
+    moved_pools_store: StoreGetArray<String>,             /* store_moved_pools */
+    output: StoreSetBigDecimal,
+) {
+    let get_liquidity = |token_id: &String| {
+        positions_fee_state_store
+            .get_last(format!("position:{token_id}:liquidity"))
+            .unwrap_or(BigInt::zero())
+    };
+
+    // positions whose liquidity or accounting changed, and transferred ones for their snapshot
+    let mut token_ids: Vec<String> = vec![];
+    token_ids.extend(events.increase_liquidity_positions.into_iter().map(|p| p.token_id));
+    token_ids.extend(events.decrease_liquidity_positions.into_iter().map(|p| p.token_id));
+    token_ids.extend(events.collect_positions.into_iter().map(|p| p.token_id));
+    token_ids.extend(events.transfer_positions.into_iter().map(|p| p.token_id));
+
+    // on the first block of an hour, and so of a day, the open positions of the pools whose price moved during
+    // the hour, valued at the prices of the window's start
+    token_ids.extend(moved_open_positions(
+        &window_ids_deltas,
+        &moved_pools_store,
+        &pool_positions_store,
+        &positions_fee_state_store,
+    ));
+
+    token_ids.sort();
+    token_ids.dedup();
+
+    for token_id in token_ids {
+        let position = match positions_store.get_last(format!("position_created:{token_id}")) {
+            Some(PositionEvent {
+                r#type: Some(CreatedPosition(position)),
+            }) => position,
+            _ => continue,
+        };
+
+        let pool_address = &position.pool;
+        let sqrt_price = match pool_sqrt_price_store.get_last(format!("pool:{pool_address}")) {
+            Some(sqrt_price) => fixed_point::parse_uint(&sqrt_price.sqrt_price),
+            None => continue,
+        };
+        let pool = pools_store.must_get_last(format!("pool:{pool_address}"));
+        let tick_lower = position.tick_lower.parse::<i32>().unwrap();
+        let tick_upper = position.tick_upper.parse::<i32>().unwrap();
+
+        let (amount0, amount1) = fixed_point::get_amounts_for_liquidity(
+            &sqrt_price,
+            &fixed_point::get_sqrt_ratio_at_tick(tick_lower),
+            &fixed_point::get_sqrt_ratio_at_tick(tick_upper),
+            &fixed_point::to_uint(&get_liquidity(&token_id)),
+        );
+        let amount0 = fixed_point::to_big_int(&amount0).to_decimal(pool.token0_ref().decimals);
+        let amount1 = fixed_point::to_big_int(&amount1).to_decimal(pool.token1_ref().decimals);
+
+        let token0_derived_eth_price = eth_prices_store
+            .get_last(format!("token:{}:dprice:eth", position.token0))
+            .unwrap_or(BigDecimal::zero());
+        let token1_derived_eth_price = eth_prices_store
+            .get_last(format!("token:{}:dprice:eth", position.token1))
+            .unwrap_or(BigDecimal::zero());
+        let bundle_eth_price = eth_prices_store.get_last("bundle").unwrap_or(BigDecimal::zero());
+        let amount_usd = utils::calculate_amount_usd(
+            &amount0,
+            &amount1,
+            &token0_derived_eth_price,
+            &token1_derived_eth_price,
+            &bundle_eth_price,
+        );
+
+        let get_accounting = |field: &str| {
+            positions_accounting_store
+                .get_last(format!("position:{token_id}:{field}"))
+                .unwrap_or(BigDecimal::zero())
+        };
+        let uncollected_fees_usd = positions_uncollected_fees_store
+            .get_last(format!("position:{token_id}:uncollectedFeesUSD"))
+            .unwrap_or(BigDecimal::zero());
+
+        // the liquidity still in the position carries the average USD cost of the liquidity deposited,
+        // and the tokens it was deposited with for the value of holding them instead
+        let liquidity = get_liquidity(&token_id).to_decimal(0);
+        let deposited_liquidity = get_accounting("depositedLiquidity");
+        let cost_per_liquidity = math::safe_div(&get_accounting("depositedUSD"), &deposited_liquidity);
+        let held_share = math::safe_div(&liquidity, &deposited_liquidity);
+        let hold_usd = utils::calculate_amount_usd(
+            &get_accounting("depositedAmount0").mul(held_share.clone()),
+            &get_accounting("depositedAmount1").mul(held_share),
+            &token0_derived_eth_price,
+            &token1_derived_eth_price,
+            &bundle_eth_price,
+        );
+
+        // collects pay out the withdrawn principal as well, realizing the cost of the liquidity withdrawn
+        let realized_pnl_usd =
+            get_accounting("collectedUSD").sub(get_accounting("withdrawnLiquidity").mul(cost_per_liquidity.clone()));
+        let unrealized_pnl_usd = amount_usd
+            .clone()
+            .add(uncollected_fees_usd)
+            .sub(liquidity.mul(cost_per_liquidity));
+        let impermanent_loss_usd = amount_usd.clone().sub(hold_usd);
+
+        output.set(0, format!("position:{token_id}:amount0"), &amount0);
+        output.set(0, format!("position:{token_id}:amount1"), &amount1);
+        output.set(0, format!("position:{token_id}:amountUSD"), &amount_usd);
+        output.set(0, format!("position:{token_id}:realizedPnlUSD"), &realized_pnl_usd);
+        output.set(0, format!("position:{token_id}:unrealizedPnlUSD"), &unrealized_pnl_usd);
+        output.set(
+            0,
+            format!("position:{token_id}:impermanentLossUSD"),
+            &impermanent_loss_usd,
+        );
+    }
+}
+
+// Fees of the pool windows and the pools' TVL sampled on the first block of each hour, the daily windows
+// are kept 30 days for the rolling yields of store_pool_yields
+#[substreams::handlers::store]
+pub fn store_pool_fee_windows(
+    clock: Clock,
+    swaps_volume_deltas: Deltas<DeltaBigDecimal>, /* store_swaps_volume */
+    window_ids_deltas: Deltas<DeltaInt64>,        /* store_window_ids */
+    seed_index_store: StoreGetArray<String>,      /* store_seed_index */
+    pool_liquidities_store: StoreGetBigInt,       /* store_pool_liquidities */
+    derived_tvl_store: StoreGetBigDecimal,        /* store_derived_tvl */
+    output: StoreAddBigDecimal,
+) {
+    let timestamp_seconds = clock.timestamp.unwrap().seconds;
+    let day_id = timestamp_seconds / 86400;
+    let hour_id = timestamp_seconds / 3600;
+    let expired_day_id = day_id - 31;
+    let prev_hour_id = hour_id - 1;
+
+    output.delete_prefix(0, &format!("PoolDayData:{expired_day_id}:"));
+    output.delete_prefix(0, &format!("PoolHourData:{prev_hour_id}:"));
+
+    // each swap adds its fees to the windows, and its fees per unit of the liquidity in range earning them
+    for delta in swaps_volume_deltas
+        .deltas
+        .iter()
+        .filter(key_first_segments_in(vec!["PoolDayData", "PoolHourData"]))
+        .filter(key_last_segment_in("feesUSD"))
+        .filter(operations_ne(store_delta::Operation::Delete))
+    {
+        let (table_name, time_id, pool_address) = key::pool_windows_id_fields(&delta.key);
+        let fees_usd = delta.new_value.clone().sub(delta.old_value.clone());
+        let liquidity = pool_liquidities_store
+            .get_at(delta.ordinal, format!("pool:{pool_address}"))
+            .unwrap_or(BigInt::zero());
+
+        output.add(
+            delta.ordinal,
+            format!("{table_name}:{time_id}:{pool_address}:feesUSD"),
+            &fees_usd,
+        );
+        output.add(
+            delta.ordinal,
+            format!("{table_name}:{time_id}:{pool_address}:feesPerLiquidityUSD"),
+            &math::safe_div(&fees_usd, &liquidity.to_decimal(0)),
+        );
+    }
+
+    if window_ids_deltas
+        .deltas
+        .iter()
+        .any(|delta| delta.key == "hour" && delta.old_value != delta.new_value)
+    {
+        for pool_address in seed_index_store.get_last("pools").unwrap_or_default() {
+            let tvl_usd = derived_tvl_store
+                .get_last(format!("pool:{pool_address}:totalValueLockedUSD"))
+                .unwrap_or(BigDecimal::zero());
+            output.add_many(
+                0,
+                &vec![
+                    format!("PoolDayData:{day_id}:{pool_address}:tvlUSD"),
+                    format!("PoolHourData:{hour_id}:{pool_address}:tvlUSD"),
+                ],
+                &tvl_usd,
+            );
+            output.add(
+                0,
+                format!("PoolDayData:{day_id}:{pool_address}:tvlSamples"),
+                &BigDecimal::one(),
+            );
+        }
+    }
+}
+
+// Fee APR of the pool windows, the annualized fees over the average of the TVL samples of the window, and
+// the 7 and 30 days rolling fees and APR of the pools over the days before the current one
+#[substreams::handlers::store]
+pub fn store_pool_yields(
+    clock: Clock,
+    swaps_volume_deltas: Deltas<DeltaBigDecimal>, /* store_swaps_volume */
+    window_ids_deltas: Deltas<DeltaInt64>,        /* store_window_ids */
+    seed_index_store: StoreGetArray<String>,      /* store_seed_index */
+    tx_count_store: StoreGetBigInt,               /* store_total_tx_counts */
+    pool_fee_windows_store: StoreGetBigDecimal,   /* store_pool_fee_windows */
+    output: StoreSetBigDecimal,
+) {
+    let timestamp_seconds = clock.timestamp.unwrap().seconds;
+    let day_id = timestamp_seconds / 86400;
+    let hour_id = timestamp_seconds / 3600;
+    let prev_day_id = day_id - 1;
+    let prev_hour_id = hour_id - 1;
+
+    output.delete_prefix(0, &format!("PoolDayData:{prev_day_id}:"));
+    output.delete_prefix(0, &format!("PoolHourData:{prev_hour_id}:"));
+
+    let is_new_window = |window: &str| {
+        window_ids_deltas
+            .deltas
+            .iter()
+            .any(|delta| delta.key == window && delta.old_value != delta.new_value)
+    };
+    let pools = seed_index_store.get_last("pools").unwrap_or_default();
+
+    // windows which earned fees, and every window on a new TVL sample
+    let mut windows: Vec<(String, String, String)> = swaps_volume_deltas
+        .deltas
+        .iter()
+        .filter(key_first_segments_in(vec!["PoolDayData", "PoolHourData"]))
+        .filter(key_last_segment_in("feesUSD"))
+        .filter(operations_ne(store_delta::Operation::Delete))
+        .map(|delta| {
+            let (table_name, time_id, pool_address) = key::pool_windows_id_fields(&delta.key);
+            (table_name.to_string(), time_id.to_string(), pool_address.to_string())
+        })
+        .collect();
+    if is_new_window("hour") {
+        for pool_address in &pools {
+            windows.push(("PoolDayData".to_string(), day_id.to_string(), pool_address.clone()));
+            windows.push(("PoolHourData".to_string(), hour_id.to_string(), pool_address.clone()));
+        }
+    }
+    windows.sort();
+    windows.dedup();
+
+    for (table_name, time_id, pool_address) in windows {
+        // the window entity only exists once the pool had a transaction in it
+        if tx_count_store
+            .get_last(format!("{table_name}:{time_id}:{pool_address}"))
+            .is_none()
+        {
+            continue;
+        }
+
+        let get_window = |field: &str| {
+            pool_fee_windows_store
+                .get_last(format!("{table_name}:{time_id}:{pool_address}:{field}"))
+                .unwrap_or(BigDecimal::zero())
+        };
+        let (average_tvl_usd, windows_per_year) = match table_name.as_str() {
+            "PoolDayData" => (math::safe_div(&get_window("tvlUSD"), &get_window("tvlSamples")), 365),
+            _ => (get_window("tvlUSD"), 365 * 24),
+        };
+        let fee_apr = math::safe_div(
+            &get_window("feesUSD").mul(BigDecimal::from(windows_per_year)),
+            &average_tvl_usd,
+        );
+
+        output.set(0, format!("{table_name}:{time_id}:{pool_address}:feeAPR"), &fee_apr);
+        output.set(
+            0,
+            format!("{table_name}:{time_id}:{pool_address}:feesPerLiquidityUSD"),
+            &get_window("feesPerLiquidityUSD"),
+        );
+    }
+
+    if !is_new_window("day") {
+        return;
+    }
+
+    for pool_address in &pools {
+        for days in [7, 30] {
+            let mut fees_usd = BigDecimal::zero();
+            let mut tvl_usd_days = BigDecimal::zero();
+            for day in day_id - days..day_id {
+                let get_day = |field: &str| {
+                    pool_fee_windows_store
+                        .get_last(format!("PoolDayData:{day}:{pool_address}:{field}"))
+                        .unwrap_or(BigDecimal::zero())
+                };
+                fees_usd = fees_usd.add(get_day("feesUSD"));
+                tvl_usd_days = tvl_usd_days.add(math::safe_div(&get_day("tvlUSD"), &get_day("tvlSamples")));
+            }
+            // the days before the pool had any TVL don't count
+            let fee_apr = math::safe_div(&fees_usd.clone().mul(BigDecimal::from(365)), &tvl_usd_days);
+
+            output.set(0, format!("pool:{pool_address}:feesUSD{days}d"), &fees_usd);
+            output.set(0, format!("pool:{pool_address}:feeAPR{days}d"), &fee_apr);
+        }
+    }
+}
+
+#[substreams::handlers::map]
+pub fn map_ticks_crossed(events: Events) -> Result<TicksCrossed, Error> {
+    Ok(TicksCrossed {
+        ticks_crossed: events.ticks_crossed,
+    })
+}
+
+// Fee growth inside of the tracked ranges of the pools whose fee growth global or tick feeGrowthOutside moved
+// in the block, as computed by `Tick.getFeeGrowthInside`. The tracked ranges are the ones of the pool's NFT
+// positions and the ones minted or burnt in the block, whose value at the mint or burn is also in
+// `Events.fee_growth_inside_updates`.
+#[substreams::handlers::map]
+pub fn map_fee_growth_inside(
+    events: Events,                                      /* map_extract_data_types */
+    positions_store: StoreGetProto<PositionEvent>,       /* store_positions */
+    pool_positions_store: StoreGetArray<String>,         /* store_pool_positions */
+    pool_fee_growth_globals_store: StoreGetBigInt,       /* store_pool_fee_growth_globals */
+    ticks_store: StoreGetBigInt,                         /* store_ticks */
+    pool_sqrt_price_store: StoreGetProto<PoolSqrtPrice>, /* store_pool_sqrt_price */
+) -> Result<FeeGrowthInsides, Error> {
+    // (pool, ordinal of its last fee growth update in the block)
+    let mut pools: Vec<(String, u64)> = vec![];
+    pools.extend(
+        events
+            .fee_growth_global_updates
+            .iter()
+            .map(|update| (update.pool_address.clone(), update.ordinal)),
+    );
+    pools.extend(
+        events
+            .fee_growth_outside_updates
+            .iter()
+            .map(|update| (update.pool_address.clone(), update.ordinal)),
+    );
+    pools.sort_by(|x, y| x.0.cmp(&y.0).then(y.1.cmp(&x.1)));
+    pools.dedup_by(|x, y| x.0 == y.0);
+
+    let mut fee_growth_inside_updates = vec![];
+    for (pool_address, ordinal) in pools {
+        let tick_current = match pool_sqrt_price_store.get_last(format!("pool:{pool_address}")) {
+            Some(sqrt_price) => sqrt_price.tick.parse::<i32>().unwrap(),
+            None => continue,
+        };
+
+        let mut ranges: Vec<(i32, i32)> = vec![];
+        for event in events
+            .pool_events
+            .iter()
+            .filter(|event| event.pool_address == pool_address)
+        {
+            match &event.r#type {
+                Some(MintEvent(mint)) => {
+                    ranges.push((mint.tick_lower.parse().unwrap(), mint.tick_upper.parse().unwrap()))
+                }
+                Some(BurnEvent(burn)) => {
+                    ranges.push((burn.tick_lower.parse().unwrap(), burn.tick_upper.parse().unwrap()))
+                }
+                _ => {}
+            }
+        }
+        for token_id in pool_positions_store
+            .get_last(format!("pool:{pool_address}:positions"))
+            .unwrap_or_default()
+        {
+            if let Some(PositionEvent {
+                r#type: Some(CreatedPosition(position)),
+            }) = positions_store.get_last(format!("position_created:{token_id}"))
+            {
+                ranges.push((
+                    position.tick_lower.parse().unwrap(),
+                    position.tick_upper.parse().unwrap(),
+                ));
+            }
+        }
+        ranges.sort();
+        ranges.dedup();
+
+        for (tick_lower, tick_upper) in ranges {
+            for token_idx in 0..2 {
+                let fee_growth_inside = utils::get_fee_growth_inside(
+                    &pool_address,
+                    tick_current,
+                    tick_lower,
+                    tick_upper,
+                    token_idx,
+                    &pool_fee_growth_globals_store,
+                    &ticks_store,
                 );
+                fee_growth_inside_updates.push(events::FeeGrowthInside {
+                    pool_address: pool_address.clone(),
+                    tick_lower,
+                    tick_upper,
+                    ordinal,
+                    new_value: fee_growth_inside.to_string(),
+                    token_idx,
+                    ..Default::default()
+                });
             }
-            _ => {}
         }
     }
+
+    Ok(FeeGrowthInsides {
+        fee_growth_inside_updates,
+    })
+}
+
+// Active liquidity curve of the pools touched by the block: their initialized ticks with the liquidity
+// active from each of them, and the token amounts within `liquidity_depth_bps` of the current price.
+// The ticks come from store_ticks, store_ticks_liquidities only sums the changes of the day and hour.
+#[substreams::handlers::map]
+pub fn map_pool_liquidity_depth(
+    params: String,
+    events: Events,                                      /* map_extract_data_types */
+    seed_index_store: StoreGetArray<String>,             /* store_seed_index */
+    ticks_store: StoreGetBigInt,                         /* store_ticks */
+    pool_sqrt_price_store: StoreGetProto<PoolSqrtPrice>, /* store_pool_sqrt_price */
+    pool_liquidities_store: StoreGetBigInt,              /* store_pool_liquidities */
+    pools_store: StoreGetProto<Pool>,                    /* store_pools_created */
+) -> Result<PoolLiquidityDepths, Error> {
+    let config = Config::get(&params);
+
+    let mut pool_addresses: Vec<String> = vec![];
+    pool_addresses.extend(events.pool_events.into_iter().map(|event| event.pool_address));
+    pool_addresses.extend(
+        events
+            .pool_sqrt_prices
+            .into_iter()
+            .map(|sqrt_price| sqrt_price.pool_address),
+    );
+    pool_addresses.sort();
+    pool_addresses.dedup();
+
+    let mut pool_liquidity_depths = vec![];
+    for pool_address in pool_addresses {
+        let sqrt_price = match pool_sqrt_price_store.get_last(format!("pool:{pool_address}")) {
+            Some(sqrt_price) => sqrt_price,
+            None => continue,
+        };
+        let pool = pools_store.must_get_last(format!("pool:{pool_address}"));
+        let tick_current = sqrt_price.tick.parse::<i32>().unwrap();
+        let sqrt_price_x96 = fixed_point::parse_uint(&sqrt_price.sqrt_price);
+        let liquidity = pool_liquidities_store
+            .get_last(format!("pool:{pool_address}"))
+            .unwrap_or(BigInt::zero());
+
+        let mut tick_idxs: Vec<i32> = seed_index_store
+            .get_last(format!("pool:{pool_address}:ticks"))
+            .unwrap_or_default()
+            .iter()
+            .map(|tick_idx| tick_idx.parse().unwrap())
+            .collect();
+        tick_idxs.sort();
+        tick_idxs.dedup();
+
+        // cleared ticks have no keys left
+        let ticks: Vec<(i32, BigInt)> = tick_idxs
+            .into_iter()
+            .filter_map(|tick_idx| {
+                ticks_store
+                    .get_last(format!("tick:{pool_address}:{tick_idx}:liquidityNet"))
+                    .map(|liquidity_net| (tick_idx, liquidity_net))
+            })
+            .collect();
+
+        let mut liquidity_active = BigInt::zero();
+        let depth_ticks = ticks
+            .iter()
+            .map(|(tick_idx, liquidity_net)| {
+                liquidity_active = liquidity_active.clone().add(liquidity_net.clone());
+                pool_liquidity_depth::Tick {
+                    idx: tick_idx.to_string(),
+                    liquidity_net: liquidity_net.to_string(),
+                    liquidity_active: liquidity_active.to_string(),
+                }
+            })
+            .collect();
+
+        let fixed_point_ticks: Vec<(i32, num_bigint::BigInt)> = ticks
+            .iter()
+            .map(|(tick_idx, liquidity_net)| (*tick_idx, fixed_point::to_int(liquidity_net)))
+            .collect();
+        let depths = config
+            .liquidity_depth_bps
+            .iter()
+            .map(|bps| {
+                let (amount0, amount1) = fixed_point::get_amounts_in_range(
+                    &sqrt_price_x96,
+                    tick_current,
+                    &fixed_point::to_uint(&liquidity),
+                    &fixed_point_ticks,
+                    &fixed_point::scale_sqrt_price(&sqrt_price_x96, 10000u32.saturating_sub(*bps), 10000),
+                    &fixed_point::scale_sqrt_price(&sqrt_price_x96, 10000 + bps, 10000),
+                );
+                pool_liquidity_depth::Depth {
+                    bps: *bps,
+                    amount0: fixed_point::to_big_int(&amount0)
+                        .to_decimal(pool.token0_ref().decimals)
+                        .to_string(),
+                    amount1: fixed_point::to_big_int(&amount1)
+                        .to_decimal(pool.token1_ref().decimals)
+                        .to_string(),
+                }
+            })
+            .collect();
+
+        pool_liquidity_depths.push(PoolLiquidityDepth {
+            pool_address,
+            sqrt_price: sqrt_price.sqrt_price,
+            tick: sqrt_price.tick,
+            liquidity: liquidity.to_string(),
+            ticks: depth_ticks,
+            depths,
+        });
+    }
+
+    Ok(PoolLiquidityDepths { pool_liquidity_depths })
 }
 
 #[substreams::handlers::store]
-pub fn store_positions(events: Events, output: StoreSetProto<PositionEvent>) {
+pub fn store_positions(seed: SeedSnapshot, events: Events, output: StoreSetProto<PositionEvent>) {
     let mut positions_events: Vec<PositionEvent> = vec![];
     for pos in events.created_positions {
         positions_events.push(PositionEvent {
@@ -1132,30 +2794,11 @@ pub fn store_positions(events: Events, output: StoreSetProto<PositionEvent>) {
 
     positions_events.sort_by(|x, y| x.get_ordinal().cmp(&y.get_ordinal()));
 
+    for position in seed.positions {
+        output.set(0, position.store_key(), &position);
+    }
     for position in positions_events {
-        match position.r#type.as_ref().unwrap() {
-            CreatedPosition(pos) => {
-                output.set(pos.log_ordinal, format!("position_created:{}", pos.token_id), &position)
-            }
-            IncreaseLiquidityPosition(pos) => output.set(
-                pos.log_ordinal,
-                format!("position_increase_liquidity:{}", pos.token_id),
-                &position,
-            ),
-            DecreaseLiquidityPosition(pos) => output.set(
-                pos.log_ordinal,
-                format!("position_decrease_liquidity:{}", pos.token_id),
-                &position,
-            ),
-            CollectPosition(pos) => {
-                output.set(pos.log_ordinal, format!("position_collect:{}", pos.token_id), &position)
-            }
-            TransferPosition(pos) => output.set(
-                pos.log_ordinal,
-                format!("position_transfer:{}", pos.token_id),
-                &position,
-            ),
-        }
+        output.set(position.get_ordinal(), position.store_key(), &position);
     }
 }
 
@@ -1281,8 +2924,219 @@ pub fn store_max_windows(
     }
 }
 
+// Pools and ticks of each pool, the keys at which map_seed_snapshot_export reads the stores. A tick
+// is listed again when it is initialized after having been cleared.
+#[substreams::handlers::store]
+pub fn store_seed_index(
+    pools_deltas: Deltas<DeltaProto<Pool>>, /* store_pools_created */
+    ticks_deltas: Deltas<DeltaBigInt>,      /* store_ticks */
+    output: StoreAppend<String>,
+) {
+    for delta in pools_deltas
+        .deltas
+        .iter()
+        .filter(operations_eq(store_delta::Operation::Create))
+    {
+        output.append(delta.ordinal, "pools", delta.new_value.address.clone());
+    }
+
+    for delta in ticks_deltas
+        .deltas
+        .iter()
+        .filter(key_last_segment_in("liquidityGross"))
+        .filter(operations_eq(store_delta::Operation::Create))
+    {
+        let pool_address = key::segment(&delta.key, 1);
+        let tick_idx = key::segment(&delta.key, 2);
+        output.append(
+            delta.ordinal,
+            format!("pool:{pool_address}:ticks"),
+            tick_idx.to_string(),
+        );
+    }
+}
+
+// Exports the seeded stores at the end of the block as the params of map_seed_snapshot, seeding a
+// deployment which starts on the next block. Meant to be run on a single block.
+#[substreams::handlers::map]
+pub fn map_seed_snapshot_export(
+    params: String,
+    clock: Clock,
+    seed_index_store: StoreGetArray<String>,             /* store_seed_index */
+    pools_store: StoreGetProto<Pool>,                    /* store_pools_created */
+    pool_liquidities_store: StoreGetBigInt,              /* store_pool_liquidities */
+    prices_store: StoreGetBigDecimal,                    /* store_prices */
+    eth_prices_store: StoreGetBigDecimal,                /* store_eth_prices */
+    ticks_store: StoreGetBigInt,                         /* store_ticks */
+    positions_store: StoreGetProto<PositionEvent>,       /* store_positions */
+    pool_positions_store: StoreGetArray<String>,         /* store_pool_positions */
+    pool_sqrt_price_store: StoreGetProto<PoolSqrtPrice>, /* store_pool_sqrt_price */
+    native_amounts_store: StoreGetBigDecimal,            /* store_native_amounts */
+    token_tvl_store: StoreGetBigDecimal,                 /* store_token_tvl */
+    derived_tvl_store: StoreGetBigDecimal,               /* store_derived_tvl */
+    tx_counts_store: StoreGetBigInt,                     /* store_total_tx_counts */
+    ticks_liquidities_store: StoreGetBigInt,             /* store_ticks_liquidities */
+    positions_fee_state_store: StoreGetBigInt,           /* store_positions_fee_state */
+) -> Result<Lines, Error> {
+    let config = Config::get(&params);
+    let timestamp_seconds = clock.timestamp.unwrap().seconds;
+    let day_id = timestamp_seconds / 86400;
+    let hour_id = timestamp_seconds / 3600;
+    let mut snapshot = SeedSnapshot {
+        block_number: clock.number,
+        ..Default::default()
+    };
+    let mut tokens: HashSet<String> = HashSet::new();
+
+    let bundle_key = "bundle".to_string();
+    snapshot
+        .eth_prices
+        .extend(seed::entry(&bundle_key, eth_prices_store.get_last(&bundle_key)));
+
+    let factory_key = format!("factory:{}", config.factory_address());
+    snapshot
+        .tx_counts
+        .extend(seed::entry(&factory_key, tx_counts_store.get_last(&factory_key)));
+
+    for pool_address in seed_index_store.get_last("pools").unwrap_or_default() {
+        let pool = pools_store.must_get_last(format!("pool:{pool_address}"));
+        let token0_addr = pool.token0_ref().address().clone();
+        let token1_addr = pool.token1_ref().address().clone();
+
+        for key in [
+            format!("pool:{pool_address}"),
+            format!("pair:{token0_addr}:{token1_addr}"),
+            format!("pair:{token1_addr}:{token0_addr}"),
+        ] {
+            let value = pool_liquidities_store.get_last(&key);
+            snapshot.pool_liquidities.extend(seed::entry(&key, value));
+        }
+
+        for key in [
+            format!("pool:{pool_address}:{token0_addr}:token0"),
+            format!("pool:{pool_address}:{token1_addr}:token1"),
+            format!("pair:{token0_addr}:{token1_addr}"),
+            format!("pair:{token1_addr}:{token0_addr}"),
+        ] {
+            let value = prices_store.get_last(&key);
+            snapshot.prices.extend(seed::entry(&key, value));
+        }
+
+        snapshot
+            .pool_sqrt_prices
+            .extend(pool_sqrt_price_store.get_last(format!("pool:{pool_address}")));
+
+        for key in [
+            format!("pool:{pool_address}:{token0_addr}:native"),
+            format!("pool:{pool_address}:{token1_addr}:native"),
+        ] {
+            let value = native_amounts_store.get_last(&key);
+            snapshot.native_amounts.extend(seed::entry(&key, value));
+        }
+
+        for key in [
+            format!("pool:{pool_address}:{token0_addr}:token0"),
+            format!("pool:{pool_address}:{token1_addr}:token1"),
+        ] {
+            let value = token_tvl_store.get_last(&key);
+            snapshot.token_tvl.extend(seed::entry(&key, value));
+        }
+
+        for field in [
+            "totalValueLockedETH",
+            "totalValueLockedUSD",
+            "totalValueLockedETHUntracked",
+            "totalValueLockedUSDUntracked",
+        ] {
+            let key = format!("pool:{pool_address}:{field}");
+            let value = derived_tvl_store.get_last(&key);
+            snapshot.derived_tvl.extend(seed::entry(&key, value));
+        }
+
+        let key = format!("pool:{pool_address}");
+        let value = tx_counts_store.get_last(&key);
+        snapshot.tx_counts.extend(seed::entry(&key, value));
+
+        for token_addr in [token0_addr, token1_addr] {
+            if tokens.insert(token_addr.clone()) {
+                for key in [
+                    format!("token:{token_addr}:dprice:eth"),
+                    format!("token:{token_addr}:peg:deviation"),
+                ] {
+                    let value = eth_prices_store.get_last(&key);
+                    snapshot.eth_prices.extend(seed::entry(&key, value));
+                }
+
+                let key = format!("token:{token_addr}:native");
+                let value = native_amounts_store.get_last(&key);
+                snapshot.native_amounts.extend(seed::entry(&key, value));
+
+                let key = format!("token:{token_addr}");
+                let value = token_tvl_store.get_last(&key);
+                snapshot.token_tvl.extend(seed::entry(&key, value));
+
+                let key = format!("token:{token_addr}:totalValueLockedUSD");
+                let value = derived_tvl_store.get_last(&key);
+                snapshot.derived_tvl.extend(seed::entry(&key, value));
+
+                let key = format!("token:{token_addr}");
+                let value = tx_counts_store.get_last(&key);
+                snapshot.tx_counts.extend(seed::entry(&key, value));
+            }
+        }
+
+        let mut tick_idxs = seed_index_store
+            .get_last(format!("pool:{pool_address}:ticks"))
+            .unwrap_or_default();
+        tick_idxs.sort();
+        tick_idxs.dedup();
+        for tick_idx in tick_idxs {
+            // cleared ticks have no keys left
+            for field in seed::TICK_FIELDS {
+                let key = format!("tick:{pool_address}:{tick_idx}:{field}");
+                let value = ticks_store.get_last(&key);
+                snapshot.ticks.extend(seed::entry(&key, value));
+            }
+
+            for window in [format!("TickDayData:{day_id}"), format!("TickHourData:{hour_id}")] {
+                for field in ["liquidityGross", "liquidityNet"] {
+                    let key = format!("{window}:{pool_address}:{tick_idx}:{field}");
+                    let value = ticks_liquidities_store.get_last(&key);
+                    snapshot.tick_liquidities.extend(seed::entry(&key, value));
+                }
+            }
+        }
+
+        // a position is listed again each time it is reopened
+        let mut token_ids = pool_positions_store
+            .get_last(format!("pool:{pool_address}:positions"))
+            .unwrap_or_default();
+        token_ids.sort();
+        token_ids.dedup();
+        for token_id in token_ids {
+            for kind in seed::POSITION_EVENT_KINDS {
+                snapshot
+                    .positions
+                    .extend(positions_store.get_last(format!("{kind}:{token_id}")));
+            }
+            for field in seed::POSITION_FEE_STATE_FIELDS {
+                let key = format!("position:{token_id}:{field}");
+                let value = positions_fee_state_store.get_last(&key);
+                snapshot.position_fee_states.extend(seed::entry(&key, value));
+            }
+        }
+
+        snapshot.pools.push(pool);
+    }
+
+    Ok(Lines {
+        lines: vec![seed::encode(&snapshot)],
+    })
+}
+
 #[substreams::handlers::map]
 pub fn graph_out(
+    params: String,
     clock: Clock,
     pool_count_deltas: Deltas<DeltaBigInt>,              /* store_pool_count */
     tx_count_deltas: Deltas<DeltaBigInt>,                /* store_total_tx_counts deltas */
@@ -1300,31 +3154,47 @@ pub fn graph_out(
     tokens_store: StoreGetInt64,                         /* store_tokens */
     tokens_whitelist_pools_deltas: Deltas<DeltaArray<String>>, /* store_tokens_whitelist_pools */
     derived_tvl_deltas: Deltas<DeltaBigDecimal>,         /* store_derived_tvl */
-    ticks_liquidities_deltas: Deltas<DeltaBigInt>,       /* store_ticks_liquidities */
     tx_count_store: StoreGetBigInt,                      /* store_total_tx_counts */
     store_eth_prices: StoreGetBigDecimal,                /* store_eth_prices */
     store_positions: StoreGetProto<PositionEvent>,       /* store_positions */
     min_windows_deltas: Deltas<DeltaBigDecimal>,         /* store_min_windows */
     max_windows_deltas: Deltas<DeltaBigDecimal>,         /* store_max_windows */
+    fee_protocols_deltas: Deltas<DeltaInt64>,            /* store_fee_protocols */
+    protocol_fees_deltas: Deltas<DeltaBigDecimal>,       /* store_protocol_fees */
+    factory_events: FactoryEvents,                       /* map_factory_events */
+    pool_twaps_deltas: Deltas<DeltaBigDecimal>,          /* store_pool_twaps */
+    positions_uncollected_fees_deltas: Deltas<DeltaBigDecimal>, /* store_positions_uncollected_fees */
+    positions_amounts_deltas: Deltas<DeltaBigDecimal>,   /* store_positions_amounts */
+    positions_accounting_deltas: Deltas<DeltaBigDecimal>, /* store_positions_accounting */
+    pool_yields_deltas: Deltas<DeltaBigDecimal>,         /* store_pool_yields */
+    pool_position_updates_store: StoreGetInt64,          /* store_pool_position_updates */
+    pool_position_states_deltas: Deltas<DeltaBigInt>,    /* store_pool_position_states */
+    singletons_deltas: Deltas<DeltaInt64>,               /* store_singletons */
+    seed: SeedSnapshot,                                  /* map_seed_snapshot */
 ) -> Result<EntityChanges, Error> {
     let mut tables = Tables::new();
     let timestamp = clock.timestamp.unwrap().seconds;
+    let config = Config::get(&params);
+    let factory_id = config.factory_id();
 
-    if clock.number == 12369621 {
-        // FIXME: Hard-coded start block, how could we pull that from the manifest?
-        // FIXME: ideally taken from the params of the module
-        db::factory_created_factory_entity_change(&mut tables);
-        db::created_bundle_entity_change(&mut tables);
-    }
+    // created before their first update in the block, which then overwrites the initial values
+    db::singletons_created_entity_change(&mut tables, &singletons_deltas);
+    db::seed_snapshot_entity_changes(&mut tables, &seed);
 
     // Bundle
     db::bundle_store_eth_price_usd_bundle_entity_change(&mut tables, &derived_eth_prices_deltas);
 
     // Factory:
-    db::pool_created_factory_entity_change(&mut tables, &pool_count_deltas);
-    db::tx_count_factory_entity_change(&mut tables, &tx_count_deltas);
-    db::swap_volume_factory_entity_change(&mut tables, &swaps_volume_deltas);
-    db::tvl_factory_entity_change(&mut tables, &derived_factory_tvl_deltas);
+    db::pool_created_factory_entity_change(&mut tables, &pool_count_deltas, &factory_id);
+    db::tx_count_factory_entity_change(&mut tables, &tx_count_deltas, &factory_id);
+    db::swap_volume_factory_entity_change(&mut tables, &swaps_volume_deltas, &factory_id);
+    db::tvl_factory_entity_change(&mut tables, &derived_factory_tvl_deltas, &factory_id);
+    db::protocol_fees_factory_entity_change(&mut tables, &protocol_fees_deltas, &factory_id);
+    db::owner_changed_factory_entity_change(&mut tables, &factory_events.owner_changes);
+
+    // FeeTier:
+    db::fee_amount_enabled_fee_tier_entity_change(&mut tables, &factory_events.fee_amounts_enabled);
+    db::pool_count_fee_tier_entity_change(&mut tables, &pool_count_deltas);
 
     // Pool:
     db::pools_created_pool_entity_changes(&mut tables, &pools_created);
@@ -1336,6 +3206,10 @@ pub fn graph_out(
     db::price_pool_entity_change(&mut tables, &price_deltas);
     db::tx_count_pool_entity_change(&mut tables, &tx_count_deltas);
     db::swap_volume_pool_entity_change(&mut tables, &swaps_volume_deltas);
+    db::fee_protocol_pool_entity_change(&mut tables, &fee_protocols_deltas);
+    db::protocol_fees_pool_entity_change(&mut tables, &protocol_fees_deltas);
+    db::twaps_pool_entity_change(&mut tables, &pool_twaps_deltas);
+    db::yields_pool_entity_change(&mut tables, &pool_yields_deltas);
 
     // Tokens:
     db::tokens_created_token_entity_changes(&mut tables, &pools_created, tokens_store);
@@ -1345,11 +3219,12 @@ pub fn graph_out(
     db::total_value_locked_usd_token_entity_change(&mut tables, &derived_tvl_deltas);
     db::derived_eth_prices_token_entity_change(&mut tables, &derived_eth_prices_deltas);
     db::whitelist_token_entity_change(&mut tables, tokens_whitelist_pools_deltas);
+    db::depeg_events_entity_change(&mut tables, clock.number, timestamp, &derived_eth_prices_deltas, config);
 
     // Tick:
     db::create_tick_entity_change(&mut tables, &events.ticks_created);
-    db::update_tick_entity_change(&mut tables, &events.ticks_updated);
-    db::liquidities_tick_entity_change(&mut tables, &ticks_liquidities_deltas);
+    db::update_tick_entity_change(&mut tables, &events.ticks_updated, &events.ticks_crossed);
+    db::cleared_tick_entity_change(&mut tables, &events.ticks_cleared, &events.ticks_created);
 
     // Tick Day/Hour data
     // db::create_entity_tick_windows(&mut tables, &events.ticks_created);
@@ -1363,6 +3238,17 @@ pub fn graph_out(
     db::decrease_liquidity_position_entity_change(&mut tables, &events.decrease_liquidity_positions);
     db::collect_position_entity_change(&mut tables, &events.collect_positions);
     db::transfer_position_entity_change(&mut tables, &events.transfer_positions);
+    db::uncollected_fees_position_entity_change(&mut tables, &positions_uncollected_fees_deltas);
+    db::amounts_position_entity_change(&mut tables, &positions_amounts_deltas);
+    db::accounting_position_entity_change(&mut tables, &positions_accounting_deltas);
+
+    // PoolPosition:
+    db::pool_positions_create_entity_change(
+        &mut tables,
+        &events.pool_positions_updated,
+        &pool_position_updates_store,
+    );
+    db::pool_position_states_entity_change(&mut tables, &pool_position_states_deltas);
 
     // PositionSnapshot:
     // TODO: validate all the snapshot positions here
@@ -1386,16 +3272,22 @@ pub fn graph_out(
         &events.transfer_positions,
         &store_positions,
     );
+    db::pnl_snapshot_position_entity_change(&mut tables, clock.number, &events, &positions_amounts_deltas);
 
     // Transaction:
     db::transaction_entity_change(&mut tables, &events.transactions);
 
     // Swap, Mint, Burn:
-    db::swaps_mints_burns_created_entity_change(&mut tables, &events.pool_events, tx_count_store, store_eth_prices);
+    db::swaps_mints_burns_created_entity_change(
+        &mut tables,
+        &events.pool_events,
+        &tx_count_store,
+        &store_eth_prices,
+        config,
+    );
 
     // Flashes:
-    // TODO: should we implement flashes entity change - UNISWAP has not done this part
-    // db::flashes_update_pool_fee_entity_change(&mut tables, events.flashes);
+    db::flashes_created_entity_change(&mut tables, &events.flashes, &store_eth_prices);
 
     // Uniswap day data:
     db::uniswap_day_data_create(&mut tables, &tx_count_deltas);
@@ -1421,6 +3313,7 @@ pub fn graph_out(
         &derived_tvl_deltas,
         &min_windows_deltas,
         &max_windows_deltas,
+        &pool_yields_deltas,
     );
 
     // Token Day/Hour data:
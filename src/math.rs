@@ -1,28 +1,11 @@
-use crate::ticks_idx::ONE_POINT_0001;
-use std::ops::{Div, Mul};
-use substreams::scalar::BigDecimal;
+use crate::fixed_point;
+use std::ops::{Add, Div, Mul, Sub};
+use std::str::FromStr;
+use substreams::scalar::{BigDecimal, BigInt};
 
+// 1.0001^tick as seen by the pools, i.e. the square of the Q64.96 sqrt ratio of the tick
 pub fn compute_price_from_tick_idx(desired_tick_idx: i32) -> BigDecimal {
-    if desired_tick_idx == 0 {
-        return BigDecimal::one();
-    }
-
-    let desired_tick_idx_abs = desired_tick_idx.abs();
-    let base_abs = desired_tick_idx_abs - (desired_tick_idx_abs % 1000);
-    let ratio = BigDecimal::try_from(1.0001).unwrap().with_prec(100);
-    let mut val = BigDecimal::try_from(*ONE_POINT_0001.get(&base_abs).unwrap()).unwrap();
-
-    let mut idx = base_abs;
-    while idx < desired_tick_idx_abs {
-        val = val.mul(ratio.clone()).with_prec(100);
-        idx += 1;
-    }
-
-    if desired_tick_idx.lt(&0) {
-        val = safe_div(&BigDecimal::one(), &val).with_prec(100);
-    }
-
-    return val.with_prec(100);
+    fixed_point::sqrt_price_x96_to_price(&fixed_point::get_sqrt_ratio_at_tick(desired_tick_idx))
 }
 
 pub fn safe_div(amount0: &BigDecimal, amount1: &BigDecimal) -> BigDecimal {
@@ -47,18 +30,110 @@ pub fn exponent_to_big_decimal(decimals: u64) -> BigDecimal {
     return result;
 }
 
+// Fee growth per unit of liquidity inside the tick range, as computed by `Tick.getFeeGrowthInside`
+pub fn fee_growth_inside(
+    tick_current: i32,
+    tick_lower: i32,
+    tick_upper: i32,
+    fee_growth_global_x128: &BigInt,
+    fee_growth_outside_lower_x128: &BigInt,
+    fee_growth_outside_upper_x128: &BigInt,
+) -> BigInt {
+    let fee_growth_global_x128 = wrap_uint256(fee_growth_global_x128.clone());
+    let fee_growth_outside_lower_x128 = wrap_uint256(fee_growth_outside_lower_x128.clone());
+    let fee_growth_outside_upper_x128 = wrap_uint256(fee_growth_outside_upper_x128.clone());
+
+    let fee_growth_below = if tick_current >= tick_lower {
+        fee_growth_outside_lower_x128
+    } else {
+        fee_growth_global_x128.clone().sub(fee_growth_outside_lower_x128)
+    };
+    let fee_growth_above = if tick_current < tick_upper {
+        fee_growth_outside_upper_x128
+    } else {
+        fee_growth_global_x128.clone().sub(fee_growth_outside_upper_x128)
+    };
+
+    wrap_uint256(fee_growth_global_x128.sub(fee_growth_below).sub(fee_growth_above))
+}
+
+// Fees owed to a position, i.e. the tokens owed as of its last update plus the fees earned since then,
+// as computed by `Position.update`
+pub fn uncollected_fees(
+    liquidity: &BigInt,
+    fee_growth_inside_x128: &BigInt,
+    fee_growth_inside_last_x128: &BigInt,
+    tokens_owed: &BigInt,
+) -> BigInt {
+    let fee_growth_delta = wrap_uint256(
+        wrap_uint256(fee_growth_inside_x128.clone()).sub(wrap_uint256(fee_growth_inside_last_x128.clone())),
+    );
+
+    tokens_owed
+        .clone()
+        .add(liquidity.clone().mul(fee_growth_delta).div(q128()))
+}
+
+// Value at which the weights of the lower values reach half of the total weight, None when the total
+// weight isn't positive. Values are (value, weight) pairs.
+pub fn weighted_median(values: &Vec<(BigDecimal, BigDecimal)>) -> Option<BigDecimal> {
+    let mut values = values.clone();
+    values.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap());
+
+    let total_weight = values
+        .iter()
+        .fold(BigDecimal::zero(), |sum, (_, weight)| sum.add(weight.clone()));
+    if total_weight.le(&BigDecimal::zero()) {
+        return None;
+    }
+
+    let mut weight_sum = BigDecimal::zero();
+    for (value, weight) in values {
+        weight_sum = weight_sum.add(weight);
+        if weight_sum.clone().mul(BigDecimal::from(2)).ge(&total_weight) {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn q128() -> BigInt {
+    BigInt::from_str("340282366920938463463374607431768211456").unwrap()
+}
+
+fn q256() -> BigInt {
+    BigInt::from_str("115792089237316195423570985008687907853269984665640564039457584007913129639936").unwrap()
+}
+
+// the fee growth accumulators are uint256 which are allowed to overflow, and the pool's fee growth
+// globals are decoded from storage as signed values, bring everything back into [0, 2^256)
+fn wrap_uint256(value: BigInt) -> BigInt {
+    let mut value = value;
+    while value < BigInt::zero() {
+        value = value.add(q256());
+    }
+    while value >= q256() {
+        value = value.sub(q256());
+    }
+    value
+}
+
 #[cfg(test)]
 mod test {
-    use crate::math::compute_price_from_tick_idx;
+    use crate::math::{compute_price_from_tick_idx, fee_growth_inside, uncollected_fees, weighted_median};
+    use std::ops::Mul;
     use std::str::FromStr;
     use substreams::prelude::BigDecimal;
+    use substreams::scalar::BigInt;
 
+    // the prices are sqrtPriceX96^2 / 2^192 of the `TickMath.getSqrtRatioAtTick` results, to 100 digits
     #[test]
     fn test_positive_tick_idx() {
-        let tick_idx = 257820;
+        // sqrtPriceX96 31317495652248475665431435350955747
+        let tick_idx = 257760;
         let actual_value = compute_price_from_tick_idx(tick_idx);
         let expected_value = BigDecimal::from_str(
-            "157188409912.8279800665572784382799429044388135818770675416117949775512036146406973508030483126972441",
+            "156248150065.7379455762901868273547439851195637663696547433146825302633000437335975057874117059910330",
         )
         .unwrap();
         assert_eq!(expected_value, actual_value);
@@ -66,12 +141,66 @@ mod test {
 
     #[test]
     fn test_negative_tick_idx() {
+        // sqrtPriceX96 35246714534369728900268601106
         let tick_idx = -16200;
         let actual_value = compute_price_from_tick_idx(tick_idx);
         let expected_value = BigDecimal::from_str(
-            "0.1979147284588052764428880652056914101428568621377186361720748341060154174725108347202390730358454528",
+            "0.1979147284588052764428880652141991309927214906085661138506442956779146604750589603787560218716630898",
         )
         .unwrap();
         assert_eq!(expected_value, actual_value);
     }
+
+    #[test]
+    fn test_fee_growth_inside_in_range() {
+        let actual_value = fee_growth_inside(0, -60, 60, &BigInt::from(100), &BigInt::from(10), &BigInt::from(20));
+        assert_eq!(BigInt::from(70), actual_value);
+    }
+
+    #[test]
+    fn test_fee_growth_inside_below_range() {
+        let actual_value = fee_growth_inside(-120, -60, 60, &BigInt::from(100), &BigInt::from(30), &BigInt::from(10));
+        assert_eq!(BigInt::from(20), actual_value);
+    }
+
+    #[test]
+    fn test_fee_growth_inside_overflow() {
+        // the outside values were initialized when the global was lower, the difference underflows
+        let actual_value = fee_growth_inside(0, -60, 60, &BigInt::from(100), &BigInt::from(90), &BigInt::from(20));
+        let expected_value =
+            BigInt::from_str("115792089237316195423570985008687907853269984665640564039457584007913129639926").unwrap();
+        assert_eq!(expected_value, actual_value);
+    }
+
+    #[test]
+    fn test_uncollected_fees() {
+        let q128 = BigInt::from_str("340282366920938463463374607431768211456").unwrap();
+        let actual_value = uncollected_fees(
+            &BigInt::from(1000),
+            &q128.clone().mul(BigInt::from(7)),
+            &q128.mul(BigInt::from(2)),
+            &BigInt::from(42),
+        );
+        assert_eq!(BigInt::from(5042), actual_value);
+    }
+
+    #[test]
+    fn test_weighted_median() {
+        let values = vec![
+            (BigDecimal::from(3000), BigDecimal::from(10)),
+            (BigDecimal::from(100), BigDecimal::from(1)),
+            (BigDecimal::from(3010), BigDecimal::from(30)),
+            (BigDecimal::from(2990), BigDecimal::from(20)),
+        ];
+        assert_eq!(Some(BigDecimal::from(3000)), weighted_median(&values));
+    }
+
+    #[test]
+    fn test_weighted_median_without_weight() {
+        assert_eq!(None, weighted_median(&vec![]));
+        assert_eq!(
+            None,
+            weighted_median(&vec![(BigDecimal::from(3000), BigDecimal::zero())])
+        );
+    }
 }
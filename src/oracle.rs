@@ -0,0 +1,276 @@
+use substreams::scalar::BigInt;
+use substreams::store::{StoreGet, StoreGetBigInt};
+
+pub const TWAP_WINDOWS: [(&str, i64); 2] = [("30m", 1800), ("1h", 3600)];
+
+pub struct Observation {
+    pub block_timestamp: i64,
+    pub tick_cumulative: i64,
+}
+
+// Slots of a pool's observations ring buffer, None when not initialized
+trait ObservationSource {
+    fn observation(&self, index: i64) -> Option<Observation>;
+}
+
+// the ring buffer mirrored in store_pool_oracle_observations, at the ordinal being observed
+struct StoreObservations<'a> {
+    ord: u64,
+    pool_address: &'a String,
+    oracle_store: &'a StoreGetBigInt,
+}
+
+impl<'a> ObservationSource for StoreObservations<'a> {
+    fn observation(&self, index: i64) -> Option<Observation> {
+        let key = format!("pool:{}:observation:{index}", self.pool_address);
+        let initialized = self.oracle_store.get_at(self.ord, format!("{key}:initialized"))?;
+        if initialized == BigInt::zero() {
+            return None;
+        }
+
+        Some(Observation {
+            block_timestamp: to_i64(&self.oracle_store.get_at(self.ord, format!("{key}:blockTimestamp"))?),
+            tick_cumulative: to_i64(&self.oracle_store.get_at(self.ord, format!("{key}:tickCumulative"))?),
+        })
+    }
+}
+
+// Time weighted average tick of the pool over the `window` seconds preceding `timestamp`, given the
+// pool's current `tick`, rounded towards negative infinity like the `OracleLibrary.consult` of the
+// periphery contracts.
+pub fn twap_tick(
+    ord: u64,
+    pool_address: &String,
+    timestamp: i64,
+    tick: i32,
+    window: i64,
+    oracle_store: &StoreGetBigInt,
+) -> Option<i32> {
+    // the index starts at 0 and is only stored once it moved
+    let index = oracle_store
+        .get_at(ord, format!("pool:{pool_address}:observationIndex"))
+        .map(|index| to_i64(&index))
+        .unwrap_or(0);
+    let cardinality = to_i64(&oracle_store.get_at(ord, format!("pool:{pool_address}:observationCardinality"))?);
+
+    let observations = StoreObservations {
+        ord,
+        pool_address,
+        oracle_store,
+    };
+    consult(&observations, index, cardinality, timestamp, tick, window)
+}
+
+fn consult<S: ObservationSource>(
+    observations: &S,
+    index: i64,
+    cardinality: i64,
+    timestamp: i64,
+    tick: i32,
+    window: i64,
+) -> Option<i32> {
+    let tick_cumulative = observe_tick_cumulative(observations, index, cardinality, timestamp, tick, timestamp)?;
+    let past_tick_cumulative =
+        observe_tick_cumulative(observations, index, cardinality, timestamp, tick, timestamp - window)?;
+
+    let delta = tick_cumulative - past_tick_cumulative;
+    let mut tick = delta / window;
+    if delta < 0 && delta % window != 0 {
+        tick -= 1;
+    }
+    Some(tick as i32)
+}
+
+// Tick cumulative of the pool at the `target` timestamp, found by binary searching the observations
+// ring buffer, the same way `Oracle.observeSingle` does on chain. A target after the newest observation
+// is extrapolated from it with the pool's `tick` at `timestamp`, which no swap moved since.
+// Returns None when the pool's history does not go back far enough.
+fn observe_tick_cumulative<S: ObservationSource>(
+    observations: &S,
+    index: i64,
+    cardinality: i64,
+    timestamp: i64,
+    tick: i32,
+    target: i64,
+) -> Option<i64> {
+    if cardinality == 0 || target > timestamp {
+        return None;
+    }
+
+    let newest = observations.observation(index)?;
+    if target >= newest.block_timestamp {
+        return Some(newest.tick_cumulative + tick as i64 * (target - newest.block_timestamp));
+    }
+
+    // the buffer is not full yet when the slot after the newest one was never written
+    let oldest = match observations.observation((index + 1) % cardinality) {
+        Some(observation) => observation,
+        None => observations.observation(0)?,
+    };
+    if target < oldest.block_timestamp {
+        return None;
+    }
+    if target == oldest.block_timestamp {
+        return Some(oldest.tick_cumulative);
+    }
+
+    let mut l = (index + 1) % cardinality;
+    let mut r = l + cardinality - 1;
+    while l <= r {
+        let i = (l + r) / 2;
+
+        let before = match observations.observation(i % cardinality) {
+            Some(observation) => observation,
+            None => {
+                l = i + 1;
+                continue;
+            }
+        };
+        let after = observations.observation((i + 1) % cardinality)?;
+
+        let target_at_or_after = before.block_timestamp <= target;
+        if target_at_or_after && target <= after.block_timestamp {
+            let observation_time_delta = after.block_timestamp - before.block_timestamp;
+            let target_delta = target - before.block_timestamp;
+            return Some(
+                before.tick_cumulative
+                    + (after.tick_cumulative - before.tick_cumulative) / observation_time_delta * target_delta,
+            );
+        }
+
+        if !target_at_or_after {
+            r = i - 1;
+        } else {
+            l = i + 1;
+        }
+    }
+
+    None
+}
+
+// timestamps are uint32 and tick cumulatives int56 on chain, both fit in an i64
+fn to_i64(value: &BigInt) -> i64 {
+    value.to_string().parse::<i64>().unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::oracle::{consult, observe_tick_cumulative, Observation, ObservationSource};
+
+    // ring buffer slots as (block timestamp, tick cumulative)
+    struct TestObservations(Vec<Option<(i64, i64)>>);
+
+    impl ObservationSource for TestObservations {
+        fn observation(&self, index: i64) -> Option<Observation> {
+            self.0[index as usize].map(|(block_timestamp, tick_cumulative)| Observation {
+                block_timestamp,
+                tick_cumulative,
+            })
+        }
+    }
+
+    // observations every 100 seconds from 100 to 400, with ticks 10, 20 and 30 in between
+    fn full_buffer() -> TestObservations {
+        TestObservations(vec![
+            Some((100, 0)),
+            Some((200, 1000)),
+            Some((300, 3000)),
+            Some((400, 6000)),
+        ])
+    }
+
+    #[test]
+    fn test_observe_interpolates_between_observations() {
+        let observations = full_buffer();
+        let observe = |target| observe_tick_cumulative(&observations, 3, 4, 400, 0, target);
+
+        assert_eq!(Some(0), observe(100));
+        assert_eq!(Some(500), observe(150));
+        assert_eq!(Some(1000), observe(200));
+        assert_eq!(Some(2000), observe(250));
+        assert_eq!(Some(4500), observe(350));
+        assert_eq!(Some(6000), observe(400));
+        assert_eq!(None, observe(99));
+    }
+
+    #[test]
+    fn test_observe_wrapped_buffer() {
+        // the newest observation is at index 1, the oldest at index 2
+        let observations = TestObservations(vec![
+            Some((300, 3000)),
+            Some((400, 6000)),
+            Some((100, 0)),
+            Some((200, 1000)),
+        ]);
+        let observe = |target| observe_tick_cumulative(&observations, 1, 4, 400, 0, target);
+
+        assert_eq!(Some(0), observe(100));
+        assert_eq!(Some(2000), observe(250));
+        assert_eq!(Some(3000), observe(300));
+        assert_eq!(Some(4500), observe(350));
+        assert_eq!(None, observe(50));
+    }
+
+    #[test]
+    fn test_observe_buffer_not_full() {
+        // the cardinality grew to 4 but only the first two slots were written
+        let observations = TestObservations(vec![Some((100, 0)), Some((200, 1000)), None, None]);
+        let observe = |target| observe_tick_cumulative(&observations, 1, 4, 200, 0, target);
+
+        assert_eq!(Some(0), observe(100));
+        assert_eq!(Some(500), observe(150));
+        assert_eq!(Some(1000), observe(200));
+        assert_eq!(None, observe(99));
+    }
+
+    #[test]
+    fn test_observe_extrapolates_from_newest_observation() {
+        let observations = full_buffer();
+
+        // no swap since the newest observation, the tick stayed at -7
+        assert_eq!(
+            Some(6000 - 7 * 50),
+            observe_tick_cumulative(&observations, 3, 4, 500, -7, 450)
+        );
+        assert_eq!(
+            Some(6000 - 7 * 100),
+            observe_tick_cumulative(&observations, 3, 4, 500, -7, 500)
+        );
+        assert_eq!(None, observe_tick_cumulative(&observations, 3, 4, 500, -7, 501));
+        assert_eq!(None, observe_tick_cumulative(&observations, 3, 0, 500, -7, 450));
+    }
+
+    #[test]
+    fn test_observe_interpolation_truncates() {
+        // the tick cumulative grows by -10 over 3 seconds, i.e. by -3 per second once truncated like on chain
+        let observations = TestObservations(vec![Some((100, 0)), Some((103, -10))]);
+        assert_eq!(Some(-3), observe_tick_cumulative(&observations, 1, 2, 103, 0, 101));
+        assert_eq!(Some(-6), observe_tick_cumulative(&observations, 1, 2, 103, 0, 102));
+    }
+
+    #[test]
+    fn test_consult() {
+        let observations = full_buffer();
+
+        // ticks 20 and 30 over the last 200 seconds
+        assert_eq!(Some(25), consult(&observations, 3, 4, 400, 30, 200));
+        // tick 30 then the current tick, 50, for 100 seconds
+        assert_eq!(Some(40), consult(&observations, 3, 4, 500, 50, 200));
+        // the window goes back further than the oldest observation
+        assert_eq!(None, consult(&observations, 3, 4, 400, 30, 301));
+
+        // a single observation, the average is the current tick
+        let observations = TestObservations(vec![Some((100, 0))]);
+        assert_eq!(Some(-3), consult(&observations, 0, 1, 1900, -3, 1800));
+        assert_eq!(None, consult(&observations, 0, 1, 1900, -3, 1801));
+    }
+
+    #[test]
+    fn test_consult_rounds_to_negative_infinity() {
+        let observations = TestObservations(vec![Some((0, 0)), Some((10, -25))]);
+        assert_eq!(Some(-3), consult(&observations, 1, 2, 10, 0, 10));
+
+        let observations = TestObservations(vec![Some((0, 0)), Some((10, 25))]);
+        assert_eq!(Some(2), consult(&observations, 1, 2, 10, 0, 10));
+    }
+}
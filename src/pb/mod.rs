@@ -3,11 +3,12 @@ use crate::pb::uniswap::events::position_event::Type::{
     CollectPosition, CreatedPosition, DecreaseLiquidityPosition, IncreaseLiquidityPosition, TransferPosition,
 };
 use crate::pb::uniswap::events::PoolEvent;
+use crate::config::Config;
 use crate::pb::uniswap::events::PositionEvent;
-use crate::utils::ERROR_POOL;
+use crate::storage::uniswap_v3_pool::PoolLayout;
 use crate::{Erc20Token, Pool};
+use substreams::log;
 use substreams::scalar::BigDecimal;
-use substreams::{log, Hex};
 
 #[path = "sf.substreams.sink.files.v1.rs"]
 #[allow(dead_code)]
@@ -28,6 +29,17 @@ impl PositionEvent {
             TransferPosition(item) => item.log_ordinal,
         };
     }
+
+    // key of the event in store_positions, which keeps the latest event of each kind per position
+    pub fn store_key(&self) -> String {
+        return match self.r#type.as_ref().unwrap() {
+            CreatedPosition(item) => format!("position_created:{}", item.token_id),
+            IncreaseLiquidityPosition(item) => format!("position_increase_liquidity:{}", item.token_id),
+            DecreaseLiquidityPosition(item) => format!("position_decrease_liquidity:{}", item.token_id),
+            CollectPosition(item) => format!("position_collect:{}", item.token_id),
+            TransferPosition(item) => format!("position_transfer:{}", item.token_id),
+        };
+    }
 }
 
 impl Erc20Token {
@@ -43,18 +55,16 @@ impl Erc20Token {
 }
 
 impl Pool {
-    pub fn should_handle_swap(&self) -> bool {
-        if self.ignore_pool {
-            return false;
-        }
-        return &self.address != &Hex(ERROR_POOL).to_string();
+    pub fn should_handle_swap(&self, config: &Config) -> bool {
+        return !self.ignore_pool && !config.is_denylisted_pool(self);
+    }
+
+    pub fn should_handle_mint_and_burn(&self, config: &Config) -> bool {
+        return !self.ignore_pool && !config.is_denylisted_pool(self);
     }
 
-    pub fn should_handle_mint_and_burn(&self) -> bool {
-        if self.ignore_pool {
-            return false;
-        }
-        return &self.address != &Hex(ERROR_POOL).to_string();
+    pub fn storage_layout(&self) -> PoolLayout {
+        PoolLayout::for_protocol(&self.protocol)
     }
 
     pub fn token0_ref(&self) -> &Erc20Token {
@@ -120,6 +130,8 @@ impl PoolEvent {
                 token0_addr: self.token0.clone(),
                 token1_addr: self.token1.clone(),
             }),
+            // collected amounts were already removed from the pool's liquidity by the burn
+            Type::Collect(_) => None,
         };
     }
 }
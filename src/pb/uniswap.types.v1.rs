@@ -57,6 +57,12 @@ pub struct Pool {
     /// Integer
     #[prost(string, tag="7")]
     pub fee_tier: ::prost::alloc::string::String,
+    /// id of the protocol of the factory which created the pool, e.g. uniswap-v3 or pancakeswap-v3
+    #[prost(string, tag="8")]
+    pub protocol: ::prost::alloc::string::String,
+    /// address of the factory which created the pool
+    #[prost(string, tag="9")]
+    pub factory: ::prost::alloc::string::String,
     /// internals
     #[prost(int32, tag="30")]
     pub tick_spacing: i32,
@@ -70,6 +76,228 @@ pub struct Pool {
 #[derive(::serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FactoryEvents {
+    #[prost(message, repeated, tag="1")]
+    pub owner_changes: ::prost::alloc::vec::Vec<factory_events::OwnerChanged>,
+    #[prost(message, repeated, tag="2")]
+    pub fee_amounts_enabled: ::prost::alloc::vec::Vec<factory_events::FeeAmountEnabled>,
+}
+/// Nested message and enum types in `FactoryEvents`.
+pub mod factory_events {
+    #[derive(::serde::Serialize)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct OwnerChanged {
+        #[prost(string, tag="1")]
+        pub old_owner: ::prost::alloc::string::String,
+        #[prost(string, tag="2")]
+        pub new_owner: ::prost::alloc::string::String,
+        #[prost(uint64, tag="3")]
+        pub log_ordinal: u64,
+        /// address of the emitting factory
+        #[prost(string, tag="4")]
+        pub factory: ::prost::alloc::string::String,
+    }
+    #[derive(::serde::Serialize)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct FeeAmountEnabled {
+        /// Integer
+        #[prost(string, tag="1")]
+        pub fee: ::prost::alloc::string::String,
+        #[prost(int32, tag="2")]
+        pub tick_spacing: i32,
+        #[prost(uint64, tag="3")]
+        pub log_ordinal: u64,
+        #[prost(uint64, tag="4")]
+        pub timestamp: u64,
+        #[prost(uint64, tag="5")]
+        pub block_number: u64,
+        /// address of the emitting factory
+        #[prost(string, tag="6")]
+        pub factory: ::prost::alloc::string::String,
+    }
+}
+#[derive(::serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OracleObservations {
+    #[prost(message, repeated, tag="1")]
+    pub observations: ::prost::alloc::vec::Vec<OracleObservation>,
+    #[prost(message, repeated, tag="2")]
+    pub states: ::prost::alloc::vec::Vec<OracleState>,
+}
+#[derive(::serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OracleObservation {
+    #[prost(string, tag="1")]
+    pub pool_address: ::prost::alloc::string::String,
+    /// index of the observation in the pool's ring buffer
+    #[prost(uint32, tag="2")]
+    pub index: u32,
+    #[prost(uint64, tag="3")]
+    pub block_timestamp: u64,
+    /// Integer
+    #[prost(string, tag="4")]
+    pub tick_cumulative: ::prost::alloc::string::String,
+    /// Integer
+    #[prost(string, tag="5")]
+    pub seconds_per_liquidity_cumulative_x128: ::prost::alloc::string::String,
+    #[prost(bool, tag="6")]
+    pub initialized: bool,
+    #[prost(uint64, tag="7")]
+    pub log_ordinal: u64,
+}
+/// oracle fields of the pool's slot0, only set when they changed
+#[derive(::serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OracleState {
+    #[prost(string, tag="1")]
+    pub pool_address: ::prost::alloc::string::String,
+    #[prost(uint32, optional, tag="2")]
+    pub observation_index: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag="3")]
+    pub observation_cardinality: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag="4")]
+    pub observation_cardinality_next: ::core::option::Option<u32>,
+    #[prost(uint64, tag="5")]
+    pub log_ordinal: u64,
+}
+#[derive(::serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TicksCrossed {
+    #[prost(message, repeated, tag="1")]
+    pub ticks_crossed: ::prost::alloc::vec::Vec<events::TickCrossed>,
+}
+/// fee growth inside of the tracked ranges of the pools whose fee growth moved in a block, output by
+/// map_fee_growth_inside
+#[derive(::serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FeeGrowthInsides {
+    #[prost(message, repeated, tag="1")]
+    pub fee_growth_inside_updates: ::prost::alloc::vec::Vec<events::FeeGrowthInside>,
+}
+/// state of the seeded stores after `block_number`, exported by map_seed_snapshot_export and
+/// loaded by map_seed_snapshot on the following block
+#[derive(::serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SeedSnapshot {
+    #[prost(uint64, tag="1")]
+    pub block_number: u64,
+    #[prost(message, repeated, tag="2")]
+    pub pools: ::prost::alloc::vec::Vec<Pool>,
+    /// keys of store_pool_liquidities, store_prices, store_eth_prices and store_ticks with their value
+    #[prost(message, repeated, tag="3")]
+    pub pool_liquidities: ::prost::alloc::vec::Vec<seed_snapshot::Entry>,
+    #[prost(message, repeated, tag="4")]
+    pub prices: ::prost::alloc::vec::Vec<seed_snapshot::Entry>,
+    #[prost(message, repeated, tag="5")]
+    pub eth_prices: ::prost::alloc::vec::Vec<seed_snapshot::Entry>,
+    #[prost(message, repeated, tag="6")]
+    pub ticks: ::prost::alloc::vec::Vec<seed_snapshot::Entry>,
+    /// latest event of each kind of the NFT positions, as kept in store_positions
+    #[prost(message, repeated, tag="7")]
+    pub positions: ::prost::alloc::vec::Vec<events::PositionEvent>,
+    /// latest price of each pool, as kept in store_pool_sqrt_price
+    #[prost(message, repeated, tag="8")]
+    pub pool_sqrt_prices: ::prost::alloc::vec::Vec<events::PoolSqrtPrice>,
+    /// keys of store_native_amounts, store_token_tvl, store_derived_tvl and store_total_tx_counts with their value
+    #[prost(message, repeated, tag="9")]
+    pub native_amounts: ::prost::alloc::vec::Vec<seed_snapshot::Entry>,
+    #[prost(message, repeated, tag="10")]
+    pub token_tvl: ::prost::alloc::vec::Vec<seed_snapshot::Entry>,
+    #[prost(message, repeated, tag="11")]
+    pub derived_tvl: ::prost::alloc::vec::Vec<seed_snapshot::Entry>,
+    #[prost(message, repeated, tag="12")]
+    pub tx_counts: ::prost::alloc::vec::Vec<seed_snapshot::Entry>,
+    /// day and hour window keys of store_ticks_liquidities with their value
+    #[prost(message, repeated, tag="13")]
+    pub tick_liquidities: ::prost::alloc::vec::Vec<seed_snapshot::Entry>,
+    /// keys of store_positions_fee_state with their value
+    #[prost(message, repeated, tag="14")]
+    pub position_fee_states: ::prost::alloc::vec::Vec<seed_snapshot::Entry>,
+}
+/// Nested message and enum types in `SeedSnapshot`.
+pub mod seed_snapshot {
+    #[derive(::serde::Serialize)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Entry {
+        #[prost(string, tag="1")]
+        pub key: ::prost::alloc::string::String,
+        /// Integer or Decimal, depending on the store
+        #[prost(string, tag="2")]
+        pub value: ::prost::alloc::string::String,
+    }
+}
+/// active liquidity curve of the pools touched in a block, output by map_pool_liquidity_depth
+#[derive(::serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PoolLiquidityDepths {
+    #[prost(message, repeated, tag="1")]
+    pub pool_liquidity_depths: ::prost::alloc::vec::Vec<PoolLiquidityDepth>,
+}
+#[derive(::serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PoolLiquidityDepth {
+    #[prost(string, tag="1")]
+    pub pool_address: ::prost::alloc::string::String,
+    /// Integer
+    #[prost(string, tag="2")]
+    pub sqrt_price: ::prost::alloc::string::String,
+    /// Integer
+    #[prost(string, tag="3")]
+    pub tick: ::prost::alloc::string::String,
+    /// Integer, liquidity active at the current price
+    #[prost(string, tag="4")]
+    pub liquidity: ::prost::alloc::string::String,
+    /// initialized ticks in ascending order
+    #[prost(message, repeated, tag="5")]
+    pub ticks: ::prost::alloc::vec::Vec<pool_liquidity_depth::Tick>,
+    /// token amounts available around the current price, one per `liquidity_depth_bps` param
+    #[prost(message, repeated, tag="6")]
+    pub depths: ::prost::alloc::vec::Vec<pool_liquidity_depth::Depth>,
+}
+/// Nested message and enum types in `PoolLiquidityDepth`.
+pub mod pool_liquidity_depth {
+    #[derive(::serde::Serialize)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Tick {
+        /// Integer
+        #[prost(string, tag="1")]
+        pub idx: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="2")]
+        pub liquidity_net: ::prost::alloc::string::String,
+        /// Integer, liquidity active from this tick up to the next one
+        #[prost(string, tag="3")]
+        pub liquidity_active: ::prost::alloc::string::String,
+    }
+    #[derive(::serde::Serialize)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Depth {
+        #[prost(uint32, tag="1")]
+        pub bps: u32,
+        /// Decimal, amount of token0 between the current price and `bps` above it
+        #[prost(string, tag="2")]
+        pub amount0: ::prost::alloc::string::String,
+        /// Decimal, amount of token1 between `bps` below the current price and the current price
+        #[prost(string, tag="3")]
+        pub amount1: ::prost::alloc::string::String,
+    }
+}
+#[derive(::serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Events {
     #[prost(message, repeated, tag="1")]
     pub pool_sqrt_prices: ::prost::alloc::vec::Vec<events::PoolSqrtPrice>,
@@ -87,10 +315,22 @@ pub struct Events {
     pub transactions: ::prost::alloc::vec::Vec<events::Transaction>,
     #[prost(message, repeated, tag="6")]
     pub flashes: ::prost::alloc::vec::Vec<events::Flash>,
+    #[prost(message, repeated, tag="12")]
+    pub fee_protocol_updates: ::prost::alloc::vec::Vec<events::FeeProtocol>,
+    #[prost(message, repeated, tag="13")]
+    pub protocol_fees_updates: ::prost::alloc::vec::Vec<events::ProtocolFees>,
+    #[prost(message, repeated, tag="14")]
+    pub protocol_collects: ::prost::alloc::vec::Vec<events::CollectProtocol>,
     #[prost(message, repeated, tag="8")]
     pub ticks_created: ::prost::alloc::vec::Vec<events::TickCreated>,
     #[prost(message, repeated, tag="9")]
     pub ticks_updated: ::prost::alloc::vec::Vec<events::TickUpdated>,
+    #[prost(message, repeated, tag="15")]
+    pub ticks_crossed: ::prost::alloc::vec::Vec<events::TickCrossed>,
+    #[prost(message, repeated, tag="16")]
+    pub ticks_cleared: ::prost::alloc::vec::Vec<events::TickCleared>,
+    #[prost(message, repeated, tag="17")]
+    pub pool_positions_updated: ::prost::alloc::vec::Vec<events::PoolPositionUpdated>,
     #[prost(message, repeated, tag="20")]
     pub created_positions: ::prost::alloc::vec::Vec<events::CreatedPosition>,
     #[prost(message, repeated, tag="21")]
@@ -118,12 +358,14 @@ pub mod events {
         #[prost(string, tag="4")]
         pub new_value: ::prost::alloc::string::String,
     }
+    /// fee growth inside of the range of a position, per unit of liquidity, of token `token_idx`
     #[derive(::serde::Serialize)]
     #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct FeeGrowthInside {
         #[prost(string, tag="1")]
         pub pool_address: ::prost::alloc::string::String,
+        /// never set, the range is given by tick_lower and tick_upper
         #[prost(int32, tag="2")]
         pub tick_idx: i32,
         #[prost(uint64, tag="3")]
@@ -131,13 +373,21 @@ pub mod events {
         /// Integer
         #[prost(string, tag="4")]
         pub new_value: ::prost::alloc::string::String,
+        #[prost(int32, tag="5")]
+        pub tick_lower: i32,
+        #[prost(int32, tag="6")]
+        pub tick_upper: i32,
+        #[prost(int32, tag="7")]
+        pub token_idx: i32,
     }
+    /// fee growth on the other side of tick `tick_idx` from the current tick, of token `token_idx`
     #[derive(::serde::Serialize)]
     #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct FeeGrowthOutside {
         #[prost(string, tag="1")]
         pub pool_address: ::prost::alloc::string::String,
+        /// never set, the tick is given by tick_idx
         #[prost(int32, tag="2")]
         pub tick_lower: i32,
         #[prost(int32, tag="3")]
@@ -147,6 +397,10 @@ pub mod events {
         /// Integer
         #[prost(string, tag="5")]
         pub new_value: ::prost::alloc::string::String,
+        #[prost(int32, tag="6")]
+        pub tick_idx: i32,
+        #[prost(int32, tag="7")]
+        pub token_idx: i32,
     }
     #[derive(::serde::Serialize)]
     #[allow(clippy::derive_partial_eq_without_eq)]
@@ -192,6 +446,116 @@ pub mod events {
         pub fee_growth_outside_1x_128: ::prost::alloc::string::String,
         #[prost(uint64, tag="6")]
         pub timestamp: u64,
+        /// Integer
+        #[prost(string, tag="7")]
+        pub liquidity_gross: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="8")]
+        pub liquidity_net: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="9")]
+        pub tick_cumulative_outside: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="10")]
+        pub seconds_per_liquidity_outside_x128: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="11")]
+        pub seconds_outside: ::prost::alloc::string::String,
+    }
+    #[derive(::serde::Serialize)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct TickCleared {
+        #[prost(string, tag="1")]
+        pub pool_address: ::prost::alloc::string::String,
+        #[prost(string, tag="2")]
+        pub transaction_id: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="3")]
+        pub idx: ::prost::alloc::string::String,
+        #[prost(uint64, tag="4")]
+        pub log_ordinal: u64,
+        #[prost(uint64, tag="5")]
+        pub timestamp: u64,
+    }
+    #[derive(::serde::Serialize)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct TickCrossed {
+        #[prost(string, tag="1")]
+        pub pool_address: ::prost::alloc::string::String,
+        #[prost(string, tag="2")]
+        pub transaction_id: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="3")]
+        pub idx: ::prost::alloc::string::String,
+        /// true when the price moved down, i.e. the tick was crossed from right to left
+        #[prost(bool, tag="4")]
+        pub zero_for_one: bool,
+        /// Integer, liquidityNet of the tick as applied to the pool's active liquidity,
+        /// negated when the tick was crossed from right to left
+        #[prost(string, tag="5")]
+        pub liquidity_delta: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="6")]
+        pub fee_growth_outside_0x_128: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="7")]
+        pub fee_growth_outside_1x_128: ::prost::alloc::string::String,
+        #[prost(uint64, tag="8")]
+        pub log_ordinal: u64,
+        #[prost(uint64, tag="9")]
+        pub timestamp: u64,
+        /// Integer
+        #[prost(string, tag="10")]
+        pub tick_cumulative_outside: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="11")]
+        pub seconds_per_liquidity_outside_x128: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="12")]
+        pub seconds_outside: ::prost::alloc::string::String,
+    }
+    /// A position held directly on a pool, i.e. not through the NonfungiblePositionManager,
+    /// with the members of its `positions` mapping entry which changed in the call
+    #[derive(::serde::Serialize)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct PoolPositionUpdated {
+        #[prost(string, tag="1")]
+        pub pool_address: ::prost::alloc::string::String,
+        #[prost(string, tag="2")]
+        pub owner: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="3")]
+        pub tick_lower: ::prost::alloc::string::String,
+        /// Integer
+        #[prost(string, tag="4")]
+        pub tick_upper: ::prost::alloc::string::String,
+        /// keccak256(owner, tickLower, tickUpper), the key of the pool's `positions` mapping
+        #[prost(string, tag="5")]
+        pub position_key: ::prost::alloc::string::String,
+        #[prost(string, tag="6")]
+        pub transaction_id: ::prost::alloc::string::String,
+        #[prost(uint64, tag="7")]
+        pub log_ordinal: u64,
+        #[prost(uint64, tag="8")]
+        pub timestamp: u64,
+        /// BigInt
+        #[prost(string, optional, tag="9")]
+        pub liquidity: ::core::option::Option<::prost::alloc::string::String>,
+        /// BigInt
+        #[prost(string, optional, tag="10")]
+        pub fee_growth_inside0_last_x128: ::core::option::Option<::prost::alloc::string::String>,
+        /// BigInt
+        #[prost(string, optional, tag="11")]
+        pub fee_growth_inside1_last_x128: ::core::option::Option<::prost::alloc::string::String>,
+        /// BigInt
+        #[prost(string, optional, tag="12")]
+        pub tokens_owed0: ::core::option::Option<::prost::alloc::string::String>,
+        /// BigInt
+        #[prost(string, optional, tag="13")]
+        pub tokens_owed1: ::core::option::Option<::prost::alloc::string::String>,
     }
     #[derive(::serde::Serialize)]
     #[allow(clippy::derive_partial_eq_without_eq)]
@@ -232,7 +596,7 @@ pub mod events {
         pub timestamp: u64,
         #[prost(uint64, tag="108")]
         pub created_at_block_number: u64,
-        #[prost(oneof="pool_event::Type", tags="1, 2, 3")]
+        #[prost(oneof="pool_event::Type", tags="1, 2, 3, 4")]
         pub r#type: ::core::option::Option<pool_event::Type>,
     }
     /// Nested message and enum types in `PoolEvent`.
@@ -262,6 +626,8 @@ pub mod events {
             /// Integer
             #[prost(string, tag="8")]
             pub tick: ::prost::alloc::string::String,
+            #[prost(uint32, tag="9")]
+            pub ticks_crossed: u32,
         }
         #[derive(::serde::Serialize)]
         #[allow(clippy::derive_partial_eq_without_eq)]
@@ -315,6 +681,27 @@ pub mod events {
         }
         #[derive(::serde::Serialize)]
         #[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct Collect {
+            #[prost(string, tag="1")]
+            pub owner: ::prost::alloc::string::String,
+            #[prost(string, tag="2")]
+            pub recipient: ::prost::alloc::string::String,
+            /// Decimal
+            #[prost(string, tag="3")]
+            pub amount_0: ::prost::alloc::string::String,
+            /// Decimal
+            #[prost(string, tag="4")]
+            pub amount_1: ::prost::alloc::string::String,
+            /// Integer
+            #[prost(string, tag="5")]
+            pub tick_lower: ::prost::alloc::string::String,
+            /// Integer
+            #[prost(string, tag="6")]
+            pub tick_upper: ::prost::alloc::string::String,
+        }
+        #[derive(::serde::Serialize)]
+        #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Oneof)]
         pub enum Type {
             #[prost(message, tag="1")]
@@ -323,6 +710,8 @@ pub mod events {
             Burn(Burn),
             #[prost(message, tag="3")]
             Mint(Mint),
+            #[prost(message, tag="4")]
+            Collect(Collect),
         }
     }
     #[derive(::serde::Serialize)]
@@ -356,6 +745,88 @@ pub mod events {
         pub fee_growth_global_1x_128: ::prost::alloc::string::String,
         #[prost(uint64, tag="4")]
         pub log_ordinal: u64,
+        #[prost(uint64, tag="5")]
+        pub log_index: u64,
+        #[prost(string, tag="6")]
+        pub transaction_id: ::prost::alloc::string::String,
+        #[prost(uint64, tag="7")]
+        pub timestamp: u64,
+        #[prost(string, tag="8")]
+        pub token0: ::prost::alloc::string::String,
+        #[prost(string, tag="9")]
+        pub token1: ::prost::alloc::string::String,
+        #[prost(string, tag="10")]
+        pub sender: ::prost::alloc::string::String,
+        #[prost(string, tag="11")]
+        pub recipient: ::prost::alloc::string::String,
+        /// Decimal
+        #[prost(string, tag="12")]
+        pub amount_0: ::prost::alloc::string::String,
+        /// Decimal
+        #[prost(string, tag="13")]
+        pub amount_1: ::prost::alloc::string::String,
+        /// Decimal
+        #[prost(string, tag="14")]
+        pub paid_0: ::prost::alloc::string::String,
+        /// Decimal
+        #[prost(string, tag="15")]
+        pub paid_1: ::prost::alloc::string::String,
+    }
+    #[derive(::serde::Serialize)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct FeeProtocol {
+        #[prost(string, tag="1")]
+        pub pool_address: ::prost::alloc::string::String,
+        /// denominator of the share of the swap fees going to the protocol (1/x), 0 when turned off
+        #[prost(uint32, tag="2")]
+        pub fee_protocol_0: u32,
+        #[prost(uint32, tag="3")]
+        pub fee_protocol_1: u32,
+        #[prost(uint64, tag="4")]
+        pub log_ordinal: u64,
+    }
+    #[derive(::serde::Serialize)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ProtocolFees {
+        #[prost(string, tag="1")]
+        pub pool_address: ::prost::alloc::string::String,
+        #[prost(string, tag="2")]
+        pub token0: ::prost::alloc::string::String,
+        #[prost(string, tag="3")]
+        pub token1: ::prost::alloc::string::String,
+        /// Decimal, protocol fees accrued by the pool since the previous update
+        #[prost(string, tag="4")]
+        pub amount_0: ::prost::alloc::string::String,
+        /// Decimal, protocol fees accrued by the pool since the previous update
+        #[prost(string, tag="5")]
+        pub amount_1: ::prost::alloc::string::String,
+        #[prost(uint64, tag="6")]
+        pub log_ordinal: u64,
+    }
+    #[derive(::serde::Serialize)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct CollectProtocol {
+        #[prost(string, tag="1")]
+        pub pool_address: ::prost::alloc::string::String,
+        #[prost(string, tag="2")]
+        pub token0: ::prost::alloc::string::String,
+        #[prost(string, tag="3")]
+        pub token1: ::prost::alloc::string::String,
+        #[prost(string, tag="4")]
+        pub sender: ::prost::alloc::string::String,
+        #[prost(string, tag="5")]
+        pub recipient: ::prost::alloc::string::String,
+        /// Decimal
+        #[prost(string, tag="6")]
+        pub amount_0: ::prost::alloc::string::String,
+        /// Decimal
+        #[prost(string, tag="7")]
+        pub amount_1: ::prost::alloc::string::String,
+        #[prost(uint64, tag="8")]
+        pub log_ordinal: u64,
     }
     #[derive(::serde::Serialize)]
     #[allow(clippy::derive_partial_eq_without_eq)]
@@ -452,6 +923,15 @@ pub mod events {
         /// BigInt
         #[prost(string, optional, tag="6")]
         pub fee_growth_inside1_last_x128: ::core::option::Option<::prost::alloc::string::String>,
+        /// BigInt
+        #[prost(string, optional, tag="7")]
+        pub total_liquidity: ::core::option::Option<::prost::alloc::string::String>,
+        /// BigInt
+        #[prost(string, optional, tag="8")]
+        pub tokens_owed0: ::core::option::Option<::prost::alloc::string::String>,
+        /// BigInt
+        #[prost(string, optional, tag="9")]
+        pub tokens_owed1: ::core::option::Option<::prost::alloc::string::String>,
         #[prost(uint64, tag="10")]
         pub log_ordinal: u64,
     }
@@ -476,6 +956,15 @@ pub mod events {
         /// BigInt
         #[prost(string, optional, tag="6")]
         pub fee_growth_inside1_last_x128: ::core::option::Option<::prost::alloc::string::String>,
+        /// BigInt
+        #[prost(string, optional, tag="7")]
+        pub total_liquidity: ::core::option::Option<::prost::alloc::string::String>,
+        /// BigInt
+        #[prost(string, optional, tag="8")]
+        pub tokens_owed0: ::core::option::Option<::prost::alloc::string::String>,
+        /// BigInt
+        #[prost(string, optional, tag="9")]
+        pub tokens_owed1: ::core::option::Option<::prost::alloc::string::String>,
         #[prost(uint64, tag="10")]
         pub log_ordinal: u64,
     }
@@ -497,6 +986,15 @@ pub mod events {
         /// BigInt
         #[prost(string, optional, tag="6")]
         pub fee_growth_inside1_last_x128: ::core::option::Option<::prost::alloc::string::String>,
+        /// BigInt
+        #[prost(string, optional, tag="7")]
+        pub total_liquidity: ::core::option::Option<::prost::alloc::string::String>,
+        /// BigInt
+        #[prost(string, optional, tag="8")]
+        pub tokens_owed0: ::core::option::Option<::prost::alloc::string::String>,
+        /// BigInt
+        #[prost(string, optional, tag="9")]
+        pub tokens_owed1: ::core::option::Option<::prost::alloc::string::String>,
         #[prost(uint64, tag="10")]
         pub log_ordinal: u64,
     }
@@ -1,50 +1,15 @@
-use crate::{math, Erc20Token, Pool};
-use std::ops::{Div, Mul};
+use crate::config::Config;
+use crate::{fixed_point, math, Erc20Token, Pool};
+use num_bigint::BigUint;
+use std::collections::{HashMap, HashSet};
+use std::ops::{Add, Div, Mul, Sub};
 use std::str;
-use std::str::FromStr;
 use substreams::log;
 use substreams::scalar::{BigDecimal, BigInt};
 use substreams::store::{StoreGet, StoreGetBigDecimal, StoreGetBigInt, StoreGetProto, StoreGetRaw};
 
-const USDC_WETH_03_POOL: &str = "8ad599c3a0ff1de082011efddc58f1908eb6e6d8";
-const USDC_ADDRESS: &str = "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
-const WETH_ADDRESS: &str = "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
-
-pub const STABLE_COINS: [&str; 6] = [
-    "6b175474e89094c44da98b954eedeac495271d0f", // DAI
-    "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", // USDC
-    "dac17f958d2ee523a2206206994597c13d831ec7", // USDT
-    "0000000000085d4780b73119b644ae5ecd22b376", // TUSD
-    "956f47f50a910163d8bf957cf5846d573e7f87ca", // FEI
-    "4dd28568d05f09b02220b09c2cb307bfd837cb95", // PRINTS
-];
-
-pub const WHITELIST_TOKENS: [&str; 21] = [
-    "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2", // WETH
-    "6b175474e89094c44da98b954eedeac495271d0f", // DAI
-    "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", // USDC
-    "dac17f958d2ee523a2206206994597c13d831ec7", // USDT
-    "0000000000085d4780b73119b644ae5ecd22b376", // TUSD
-    "2260fac5e5542a773aa44fbcfedf7c193bc2c599", // WBTC
-    "5d3a536e4d6dbd6114cc1ead35777bab948e3643", // cDAI
-    "39aa39c021dfbae8fac545936693ac917d5e7563", // cUSDC
-    "86fadb80d8d2cff3c3680819e4da99c10232ba0f", // EBASE
-    "57ab1ec28d129707052df4df418d58a2d46d5f51", // sUSD
-    "9f8f72aa9304c8b593d555f12ef6589cc3a579a2", // MKR
-    "c00e94cb662c3520282e6f5717214004a7f26888", // COMP
-    "514910771af9ca656af840dff83e8264ecf986ca", // LINK
-    "c011a73ee8576fb46f5e1c5751ca3b9fe0af2a6f", // SNX
-    "0bc529c00c6401aef6d220be8c6ea1667f6ad93e", // YFI
-    "111111111117dc0aa78b770fa6a738034120c302", // 1INCH
-    "df5e0e81dff6faf3a7e52ba697820c5e32d806a8", // yCurv
-    "956f47f50a910163d8bf957cf5846d573e7f87ca", // FEI
-    "7d1afa7b718fb893db30a3abc0cfc608aacfebb0", // MATIC
-    "7fc66500c84a76ad7e9c93437bfc5ac33e2ddae9", // AAVE
-    "fe2e637202056d30016725477c5da089ab0a043a", // sETH2
-];
-
 pub fn sqrt_price_x96_to_token_prices(
-    sqrt_price: BigDecimal,
+    sqrt_price_x96: &BigUint,
     token_0: &Erc20Token,
     token_1: &Erc20Token,
 ) -> (BigDecimal, BigDecimal) {
@@ -56,12 +21,7 @@ pub fn sqrt_price_x96_to_token_prices(
         token_1.decimals
     );
 
-    let price: BigDecimal = sqrt_price.clone().mul(sqrt_price);
-    let denominator: BigDecimal =
-        BigDecimal::from_str("6277101735386680763835789423207666416102355444464034512896").unwrap();
-
-    let price1 = price
-        .div(denominator)
+    let price1 = fixed_point::sqrt_price_x96_to_price(sqrt_price_x96)
         .mul(math::exponent_to_big_decimal(token_0.decimals))
         .div(math::exponent_to_big_decimal(token_1.decimals));
 
@@ -70,6 +30,19 @@ pub fn sqrt_price_x96_to_token_prices(
     return (price0, price1);
 }
 
+// token prices at the given tick, e.g. from a time weighted average tick
+pub fn tick_to_token_prices(tick: i32, token_0: &Erc20Token, token_1: &Erc20Token) -> (BigDecimal, BigDecimal) {
+    sqrt_price_x96_to_token_prices(&fixed_point::get_sqrt_ratio_at_tick(tick), token_0, token_1)
+}
+
+// Derived ETH price of a token, the liquidity weighted average of its prices in the pools of the pricing
+// graph. The edges of the graph are the pools pairing a token with a whitelisted token, whose own ETH
+// price is found the same way, up to `max_price_hops` pools away from WETH or a stable coin. Each pool
+// weighs the ETH locked on its whitelisted side, pools under `min_eth_locked` are left out unless the
+// priced token is whitelisted.
+//
+// Stable coins are worth 1 USD, unless `price_stable_coins` is set: they are then priced from their own
+// pools like any other token, falling back to their peg when none of them has a price.
 pub fn find_eth_per_token(
     ord: u64,
     pool_address: &String,
@@ -79,222 +52,504 @@ pub fn find_eth_per_token(
     tokens_whitelist_pools_store: &StoreGetRaw,
     total_native_amounts_store: &StoreGetBigDecimal,
     prices_store: &StoreGetBigDecimal,
+    config: &Config,
 ) -> BigDecimal {
     log::debug!("finding ETH per token for {} in pool {}", token_address, pool_address);
-    if token_address.eq(WETH_ADDRESS) {
-        log::debug!("is ETH return 1");
-        return BigDecimal::one();
+    let stores = PricingStores {
+        ord,
+        pools_store,
+        pool_liquidities_store,
+        tokens_whitelist_pools_store,
+        total_native_amounts_store,
+        prices_store,
+    };
+    let eth_price_usd = get_eth_price_in_usd(&eth_price_sources(
+        ord,
+        pools_store,
+        pool_liquidities_store,
+        total_native_amounts_store,
+        prices_store,
+        config,
+    ));
+    let mut graph = PricingGraph::new(&stores, config, eth_price_usd);
+
+    return graph
+        .eth_per_token(token_address, config.max_price_hops)
+        .unwrap_or(BigDecimal::zero());
+}
+
+// Reads of the pricing graph, at the ordinal of the price being derived
+trait PricingSource {
+    // `;` separated addresses of the pools pairing the token with a whitelisted token
+    fn whitelist_pools(&self, token_address: &String) -> Option<String>;
+    fn pool(&self, pool_address: &str) -> Option<Pool>;
+    fn liquidity(&self, pool_address: &str) -> BigInt;
+    fn native_amount(&self, pool_address: &str, token_address: &String) -> BigDecimal;
+    fn price(&self, price_key: &String) -> Option<BigDecimal>;
+}
+
+struct PricingStores<'a> {
+    ord: u64,
+    pools_store: &'a StoreGetProto<Pool>,
+    pool_liquidities_store: &'a StoreGetBigInt,
+    tokens_whitelist_pools_store: &'a StoreGetRaw,
+    total_native_amounts_store: &'a StoreGetBigDecimal,
+    prices_store: &'a StoreGetBigDecimal,
+}
+
+impl<'a> PricingSource for PricingStores<'a> {
+    fn whitelist_pools(&self, token_address: &String) -> Option<String> {
+        self.tokens_whitelist_pools_store
+            .get_last(&format!("token:{token_address}"))
+            .map(|bytes| String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    fn pool(&self, pool_address: &str) -> Option<Pool> {
+        self.pools_store.get_last(format!("pool:{pool_address}"))
+    }
+
+    fn liquidity(&self, pool_address: &str) -> BigInt {
+        self.pool_liquidities_store
+            .get_at(self.ord, format!("pool:{pool_address}"))
+            .unwrap_or(BigInt::zero())
+    }
+
+    fn native_amount(&self, pool_address: &str, token_address: &String) -> BigDecimal {
+        self.total_native_amounts_store
+            .get_at(self.ord, format!("pool:{pool_address}:{token_address}:native"))
+            .unwrap_or(BigDecimal::zero())
+    }
+
+    fn price(&self, price_key: &String) -> Option<BigDecimal> {
+        self.prices_store.get_at(self.ord, price_key)
+    }
+}
+
+struct PricingGraph<'a, S: PricingSource> {
+    source: &'a S,
+    config: &'a Config,
+    eth_price_usd: Option<BigDecimal>,
+    // prices already found, by token and remaining hops
+    prices: HashMap<(String, u32), Option<BigDecimal>>,
+    // tokens of the current path, to avoid going around cycles
+    visiting: HashSet<String>,
+    // whether a token of the current path was skipped since the last price was found, that price then
+    // depends on the path and isn't kept in `prices`
+    pruned: bool,
+}
+
+impl<'a, S: PricingSource> PricingGraph<'a, S> {
+    fn new(source: &'a S, config: &'a Config, eth_price_usd: Option<BigDecimal>) -> Self {
+        PricingGraph {
+            source,
+            config,
+            eth_price_usd,
+            prices: HashMap::new(),
+            visiting: HashSet::new(),
+            pruned: false,
+        }
+    }
+
+    fn eth_per_token(&mut self, token_address: &String, hops: u32) -> Option<BigDecimal> {
+        if token_address.eq(&self.config.weth) {
+            return Some(BigDecimal::one());
+        }
+        if self.config.is_denylisted_token(token_address) {
+            log::debug!("token addr: {} is denylisted", token_address);
+            return None;
+        }
+        let is_stable_coin = self.config.is_stable_coin(token_address);
+        if is_stable_coin && !self.config.price_stable_coins {
+            log::debug!("token addr: {} is a stable coin", token_address);
+            return self.peg_eth_price();
+        }
+        if hops == 0 || self.visiting.contains(token_address) {
+            // unlike running out of hops, skipping a token of the path depends on the path
+            self.pruned = self.pruned || hops > 0;
+            return if is_stable_coin { self.peg_eth_price() } else { None };
+        }
+
+        let key = (token_address.clone(), hops);
+        if let Some(price) = self.prices.get(&key) {
+            return price.clone();
+        }
+
+        let pruned = std::mem::replace(&mut self.pruned, false);
+        self.visiting.insert(token_address.clone());
+        let mut price = self.weighted_eth_price(token_address, hops);
+        self.visiting.remove(token_address);
+        if price.is_none() && is_stable_coin {
+            price = self.peg_eth_price();
+        }
+
+        log::debug!("token {} derived eth price {:?} ({} hops)", token_address, price, hops);
+        if !self.pruned {
+            self.prices.insert(key, price.clone());
+        }
+        self.pruned = self.pruned || pruned;
+        return price;
     }
 
-    let mut price_so_far = BigDecimal::zero();
+    // ETH price of a stable coin worth exactly 1 USD
+    fn peg_eth_price(&self) -> Option<BigDecimal> {
+        self.eth_price_usd
+            .as_ref()
+            .map(|eth_price_usd| math::safe_div(&BigDecimal::one(), eth_price_usd))
+    }
 
-    if STABLE_COINS.contains(&token_address.as_str()) {
-        log::debug!("token addr: {} is a stable coin", token_address);
-        let eth_price_usd = get_eth_price_in_usd(prices_store, ord);
-        log::info!("eth_price_usd {}", eth_price_usd);
-        price_so_far = math::safe_div(&BigDecimal::one(), &eth_price_usd);
-    } else {
-        // TODO: @eduard change this once the changes for store of list has been merged
-        let wl = match tokens_whitelist_pools_store.get_last(&format!("token:{token_address}")) {
+    fn weighted_eth_price(&mut self, token_address: &String, hops: u32) -> Option<BigDecimal> {
+        let whitelist_pools = match self.source.whitelist_pools(token_address) {
             None => {
                 log::debug!("failed to get whitelisted pools for token {}", token_address);
-                return BigDecimal::zero();
+                return None;
             }
-            Some(bytes) => String::from_utf8(bytes.to_vec()).unwrap(),
+            Some(pools) => pools,
         };
 
-        let mut whitelisted_pools: Vec<&str> = vec![];
-        for p in wl.split(";") {
-            if !p.is_empty() {
-                whitelisted_pools.push(p);
-            }
-        }
-        log::debug!("found whitelisted pools {}", whitelisted_pools.len());
-
-        let mut largest_eth_locked = BigDecimal::zero();
-        let minimum_eth_locked = BigDecimal::from_str("52").unwrap();
-        let mut eth_locked: BigDecimal;
+        let mut weighted_price_sum = BigDecimal::zero();
+        let mut weight_sum = BigDecimal::zero();
 
-        for pool_address in whitelisted_pools.iter() {
-            log::debug!("checking pool: {}", pool_address);
-            let pool = match pools_store.get_last(format!("pool:{pool_address}")) {
+        for pool_address in whitelist_pools
+            .split(";")
+            .filter(|pool_address| !pool_address.is_empty())
+        {
+            let pool = match self.source.pool(pool_address) {
                 None => continue,
                 Some(p) => p,
             };
-            let token0 = pool.token0.as_ref().unwrap();
-            let token1 = pool.token1.as_ref().unwrap();
-            let token0_addr = &token0.address;
-            let token1_addr = &token1.address;
+            if self.config.is_denylisted_pool(&pool) {
+                continue;
+            }
+
+            let liquidity = self.source.liquidity(pool_address);
+            if liquidity.le(&BigInt::zero()) {
+                continue;
+            }
 
-            log::debug!("found pool: {pool_address} with token0 {token0_addr} and with token1 {token1_addr}",);
+            // the price of the token in the counter token is the counter token's price of the pool,
+            // e.g. token1Price (token1 per token0) when pricing token0
+            let token0_addr = &pool.token0_ref().address;
+            let token1_addr = &pool.token1_ref().address;
+            let (counter_addr, price_key) = if token0_addr == token_address {
+                (token1_addr, format!("pool:{pool_address}:{token1_addr}:token1"))
+            } else if token1_addr == token_address {
+                (token0_addr, format!("pool:{pool_address}:{token0_addr}:token0"))
+            } else {
+                continue;
+            };
 
-            let liquidity: BigInt = match pool_liquidities_store.get_at(ord, format!("pool:{pool_address}")) {
+            let counter_eth_price = match self.eth_per_token(counter_addr, hops - 1) {
+                Some(price) if price.gt(&BigDecimal::zero()) => price,
+                _ => continue,
+            };
+
+            let native_amount = self.source.native_amount(pool_address, counter_addr);
+            let eth_locked = native_amount.mul(counter_eth_price.clone());
+            if eth_locked.le(&BigDecimal::zero())
+                || (eth_locked.lt(&self.config.min_eth_locked) && !self.config.is_whitelisted(token_address))
+            {
+                log::debug!("eth locked in pool {pool_address} {eth_locked} is too low");
+                continue;
+            }
+
+            let price = match self.source.price(&price_key) {
                 None => {
-                    log::debug!("No liquidity for pool {pool_address}");
-                    BigInt::zero()
+                    log::debug!("unable to find price {price_key}");
+                    continue;
                 }
-                Some(l) => l,
+                Some(price) => price,
             };
 
-            if liquidity.gt(&BigInt::zero()) {
-                if &token0.address == token_address {
-                    log::info!(
-                        "current pool token 0 matches desired token, complementary token is {} {}",
-                        token1_addr,
-                        token1.symbol
-                    );
-                    let native_amount = match total_native_amounts_store
-                        .get_at(ord, format!("pool:{pool_address}:{token1_addr}:native"))
-                    {
-                        None => BigDecimal::zero(),
-                        Some(amount) => amount,
-                    };
-                    log::debug!("native amount value of token1 in pool {}", native_amount);
-
-                    let token1_eth_price;
-                    // If the counter token is WETH we know the derived price is 1
-                    if token1.address.eq(WETH_ADDRESS) {
-                        log::debug!("token 1 is WETH");
-                        eth_locked = native_amount;
-                        token1_eth_price = BigDecimal::one();
-                    } else {
-                        log::debug!("token 1 is NOT WETH");
-
-                        match pool_liquidities_store.get_at(ord, format!("pair:{WETH_ADDRESS}:{token1_addr}")) {
-                            None => {
-                                log::debug!("unable to find liquidity for {:?}", token1_addr);
-                                continue;
-                            }
-                            Some(l) => {
-                                // There is no liquidity in the pool. We can't compute the eth_price
-                                // of the token.
-                                if l.eq(&BigInt::zero()) {
-                                    continue;
-                                }
-
-                                // Else we have enough liquidity to compute the price
-                            }
-                        }
-
-                        token1_eth_price = match prices_store.get_at(ord, format!("pair:{WETH_ADDRESS}:{token1_addr}"))
-                        {
-                            None => {
-                                log::debug!("unable to find token 1 price in eth {token1_addr}");
-                                continue;
-                            }
-                            Some(price) => price,
-                        };
-                        log::debug!("token 1 is price in eth {}", token1_eth_price);
-                        eth_locked = native_amount.mul(token1_eth_price.clone());
-                        log::debug!("computed eth locked {}", eth_locked);
-                    }
-                    log::debug!(
-                        "eth locked in pool {pool_address} {} (largest {})",
-                        eth_locked,
-                        largest_eth_locked
-                    );
-                    // should the check below make more sens if we EITHER have eth.gt > largest && (eth_locked > min BUT !Whitelist || whitelist)???
-                    if eth_locked.gt(&largest_eth_locked)
-                        && (eth_locked.gt(&minimum_eth_locked) || WHITELIST_TOKENS.contains(&token0_addr.as_str()))
-                    {
-                        log::debug!("eth locked passed test");
-                        let token1_price =
-                            match prices_store.get_at(ord, format!("pool:{pool_address}:{token1_addr}:token1")) {
-                                None => {
-                                    log::debug!("unable to find pool {pool_address} for token {token1_addr} price",);
-                                    continue;
-                                }
-                                Some(price) => price,
-                            };
-                        log::debug!("found token 1 price {}", token1_price);
-                        largest_eth_locked = eth_locked.clone();
-                        price_so_far = token1_price.mul(token1_eth_price.clone());
-                        log::debug!("price_so_far {}", price_so_far);
-                    }
-                }
-                if &token1.address == token_address {
-                    log::debug!(
-                        "current pool token 1 matches desired token, complementary token is {} {}",
-                        token0.address,
-                        token1.symbol
-                    );
-                    let native_amount = match total_native_amounts_store
-                        .get_at(ord, format!("pool:{pool_address}:{token0_addr}:native"))
-                    {
-                        None => BigDecimal::zero(),
-                        Some(price) => price,
-                    };
-                    log::debug!("native amount value of token0 in pool {}", native_amount);
-
-                    let mut token0_eth_price = BigDecimal::zero();
-
-                    // If the counter token is WETH we know the derived price is 1
-                    if token0.address.eq(WETH_ADDRESS) {
-                        log::debug!("token 0 is WETH");
-                        eth_locked = native_amount
-                    } else {
-                        log::debug!("token 0 is NOT WETH");
-
-                        match pool_liquidities_store.get_at(ord, format!("pair:{WETH_ADDRESS}:{token0_addr}")) {
-                            None => {
-                                log::debug!("unable to find liquidity for {:?}", token0_addr);
-                                continue;
-                            }
-                            Some(l) => {
-                                // There is no liquidity in the pool. We can't compute the eth_price
-                                // of the token.
-                                if l.eq(&BigInt::zero()) {
-                                    continue;
-                                }
-
-                                // Else we have enough liquidity to compute the price
-                            }
-                        }
-
-                        token0_eth_price = match prices_store.get_at(ord, format!("pair:{WETH_ADDRESS}:{token0_addr}"))
-                        {
-                            None => {
-                                log::debug!("unable to find token 0 price in eth {:?}", token0.address);
-                                continue;
-                            }
-                            Some(price) => price,
-                        };
-                        log::debug!("token 0 is price in eth {}", token0_eth_price);
-                        eth_locked = native_amount.mul(token0_eth_price.clone());
-                        log::debug!("computed eth locked {}", eth_locked);
-                    }
-                    log::debug!("eth locked in pool {pool_address} {eth_locked} (largest {largest_eth_locked})",);
-                    if eth_locked.gt(&largest_eth_locked)
-                        && (eth_locked.gt(&minimum_eth_locked) || WHITELIST_TOKENS.contains(&token1_addr.as_str()))
-                    {
-                        log::debug!("eth locked passed test");
-                        let token0_price =
-                            match prices_store.get_at(ord, format!("pool:{pool_address}:{token0_addr}:token0")) {
-                                None => {
-                                    log::debug!("unable to find pool {pool_address} for token {token0_addr} price",);
-                                    continue;
-                                }
-                                Some(price) => price,
-                            };
-                        log::debug!("found token 0 price {}", token0_price);
-                        largest_eth_locked = eth_locked.clone();
-                        price_so_far = token0_price.mul(token0_eth_price.clone());
-                        log::debug!("price_so_far {}", price_so_far);
-                    }
-                }
-            }
+            log::debug!("pool {pool_address} price {price} in {counter_addr}, eth locked {eth_locked}");
+            weighted_price_sum = weighted_price_sum.add(price.mul(counter_eth_price).mul(eth_locked.clone()));
+            weight_sum = weight_sum.add(eth_locked);
         }
+
+        if weight_sum.eq(&BigDecimal::zero()) {
+            return None;
+        }
+        return Some(weighted_price_sum.div(weight_sum));
     }
-    return price_so_far;
 }
 
-pub fn get_eth_price_in_usd(prices_store: &StoreGetBigDecimal, ordinal: u64) -> BigDecimal {
-    let key = format!("pool:{}:{}:{}", USDC_WETH_03_POOL, USDC_ADDRESS, "token0");
-    return match prices_store.get_at(ordinal, &key) {
-        None => {
-            log::debug!("price not found");
-            BigDecimal::zero()
+// Price of the native token in a pool of the `eth_price_pools`, weighing the stable coins it holds
+pub struct EthPriceSource {
+    pub pool_address: String,
+    pub price: BigDecimal,
+    pub weight: BigDecimal,
+}
+
+// Prices of the native token in the `eth_price_pools` having one. A pool without liquidity keeps the
+// last price it was swapped at, it is left out rather than letting a stale price into the median.
+pub fn eth_price_sources(
+    ordinal: u64,
+    pools_store: &StoreGetProto<Pool>,
+    pool_liquidities_store: &StoreGetBigInt,
+    total_native_amounts_store: &StoreGetBigDecimal,
+    prices_store: &StoreGetBigDecimal,
+    config: &Config,
+) -> Vec<EthPriceSource> {
+    let mut sources = vec![];
+    for pool_address in &config.eth_price_pools {
+        let pool = match pools_store.get_last(format!("pool:{pool_address}")) {
+            None => continue,
+            Some(p) => p,
+        };
+
+        let liquidity = pool_liquidities_store
+            .get_at(ordinal, format!("pool:{pool_address}"))
+            .unwrap_or(BigInt::zero());
+        if liquidity.le(&BigInt::zero()) {
+            continue;
         }
-        Some(price) => price,
-    };
+
+        // the USD price of the native token is the stable coin's price of the pool, e.g. token0Price
+        // (token0 per token1) when the stable coin is token0
+        let token0_addr = &pool.token0_ref().address;
+        let token1_addr = &pool.token1_ref().address;
+        let (stable_addr, price_key) = if token1_addr.eq(&config.weth) {
+            (token0_addr, format!("pool:{pool_address}:{token0_addr}:token0"))
+        } else if token0_addr.eq(&config.weth) {
+            (token1_addr, format!("pool:{pool_address}:{token1_addr}:token1"))
+        } else {
+            log::info!("eth price pool {pool_address} doesn't hold the native token");
+            continue;
+        };
+
+        let price = match prices_store.get_at(ordinal, &price_key) {
+            Some(price) if price.gt(&BigDecimal::zero()) => price,
+            _ => continue,
+        };
+        let weight = total_native_amounts_store
+            .get_at(ordinal, format!("pool:{pool_address}:{stable_addr}:native"))
+            .unwrap_or(BigDecimal::zero());
+        if weight.le(&BigDecimal::zero()) {
+            continue;
+        }
+
+        sources.push(EthPriceSource {
+            pool_address: pool_address.clone(),
+            price,
+            weight,
+        });
+    }
+    sources
+}
+
+// USD price of the native token, the median of its prices in the `eth_price_sources` weighted by
+// the stable coins they hold. None when no pool has a price, the bundle then keeps its last price
+// instead of dropping to zero.
+pub fn get_eth_price_in_usd(sources: &Vec<EthPriceSource>) -> Option<BigDecimal> {
+    let prices = sources
+        .iter()
+        .map(|source| (source.price.clone(), source.weight.clone()))
+        .collect();
+    let price = math::weighted_median(&prices);
+    if price.is_none() {
+        log::debug!("eth price not found");
+    }
+    price
+}
+
+// Relative deviation of a stable coin's USD price from its 1 USD peg, e.g. -0.1 for a coin at 0.9 USD
+pub fn peg_deviation(derived_eth_price: &BigDecimal, eth_price_usd: &BigDecimal) -> BigDecimal {
+    derived_eth_price
+        .clone()
+        .mul(eth_price_usd.clone())
+        .sub(BigDecimal::one())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::Config;
+    use crate::price::{PricingGraph, PricingSource};
+    use crate::{Erc20Token, Pool};
+    use std::collections::HashMap;
+    use std::ops::Div;
+    use substreams::scalar::{BigDecimal, BigInt};
+
+    // pools of the graph, with the liquidity, native amounts and prices they are read at
+    #[derive(Default)]
+    struct TestSource {
+        whitelist_pools: HashMap<String, String>,
+        pools: HashMap<String, Pool>,
+        native_amounts: HashMap<String, BigDecimal>,
+        prices: HashMap<String, BigDecimal>,
+    }
+
+    impl TestSource {
+        // pool of token0 and token1 holding `amount0` and `amount1`, whose token1Price (token1 per token0)
+        // is `price1`
+        fn add_pool(
+            &mut self,
+            pool_address: &str,
+            token0: &str,
+            token1: &str,
+            amount0: &str,
+            amount1: &str,
+            price1: &str,
+        ) {
+            self.pools.insert(
+                pool_address.to_string(),
+                Pool {
+                    address: pool_address.to_string(),
+                    token0: Some(token(token0)),
+                    token1: Some(token(token1)),
+                    ..Default::default()
+                },
+            );
+            for (token_address, amount) in [(token0, amount0), (token1, amount1)] {
+                self.native_amounts
+                    .insert(format!("pool:{pool_address}:{token_address}:native"), decimal(amount));
+                let pools = self.whitelist_pools.entry(token_address.to_string()).or_default();
+                pools.push_str(&format!("{pool_address};"));
+            }
+            let price1 = decimal(price1);
+            self.prices.insert(
+                format!("pool:{pool_address}:{token0}:token0"),
+                BigDecimal::one().div(price1.clone()),
+            );
+            self.prices
+                .insert(format!("pool:{pool_address}:{token1}:token1"), price1);
+        }
+    }
+
+    impl PricingSource for TestSource {
+        fn whitelist_pools(&self, token_address: &String) -> Option<String> {
+            self.whitelist_pools.get(token_address).cloned()
+        }
+
+        fn pool(&self, pool_address: &str) -> Option<Pool> {
+            self.pools.get(pool_address).cloned()
+        }
+
+        fn liquidity(&self, pool_address: &str) -> BigInt {
+            match self.pools.contains_key(pool_address) {
+                true => BigInt::one(),
+                false => BigInt::zero(),
+            }
+        }
+
+        fn native_amount(&self, pool_address: &str, token_address: &String) -> BigDecimal {
+            self.native_amounts
+                .get(&format!("pool:{pool_address}:{token_address}:native"))
+                .cloned()
+                .unwrap_or(BigDecimal::zero())
+        }
+
+        fn price(&self, price_key: &String) -> Option<BigDecimal> {
+            self.prices.get(price_key).cloned()
+        }
+    }
+
+    fn token(address: &str) -> Erc20Token {
+        Erc20Token {
+            address: address.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn decimal(value: &str) -> BigDecimal {
+        BigDecimal::try_from(value).unwrap()
+    }
+
+    fn config() -> Config {
+        let mut config = Config::parse("");
+        config.weth = "weth".to_string();
+        config.stable_coins = ["usdc".to_string()].into();
+        config.whitelist_tokens = ["weth".to_string(), "usdc".to_string(), "tkb".to_string()].into();
+        config.min_eth_locked = BigDecimal::zero();
+        config
+    }
+
+    fn eth_per_token(source: &TestSource, config: &Config, token_address: &str, hops: u32) -> Option<BigDecimal> {
+        PricingGraph::new(source, config, Some(decimal("2000"))).eth_per_token(&token_address.to_string(), hops)
+    }
+
+    #[test]
+    fn test_multi_hop_weighting() {
+        let mut source = TestSource::default();
+        // 0.5 ETH per TKB
+        source.add_pool("b-weth", "tkb", "weth", "200", "100", "0.5");
+        // 4 TKB per TKA, i.e. 2 ETH per TKA, weighing 200 TKB * 0.5 ETH
+        source.add_pool("a-b", "tka", "tkb", "50", "200", "4");
+        // 3 ETH per TKA, weighing 300 ETH
+        source.add_pool("a-weth", "tka", "weth", "100", "300", "3");
+        let config = config();
+
+        // (0.5 * 100 + 0.25 * 3 * 150) / (100 + 150), TKA being priced from its ETH pool alone
+        assert_eq!(Some(decimal("0.65")), eth_per_token(&source, &config, "tkb", 3));
+        // (2 * 100 + 3 * 300) / (100 + 300)
+        assert_eq!(Some(decimal("2.75")), eth_per_token(&source, &config, "tka", 3));
+        // TKB is one hop too far
+        assert_eq!(Some(decimal("3")), eth_per_token(&source, &config, "tka", 1));
+        assert_eq!(None, eth_per_token(&source, &config, "tka", 0));
+        assert_eq!(None, eth_per_token(&source, &config, "tkc", 3));
+    }
+
+    #[test]
+    fn test_min_eth_locked() {
+        let mut source = TestSource::default();
+        source.add_pool("a-weth-1", "tka", "weth", "10", "20", "5");
+        source.add_pool("a-weth-2", "tka", "weth", "40", "80", "2");
+        source.add_pool("b-weth", "tkb", "weth", "10", "20", "5");
+        let mut config = config();
+        config.min_eth_locked = BigDecimal::from(52);
+
+        // the pool locking 20 ETH is left out
+        assert_eq!(Some(decimal("2")), eth_per_token(&source, &config, "tka", 3));
+        // unless the token is whitelisted
+        config.whitelist_tokens.insert("tka".to_string());
+        // (5 * 20 + 2 * 80) / (20 + 80)
+        assert_eq!(Some(decimal("2.6")), eth_per_token(&source, &config, "tka", 3));
+        assert_eq!(Some(decimal("5")), eth_per_token(&source, &config, "tkb", 3));
+
+        config.whitelist_tokens.remove("tkb");
+        assert_eq!(None, eth_per_token(&source, &config, "tkb", 3));
+    }
+
+    #[test]
+    fn test_stable_coin_fallback() {
+        let mut source = TestSource::default();
+        let mut config = config();
+        let peg = Some(decimal("0.0005"));
+
+        // pegged to 1 USD, at 2000 USD per ETH
+        assert_eq!(peg, eth_per_token(&source, &config, "usdc", 3));
+
+        // priced from its pools, falling back to the peg without any
+        config.price_stable_coins = true;
+        assert_eq!(peg, eth_per_token(&source, &config, "usdc", 3));
+        source.add_pool("usdc-weth", "usdc", "weth", "1000000", "500", "0.0004");
+        assert_eq!(Some(decimal("0.0004")), eth_per_token(&source, &config, "usdc", 3));
+        assert_eq!(peg, eth_per_token(&source, &config, "usdc", 0));
+
+        config.price_stable_coins = false;
+        assert_eq!(peg, eth_per_token(&source, &config, "usdc", 3));
+
+        // no peg without an ETH price
+        let graph_price = PricingGraph::new(&source, &config, None).eth_per_token(&"usdc".to_string(), 3);
+        assert_eq!(None, graph_price);
+    }
+
+    #[test]
+    fn test_pruned_prices_are_not_reused() {
+        let mut source = TestSource::default();
+        // 2 ETH per TKB
+        source.add_pool("b-weth", "tkb", "weth", "100", "100", "2");
+        // 1 ETH per TKC, weighing 100 ETH
+        source.add_pool("c-weth", "tkc", "weth", "100", "100", "1");
+        // 3 TKB per TKC, i.e. 6 ETH per TKC, weighing 50 TKB * 2 ETH
+        source.add_pool("b-c", "tkc", "tkb", "10", "50", "3");
+        let config = config();
+
+        // (6 * 100 + 1 * 100) / (100 + 100)
+        let expected = Some(decimal("3.5"));
+        assert_eq!(expected, eth_per_token(&source, &config, "tkc", 2));
+
+        // pricing TKB first prices TKC without TKB's pool, as TKB is on the path
+        let mut graph = PricingGraph::new(&source, &config, Some(decimal("2000")));
+        graph.eth_per_token(&"tkb".to_string(), 3);
+        assert_eq!(expected, graph.eth_per_token(&"tkc".to_string(), 2));
+    }
 }
@@ -0,0 +1,80 @@
+use crate::pb::uniswap::seed_snapshot::Entry;
+use crate::pb::uniswap::SeedSnapshot;
+use prost::Message;
+
+// A seeded deployment gets the line written by `map_seed_snapshot_export` as the params of
+// `map_seed_snapshot`, i.e. `block=<n>&snapshot=<hex encoded SeedSnapshot>`, and starts its modules
+// at block `<n>`, the block following the snapshot. Empty params disable the seed.
+
+// kinds of events kept per NFT position in store_positions, see `PositionEvent::store_key`
+pub const POSITION_EVENT_KINDS: [&str; 5] = [
+    "position_created",
+    "position_increase_liquidity",
+    "position_decrease_liquidity",
+    "position_collect",
+    "position_transfer",
+];
+
+// on-chain values of the NFT positions in store_positions_fee_state
+pub const POSITION_FEE_STATE_FIELDS: [&str; 5] = [
+    "liquidity",
+    "feeGrowthInside0LastX128",
+    "feeGrowthInside1LastX128",
+    "tokensOwed0",
+    "tokensOwed1",
+];
+
+pub const TICK_FIELDS: [&str; 7] = [
+    "liquidityGross",
+    "liquidityNet",
+    "feeGrowthOutside0X128",
+    "feeGrowthOutside1X128",
+    "tickCumulativeOutside",
+    "secondsPerLiquidityOutsideX128",
+    "secondsOutside",
+];
+
+// the snapshot seeding `block_number`, empty on any other block
+pub fn parse(params: &str, block_number: u64) -> SeedSnapshot {
+    let mut seed_block: Option<u64> = None;
+    let mut snapshot = "";
+    for param in params.split("&").map(str::trim).filter(|param| !param.is_empty()) {
+        let (key, value) = param
+            .split_once("=")
+            .expect(&format!("invalid param {param}, expected key=value"));
+        match key {
+            "block" => seed_block = Some(value.parse().expect("invalid block")),
+            "snapshot" => snapshot = value,
+            _ => panic!("unknown param {key}"),
+        }
+    }
+
+    // the snapshot can be large, it is only decoded on the block it seeds
+    if seed_block != Some(block_number) {
+        return SeedSnapshot::default();
+    }
+
+    let bytes = hex::decode(snapshot).expect("invalid snapshot, expected hex");
+    let seed = SeedSnapshot::decode(bytes.as_slice()).expect("invalid snapshot");
+    assert_eq!(
+        seed.block_number + 1,
+        block_number,
+        "snapshot doesn't precede block {block_number}"
+    );
+    seed
+}
+
+pub fn encode(snapshot: &SeedSnapshot) -> String {
+    format!(
+        "block={}&snapshot={}",
+        snapshot.block_number + 1,
+        hex::encode(snapshot.encode_to_vec())
+    )
+}
+
+pub fn entry<T: ToString>(key: &String, value: Option<T>) -> Option<Entry> {
+    value.map(|value| Entry {
+        key: key.clone(),
+        value: value.to_string(),
+    })
+}
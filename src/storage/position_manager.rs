@@ -30,10 +30,7 @@ impl<'a> PositionManagerStorage<'a> {
         if let Some((old_data, new_data)) =
             utils::get_storage_change(&self.filtered_changes(), slot_key, offset, number_of_bytes)
         {
-            Some((
-                BigInt::from_signed_bytes_be(old_data),
-                BigInt::from_signed_bytes_be(new_data),
-            ))
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
         } else {
             None
         }
@@ -49,10 +46,7 @@ impl<'a> PositionManagerStorage<'a> {
         if let Some((old_data, new_data)) =
             utils::get_storage_change(&self.filtered_changes(), slot_key, offset, number_of_bytes)
         {
-            Some((
-                BigInt::from_signed_bytes_be(old_data),
-                BigInt::from_signed_bytes_be(new_data),
-            ))
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
         } else {
             None
         }
@@ -80,10 +74,7 @@ impl<'a> PositionManagerStorage<'a> {
         if let Some((old_data, new_data)) =
             utils::get_storage_change(&self.filtered_changes(), pool_ids_address_slot, 0, 20)
         {
-            Some((
-                BigInt::from_signed_bytes_be(old_data),
-                BigInt::from_signed_bytes_be(new_data),
-            ))
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
         } else {
             None
         }
@@ -121,10 +112,7 @@ impl<'a> PositionStruct<'a> {
         if let Some((old_data, new_data)) =
             utils::get_storage_change(&self.storage_changes, slot_key, offset, number_of_bytes)
         {
-            Some((
-                BigInt::from_signed_bytes_be(old_data),
-                BigInt::from_signed_bytes_be(new_data),
-            ))
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
         } else {
             None
         }
@@ -159,10 +147,7 @@ impl<'a> PositionStruct<'a> {
         if let Some((old_data, new_data)) =
             utils::get_storage_change(&self.storage_changes, slot_key, offset, number_of_bytes)
         {
-            Some((
-                BigInt::from_signed_bytes_be(old_data),
-                BigInt::from_signed_bytes_be(new_data),
-            ))
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
         } else {
             None
         }
@@ -216,10 +201,7 @@ impl<'a> PositionStruct<'a> {
         if let Some((old_data, new_data)) =
             utils::get_storage_change(&self.storage_changes, slot_key, offset, number_of_bytes)
         {
-            Some((
-                BigInt::from_signed_bytes_be(old_data),
-                BigInt::from_signed_bytes_be(new_data),
-            ))
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
         } else {
             None
         }
@@ -235,10 +217,7 @@ impl<'a> PositionStruct<'a> {
         if let Some((old_data, new_data)) =
             utils::get_storage_change(&self.storage_changes, slot_key, offset, number_of_bytes)
         {
-            Some((
-                BigInt::from_signed_bytes_be(old_data),
-                BigInt::from_signed_bytes_be(new_data),
-            ))
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
         } else {
             None
         }
@@ -254,10 +233,39 @@ impl<'a> PositionStruct<'a> {
         if let Some((old_data, new_data)) =
             utils::get_storage_change(&self.storage_changes, slot_key, offset, number_of_bytes)
         {
-            Some((
-                BigInt::from_signed_bytes_be(old_data),
-                BigInt::from_signed_bytes_be(new_data),
-            ))
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
+        } else {
+            None
+        }
+    }
+
+    pub fn tokens_owed0(&self) -> Option<(BigInt, BigInt)> {
+        let slot = BigInt::from(4);
+        let offset = 0;
+        let number_of_bytes = 16;
+
+        let slot_key = utils::calc_struct_slot(&self.struct_slot, slot);
+
+        if let Some((old_data, new_data)) =
+            utils::get_storage_change(&self.storage_changes, slot_key, offset, number_of_bytes)
+        {
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
+        } else {
+            None
+        }
+    }
+
+    pub fn tokens_owed1(&self) -> Option<(BigInt, BigInt)> {
+        let slot = BigInt::from(4);
+        let offset = 16;
+        let number_of_bytes = 16;
+
+        let slot_key = utils::calc_struct_slot(&self.struct_slot, slot);
+
+        if let Some((old_data, new_data)) =
+            utils::get_storage_change(&self.storage_changes, slot_key, offset, number_of_bytes)
+        {
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
         } else {
             None
         }
@@ -325,10 +333,7 @@ impl<'a> PoolKeyStruct<'a> {
         if let Some((old_data, new_data)) =
             utils::get_storage_change(&self.storage_changes, slot_key, offset, number_of_bytes)
         {
-            Some((
-                BigInt::from_signed_bytes_be(old_data),
-                BigInt::from_signed_bytes_be(new_data),
-            ))
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
         } else {
             None
         }
@@ -448,6 +453,22 @@ mod tests {
         assert_eq!(None, v_opt);
     }
 
+    #[test]
+    fn position_tokens_owed0() {
+        let changes = get_store_changes();
+        let storage = get_position_manager(&changes);
+        let v_opt = storage.positions(&BigInt::from_str("1").unwrap()).tokens_owed0();
+        assert_eq!(None, v_opt);
+    }
+
+    #[test]
+    fn position_tokens_owed1() {
+        let changes = get_store_changes();
+        let storage = get_position_manager(&changes);
+        let v_opt = storage.positions(&BigInt::from_str("1").unwrap()).tokens_owed1();
+        assert_eq!(None, v_opt);
+    }
+
     #[test]
     fn pool_ids() {
         let changes = get_store_changes();
@@ -499,6 +520,52 @@ mod tests {
         );
     }
 
+    #[test]
+    fn position_uints_with_top_bit_set() {
+        // a fee growth inside which wrapped below zero, as for a range above the current tick, and
+        // tokens owed with their most significant bit set
+        let changes = vec![
+            StorageChange {
+                address: hex!("C36442b4a4522E871399CD717aBDD847Ab11FE88").to_vec(),
+                key: hex!("d421a5181c571bba3f01190c922c3b2a896fc1d84e86c9f17ac10e67ebef8b5e").to_vec(),
+                old_value: hex!("0000000000000000000000000000000000000000000000000000000000000000").to_vec(),
+                new_value: hex!("fffffffffffffffffffffffffffffffffffffffffffffffffd8a6bb2b6d4cbc1").to_vec(),
+                ordinal: 0,
+            },
+            StorageChange {
+                address: hex!("C36442b4a4522E871399CD717aBDD847Ab11FE88").to_vec(),
+                key: hex!("d421a5181c571bba3f01190c922c3b2a896fc1d84e86c9f17ac10e67ebef8b60").to_vec(),
+                old_value: hex!("0000000000000000000000000000000000000000000000000000000000000000").to_vec(),
+                new_value: hex!("80000000000000000000000000000005ffffffffffffffffffffffffffffff9c").to_vec(),
+                ordinal: 0,
+            },
+        ];
+        let storage = get_position_manager(&changes);
+        let position = storage.positions(&BigInt::from_str("1").unwrap());
+        assert_eq!(
+            Some((
+                BigInt::from_str("0").unwrap(),
+                BigInt::from_str("115792089237316195423570985008687907853269984665640564039457406797093117676481")
+                    .unwrap()
+            )),
+            position.fee_growth_inside0last_x128()
+        );
+        assert_eq!(
+            Some((
+                BigInt::from_str("0").unwrap(),
+                BigInt::from_str("340282366920938463463374607431768211356").unwrap()
+            )),
+            position.tokens_owed0()
+        );
+        assert_eq!(
+            Some((
+                BigInt::from_str("0").unwrap(),
+                BigInt::from_str("170141183460469231731687303715884105733").unwrap()
+            )),
+            position.tokens_owed1()
+        );
+    }
+
     fn get_store_changes() -> Vec<StorageChange> {
         return vec![
             StorageChange {
@@ -1,23 +1,84 @@
 use crate::storage::utils;
 use hex::encode;
+use std::collections::HashSet;
+use std::ops::{Add, Sub};
 use substreams::scalar::BigInt;
 use substreams_ethereum::pb::eth::v2::StorageChange;
+use tiny_keccak::{Hasher, Keccak};
+
+const OBSERVATIONS_SLOT: u64 = 8;
+const OBSERVATIONS_LENGTH: u64 = 65535;
+const TICKS_SLOT: u64 = 5;
+const POSITIONS_SLOT: u64 = 7;
+
+// storage layout of the pool contract of a Uniswap v3 fork
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PoolLayout {
+    UniswapV3,
+    // feeProtocol is a uint32 which doesn't fit in the first slot of slot0, it moves to a second
+    // slot along with `unlocked` and every state variable declared after slot0 is shifted by one
+    PancakeSwapV3,
+}
+
+impl PoolLayout {
+    // forks which didn't touch the pool storage, e.g. SushiSwap v3, use the Uniswap v3 layout
+    pub fn for_protocol(protocol: &str) -> PoolLayout {
+        match protocol {
+            "pancakeswap-v3" => PoolLayout::PancakeSwapV3,
+            _ => PoolLayout::UniswapV3,
+        }
+    }
+
+    // slot of a state variable declared after slot0, from its slot in the Uniswap v3 pool
+    fn slot(&self, uniswap_slot: u64) -> u64 {
+        match self {
+            PoolLayout::UniswapV3 => uniswap_slot,
+            PoolLayout::PancakeSwapV3 => uniswap_slot + 1,
+        }
+    }
+
+    // (member slot, offset, number of bytes) of slot0.feeProtocol
+    fn fee_protocol(&self) -> (u64, usize, usize) {
+        match self {
+            PoolLayout::UniswapV3 => (0, 29, 1),
+            PoolLayout::PancakeSwapV3 => (1, 0, 4),
+        }
+    }
+
+    // (member slot, offset, number of bytes) of slot0.unlocked
+    fn unlocked(&self) -> (u64, usize, usize) {
+        match self {
+            PoolLayout::UniswapV3 => (0, 30, 1),
+            PoolLayout::PancakeSwapV3 => (1, 4, 1),
+        }
+    }
+}
 
 pub struct UniswapPoolStorage<'a> {
     pub storage_changes: &'a Vec<StorageChange>,
     pub contract_addr: [u8; 20],
+    pub layout: PoolLayout,
 }
 
 impl<'a> UniswapPoolStorage<'a> {
     pub fn new(storage_changes: &'a Vec<StorageChange>, contract_addr: &Vec<u8>) -> UniswapPoolStorage<'a> {
+        return Self::with_layout(storage_changes, contract_addr, PoolLayout::UniswapV3);
+    }
+
+    pub fn with_layout(
+        storage_changes: &'a Vec<StorageChange>,
+        contract_addr: &Vec<u8>,
+        layout: PoolLayout,
+    ) -> UniswapPoolStorage<'a> {
         return Self {
             storage_changes,
             contract_addr: utils::contract_pad(contract_addr),
+            layout,
         };
     }
 
     pub fn fee_growth_global0x128(&self) -> Option<(BigInt, BigInt)> {
-        let fee_growth_global0x128_slot = BigInt::from(1);
+        let fee_growth_global0x128_slot = BigInt::from(self.layout.slot(1));
         let offset = 0;
         let number_of_bytes = 32;
 
@@ -38,7 +99,7 @@ impl<'a> UniswapPoolStorage<'a> {
     }
 
     pub fn fee_growth_global1x128(&self) -> Option<(BigInt, BigInt)> {
-        let fee_growth_global1x128_slot = BigInt::from(2);
+        let fee_growth_global1x128_slot = BigInt::from(self.layout.slot(2));
         let offset = 0;
         let number_of_bytes = 32;
 
@@ -58,8 +119,52 @@ impl<'a> UniswapPoolStorage<'a> {
         }
     }
 
+    // accumulated protocol fees in token0 that can be collected by the factory owner
+    pub fn protocol_fees_token0(&self) -> Option<(BigInt, BigInt)> {
+        let protocol_fees_slot = BigInt::from(self.layout.slot(3));
+        let offset = 0;
+        let number_of_bytes = 16;
+
+        // ----
+        let slot_key = utils::left_pad_from_bigint(&protocol_fees_slot);
+        // ----
+
+        if let Some((old_data, new_data)) =
+            utils::get_storage_change(&self.filtered_changes(), slot_key, offset, number_of_bytes)
+        {
+            Some((
+                BigInt::from_signed_bytes_be(old_data),
+                BigInt::from_signed_bytes_be(new_data),
+            ))
+        } else {
+            None
+        }
+    }
+
+    // accumulated protocol fees in token1 that can be collected by the factory owner
+    pub fn protocol_fees_token1(&self) -> Option<(BigInt, BigInt)> {
+        let protocol_fees_slot = BigInt::from(self.layout.slot(3));
+        let offset = 16;
+        let number_of_bytes = 16;
+
+        // ----
+        let slot_key = utils::left_pad_from_bigint(&protocol_fees_slot);
+        // ----
+
+        if let Some((old_data, new_data)) =
+            utils::get_storage_change(&self.filtered_changes(), slot_key, offset, number_of_bytes)
+        {
+            Some((
+                BigInt::from_signed_bytes_be(old_data),
+                BigInt::from_signed_bytes_be(new_data),
+            ))
+        } else {
+            None
+        }
+    }
+
     pub fn liquidity(&self) -> Option<(BigInt, BigInt)> {
-        let liquidity_slot = BigInt::from(4);
+        let liquidity_slot = BigInt::from(self.layout.slot(4));
         let offset = 0;
         let number_of_bytes = 16;
 
@@ -81,15 +186,111 @@ impl<'a> UniswapPoolStorage<'a> {
 
     pub fn slot0(&self) -> Slot0Struct {
         let slot0_slot = utils::left_pad_from_bigint(&BigInt::from(0));
-        return Slot0Struct::new(self.filtered_changes(), slot0_slot);
+        return Slot0Struct::new(self.filtered_changes(), slot0_slot, self.layout);
     }
 
     pub fn ticks(&self, tick_idx: &BigInt) -> TickStruct {
-        let ticks_slot = utils::left_pad_from_bigint(&BigInt::from(5));
+        let ticks_slot = utils::left_pad_from_bigint(&BigInt::from(self.layout.slot(TICKS_SLOT)));
         let ticker_struct_slot = utils::calc_map_slot(&utils::left_pad_from_bigint(&tick_idx), &ticks_slot);
         return TickStruct::new(self.filtered_changes(), ticker_struct_slot);
     }
 
+    pub fn positions(&self, owner: &[u8], tick_lower: i32, tick_upper: i32) -> PositionStruct {
+        let positions_slot = utils::left_pad_from_bigint(&BigInt::from(self.layout.slot(POSITIONS_SLOT)));
+        let position_struct_slot = utils::calc_map_slot(&position_key(owner, tick_lower, tick_upper), &positions_slot);
+        return PositionStruct::new(self.filtered_changes(), position_struct_slot);
+    }
+
+    pub fn observations(&self, observation_idx: &BigInt) -> ObservationStruct {
+        let observation_slot = BigInt::from(self.layout.slot(OBSERVATIONS_SLOT)).add(observation_idx.clone());
+        let observation_struct_slot = utils::left_pad_from_bigint(&observation_slot);
+        return ObservationStruct::new(self.filtered_changes(), observation_struct_slot);
+    }
+
+    // indexes of the observations written in the oracle ring buffer, the observations
+    // are a fixed size array laid out from slot 8 onwards, one slot per observation
+    pub fn observation_indexes(&self) -> Vec<BigInt> {
+        let observations_slot = self.layout.slot(OBSERVATIONS_SLOT);
+        let first_slot = BigInt::from(observations_slot);
+        let last_slot = BigInt::from(observations_slot + OBSERVATIONS_LENGTH);
+
+        let mut indexes: Vec<BigInt> = vec![];
+        for change in self.filtered_changes() {
+            let slot = BigInt::from_signed_bytes_be(&change.key);
+            if slot < first_slot || slot >= last_slot {
+                continue;
+            }
+
+            let observation_idx = slot.sub(first_slot.clone());
+            if !indexes.contains(&observation_idx) {
+                indexes.push(observation_idx);
+            }
+        }
+        return indexes;
+    }
+
+    // ticks crossed while the current tick moved from `tick_before` to `tick_after`, in crossing order.
+    // Crossing a tick flips its outside accumulators, as the `ticks` mapping slots can't be reversed we
+    // walk the initializable ticks in between and keep the ones whose struct was written. A swap only
+    // writes mapping slots for the ticks it crosses, the walk stops once all of them are found and
+    // hashes a single slot per tick.
+    pub fn crossed_ticks(&self, tick_before: i32, tick_after: i32, tick_spacing: i32) -> Vec<i32> {
+        let mut crossed_ticks = vec![];
+        if tick_spacing <= 0 || tick_before == tick_after {
+            return crossed_ticks;
+        }
+
+        // mapping slots are keccak hashes, everything else, the observations included, is a low numbered slot
+        let written_slots: HashSet<[u8; 32]> = self
+            .filtered_changes()
+            .into_iter()
+            .filter(|change| change.key[..29].iter().any(|byte| *byte != 0))
+            .map(|change| utils::left_pad(&change.key, 0))
+            .collect();
+        if written_slots.is_empty() {
+            return crossed_ticks;
+        }
+
+        // feeGrowthOutside0X128, feeGrowthOutside1X128 and the outside cumulatives are the members 1 to 3
+        // of the tick struct, the struct slots the written slots can belong to
+        let struct_slots: HashSet<[u8; 32]> = written_slots
+            .iter()
+            .flat_map(|slot| (1..4).map(move |member| utils::calc_struct_slot(slot, BigInt::from(-member))))
+            .collect();
+        let mut remaining_slots = written_slots.len();
+
+        let ticks_slot = utils::left_pad_from_bigint(&BigInt::from(self.layout.slot(TICKS_SLOT)));
+        let going_down = tick_after < tick_before;
+        // the current tick is crossed first when going down, as the price sits on its lower boundary
+        let mut tick = if going_down {
+            tick_before.div_euclid(tick_spacing) * tick_spacing
+        } else {
+            (tick_before.div_euclid(tick_spacing) + 1) * tick_spacing
+        };
+
+        while remaining_slots > 0 && ((going_down && tick > tick_after) || (!going_down && tick <= tick_after)) {
+            let struct_slot = utils::calc_map_slot(&utils::left_pad_from_bigint(&BigInt::from(tick)), &ticks_slot);
+
+            if struct_slots.contains(&struct_slot) {
+                let written_members = (1..4)
+                    .filter(|member| {
+                        written_slots.contains(&utils::calc_struct_slot(&struct_slot, BigInt::from(*member)))
+                    })
+                    .count();
+                crossed_ticks.push(tick);
+                remaining_slots = remaining_slots.saturating_sub(written_members);
+            }
+
+            tick = if going_down {
+                tick - tick_spacing
+            } else {
+                tick + tick_spacing
+            };
+        }
+
+        return crossed_ticks;
+    }
+
     fn filtered_changes(&self) -> Vec<&StorageChange> {
         return self
             .storage_changes
@@ -102,13 +303,15 @@ impl<'a> UniswapPoolStorage<'a> {
 pub struct Slot0Struct<'a> {
     pub storage_changes: Vec<&'a StorageChange>,
     pub struct_slot: [u8; 32],
+    pub layout: PoolLayout,
 }
 
 impl<'a> Slot0Struct<'a> {
-    pub fn new(storage_changes: Vec<&'a StorageChange>, struct_slot: [u8; 32]) -> Slot0Struct<'a> {
+    pub fn new(storage_changes: Vec<&'a StorageChange>, struct_slot: [u8; 32], layout: PoolLayout) -> Slot0Struct<'a> {
         return Self {
             struct_slot: struct_slot,
             storage_changes: storage_changes,
+            layout: layout,
         };
     }
 
@@ -220,12 +423,9 @@ impl<'a> Slot0Struct<'a> {
     // the current protocol fee as a percentage of the swap fee taken on withdrawal
     // represented as an integer denominator (1/x)%
     pub fn fee_protocol(&self) -> Option<(BigInt, BigInt)> {
-        let slot = BigInt::zero();
-        let offset = 29;
-        let number_of_bytes = 1;
-        // &left_pad_from_bigint(&slot0_slot)
+        let (slot, offset, number_of_bytes) = self.layout.fee_protocol();
 
-        let slot_key = utils::calc_struct_slot(&self.struct_slot, slot);
+        let slot_key = utils::calc_struct_slot(&self.struct_slot, BigInt::from(slot));
 
         if let Some((old_data, new_data)) =
             utils::get_storage_change(&self.storage_changes, slot_key, offset, number_of_bytes)
@@ -241,12 +441,9 @@ impl<'a> Slot0Struct<'a> {
 
     // whether the pool is locked
     pub fn unlocked(&self) -> Option<(bool, bool)> {
-        let slot = BigInt::zero();
-        let offset = 30;
-        let number_of_bytes = 1;
-        // &left_pad_from_bigint(&slot0_slot)
+        let (slot, offset, number_of_bytes) = self.layout.unlocked();
 
-        let slot_key = utils::calc_struct_slot(&self.struct_slot, slot);
+        let slot_key = utils::calc_struct_slot(&self.struct_slot, BigInt::from(slot));
 
         if let Some((old_data, new_data)) =
             utils::get_storage_change(&self.storage_changes, slot_key, offset, number_of_bytes)
@@ -324,11 +521,300 @@ impl<'a> TickStruct<'a> {
             None
         }
     }
+
+    // the total position liquidity that references this tick
+    pub fn liquidity_gross(&self) -> Option<(BigInt, BigInt)> {
+        let slot = BigInt::zero();
+        let offset = 0;
+        let number_of_bytes = 16;
+
+        let slot_key = utils::calc_struct_slot(&self.struct_slot, slot);
+
+        if let Some((old_data, new_data)) =
+            utils::get_storage_change(&self.storage_changes, slot_key, offset, number_of_bytes)
+        {
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
+        } else {
+            None
+        }
+    }
+
+    // amount of net liquidity added (subtracted) when tick is crossed from left to right (right to left)
+    pub fn liquidity_net(&self) -> Option<(BigInt, BigInt)> {
+        let slot = BigInt::zero();
+        let offset = 16;
+        let number_of_bytes = 16;
+
+        let slot_key = utils::calc_struct_slot(&self.struct_slot, slot);
+
+        if let Some((old_data, new_data)) =
+            utils::get_storage_change(&self.storage_changes, slot_key, offset, number_of_bytes)
+        {
+            Some((
+                BigInt::from_signed_bytes_be(old_data),
+                BigInt::from_signed_bytes_be(new_data),
+            ))
+        } else {
+            None
+        }
+    }
+
+    // the cumulative tick value on the other side of the tick
+    pub fn tick_cumulative_outside(&self) -> Option<(BigInt, BigInt)> {
+        let slot = BigInt::from(3);
+        let offset = 0;
+        let number_of_bytes = 7;
+
+        let slot_key = utils::calc_struct_slot(&self.struct_slot, slot);
+
+        if let Some((old_data, new_data)) =
+            utils::get_storage_change(&self.storage_changes, slot_key, offset, number_of_bytes)
+        {
+            Some((
+                BigInt::from_signed_bytes_be(old_data),
+                BigInt::from_signed_bytes_be(new_data),
+            ))
+        } else {
+            None
+        }
+    }
+
+    // the seconds per unit of liquidity on the other side of the tick
+    pub fn seconds_per_liquidity_outside_x128(&self) -> Option<(BigInt, BigInt)> {
+        let slot = BigInt::from(3);
+        let offset = 7;
+        let number_of_bytes = 20;
+
+        let slot_key = utils::calc_struct_slot(&self.struct_slot, slot);
+
+        if let Some((old_data, new_data)) =
+            utils::get_storage_change(&self.storage_changes, slot_key, offset, number_of_bytes)
+        {
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
+        } else {
+            None
+        }
+    }
+
+    // the seconds spent on the other side of the tick
+    pub fn seconds_outside(&self) -> Option<(BigInt, BigInt)> {
+        let slot = BigInt::from(3);
+        let offset = 27;
+        let number_of_bytes = 4;
+
+        let slot_key = utils::calc_struct_slot(&self.struct_slot, slot);
+
+        if let Some((old_data, new_data)) =
+            utils::get_storage_change(&self.storage_changes, slot_key, offset, number_of_bytes)
+        {
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
+        } else {
+            None
+        }
+    }
+}
+
+pub struct PositionStruct<'a> {
+    pub storage_changes: Vec<&'a StorageChange>,
+    pub struct_slot: [u8; 32],
+}
+
+impl<'a> PositionStruct<'a> {
+    pub fn new(storage_changes: Vec<&'a StorageChange>, struct_slot: [u8; 32]) -> PositionStruct<'a> {
+        return Self {
+            struct_slot: struct_slot,
+            storage_changes: storage_changes,
+        };
+    }
+
+    // the amount of liquidity owned by this position
+    pub fn liquidity(&self) -> Option<(BigInt, BigInt)> {
+        let slot = BigInt::zero();
+        let offset = 0;
+        let number_of_bytes = 16;
+
+        let slot_key = utils::calc_struct_slot(&self.struct_slot, slot);
+
+        if let Some((old_data, new_data)) =
+            utils::get_storage_change(&self.storage_changes, slot_key, offset, number_of_bytes)
+        {
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
+        } else {
+            None
+        }
+    }
+
+    // fee growth of token0 inside the tick range as of the last update to liquidity or fees owed
+    pub fn fee_growth_inside_0_last_x128(&self) -> Option<(BigInt, BigInt)> {
+        let slot = BigInt::from(1);
+        let offset = 0;
+        let number_of_bytes = 32;
+
+        let slot_key = utils::calc_struct_slot(&self.struct_slot, slot);
+
+        if let Some((old_data, new_data)) =
+            utils::get_storage_change(&self.storage_changes, slot_key, offset, number_of_bytes)
+        {
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
+        } else {
+            None
+        }
+    }
+
+    // fee growth of token1 inside the tick range as of the last update to liquidity or fees owed
+    pub fn fee_growth_inside_1_last_x128(&self) -> Option<(BigInt, BigInt)> {
+        let slot = BigInt::from(2);
+        let offset = 0;
+        let number_of_bytes = 32;
+
+        let slot_key = utils::calc_struct_slot(&self.struct_slot, slot);
+
+        if let Some((old_data, new_data)) =
+            utils::get_storage_change(&self.storage_changes, slot_key, offset, number_of_bytes)
+        {
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
+        } else {
+            None
+        }
+    }
+
+    // the fees owed to the position owner in token0, collectable with `collect`
+    pub fn tokens_owed0(&self) -> Option<(BigInt, BigInt)> {
+        let slot = BigInt::from(3);
+        let offset = 0;
+        let number_of_bytes = 16;
+
+        let slot_key = utils::calc_struct_slot(&self.struct_slot, slot);
+
+        if let Some((old_data, new_data)) =
+            utils::get_storage_change(&self.storage_changes, slot_key, offset, number_of_bytes)
+        {
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
+        } else {
+            None
+        }
+    }
+
+    // the fees owed to the position owner in token1, collectable with `collect`
+    pub fn tokens_owed1(&self) -> Option<(BigInt, BigInt)> {
+        let slot = BigInt::from(3);
+        let offset = 16;
+        let number_of_bytes = 16;
+
+        let slot_key = utils::calc_struct_slot(&self.struct_slot, slot);
+
+        if let Some((old_data, new_data)) =
+            utils::get_storage_change(&self.storage_changes, slot_key, offset, number_of_bytes)
+        {
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
+        } else {
+            None
+        }
+    }
+}
+
+// key of a position in the pool's `positions` mapping, i.e. keccak256(abi.encodePacked(owner, tickLower, tickUpper))
+pub fn position_key(owner: &[u8], tick_lower: i32, tick_upper: i32) -> [u8; 32] {
+    let mut output = [0u8; 32];
+    let mut hasher = Keccak::v256();
+    hasher.update(owner);
+    // ticks are int24, keep the 3 low order bytes of their two's complement representation
+    hasher.update(&tick_lower.to_be_bytes()[1..]);
+    hasher.update(&tick_upper.to_be_bytes()[1..]);
+    hasher.finalize(&mut output);
+    return output;
+}
+
+pub struct ObservationStruct<'a> {
+    pub storage_changes: Vec<&'a StorageChange>,
+    pub struct_slot: [u8; 32],
+}
+
+// an observation is always written as a whole, so these return the old and new value of the member
+// whenever the observation slot was written, even if that specific member kept the same value
+impl<'a> ObservationStruct<'a> {
+    pub fn new(storage_changes: Vec<&'a StorageChange>, struct_slot: [u8; 32]) -> ObservationStruct<'a> {
+        return Self {
+            struct_slot: struct_slot,
+            storage_changes: storage_changes,
+        };
+    }
+
+    // the block timestamp of the observation
+    pub fn block_timestamp(&self) -> Option<(BigInt, BigInt)> {
+        let slot = BigInt::zero();
+        let offset = 0;
+        let number_of_bytes = 4;
+
+        let slot_key = utils::calc_struct_slot(&self.struct_slot, slot);
+
+        if let Some((old_data, new_data)) =
+            utils::get_storage_slot_change(&self.storage_changes, slot_key, offset, number_of_bytes)
+        {
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
+        } else {
+            None
+        }
+    }
+
+    // the tick accumulator, i.e. tick * time elapsed since the pool was first initialized
+    pub fn tick_cumulative(&self) -> Option<(BigInt, BigInt)> {
+        let slot = BigInt::zero();
+        let offset = 4;
+        let number_of_bytes = 7;
+
+        let slot_key = utils::calc_struct_slot(&self.struct_slot, slot);
+
+        if let Some((old_data, new_data)) =
+            utils::get_storage_slot_change(&self.storage_changes, slot_key, offset, number_of_bytes)
+        {
+            Some((
+                BigInt::from_signed_bytes_be(old_data),
+                BigInt::from_signed_bytes_be(new_data),
+            ))
+        } else {
+            None
+        }
+    }
+
+    // the seconds per liquidity, i.e. seconds elapsed / max(1, liquidity) since the pool was first initialized
+    pub fn seconds_per_liquidity_cumulative_x128(&self) -> Option<(BigInt, BigInt)> {
+        let slot = BigInt::zero();
+        let offset = 11;
+        let number_of_bytes = 20;
+
+        let slot_key = utils::calc_struct_slot(&self.struct_slot, slot);
+
+        if let Some((old_data, new_data)) =
+            utils::get_storage_slot_change(&self.storage_changes, slot_key, offset, number_of_bytes)
+        {
+            Some((utils::unsigned_bigint(old_data), utils::unsigned_bigint(new_data)))
+        } else {
+            None
+        }
+    }
+
+    // whether or not the observation is initialized
+    pub fn initialized(&self) -> Option<(bool, bool)> {
+        let slot = BigInt::zero();
+        let offset = 31;
+        let number_of_bytes = 1;
+
+        let slot_key = utils::calc_struct_slot(&self.struct_slot, slot);
+
+        if let Some((old_data, new_data)) =
+            utils::get_storage_slot_change(&self.storage_changes, slot_key, offset, number_of_bytes)
+        {
+            Some((old_data == [01u8], new_data == [01u8]))
+        } else {
+            None
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::storage::uniswap_v3_pool::UniswapPoolStorage;
+    use crate::storage::uniswap_v3_pool::{PoolLayout, UniswapPoolStorage};
     use crate::storage::utils;
     use std::ops::Add;
     use std::str::FromStr;
@@ -488,6 +974,54 @@ mod tests {
         assert_eq!(Some((false, true)), v_opt);
     }
 
+    #[test]
+    fn pancakeswap_layout() {
+        let storage_changes = vec![
+            StorageChange {
+                address: hex!("7858e59e0c01ea06df3af3d20ac7b0003275d4bf").to_vec(),
+                key: hex!("0000000000000000000000000000000000000000000000000000000000000001").to_vec(),
+                old_value: hex!("0000000000000000000000000000000000000000000000000000000000000000").to_vec(),
+                new_value: hex!("0000000000000000000000000000000000000000000000000000010ce40ce4").to_vec(),
+                ordinal: 0,
+            },
+            StorageChange {
+                address: hex!("7858e59e0c01ea06df3af3d20ac7b0003275d4bf").to_vec(),
+                key: hex!("0000000000000000000000000000000000000000000000000000000000000002").to_vec(),
+                old_value: hex!("0000000000000000000000000000000000000000000000000000000000000000").to_vec(),
+                new_value: hex!("00000000000000000000000000000000000000000000000000000000000003e8").to_vec(),
+                ordinal: 1,
+            },
+        ];
+
+        let storage = UniswapPoolStorage::with_layout(
+            &storage_changes,
+            &hex!("7858e59e0c01ea06df3af3d20ac7b0003275d4bf").to_vec(),
+            PoolLayout::PancakeSwapV3,
+        );
+        // feeProtocol packs the token0 and token1 protocol fees, 3300 each
+        assert_eq!(
+            Some((BigInt::zero(), BigInt::from(3300 * 65536 + 3300))),
+            storage.slot0().fee_protocol()
+        );
+        assert_eq!(Some((false, true)), storage.slot0().unlocked());
+        // feeGrowthGlobal0X128 comes after the two slots of slot0
+        assert_eq!(
+            Some((BigInt::zero(), BigInt::from(1000))),
+            storage.fee_growth_global0x128()
+        );
+        assert_eq!(None, storage.fee_growth_global1x128());
+
+        let uniswap_storage = UniswapPoolStorage::new(
+            &storage_changes,
+            &hex!("7858e59e0c01ea06df3af3d20ac7b0003275d4bf").to_vec(),
+        );
+        assert_eq!(None, uniswap_storage.slot0().fee_protocol());
+        assert_eq!(
+            Some((BigInt::zero(), BigInt::from(1000))),
+            uniswap_storage.fee_growth_global1x128()
+        );
+    }
+
     #[test]
     fn tick_initialized() {
         let storage_changes = vec![
@@ -531,6 +1065,148 @@ mod tests {
         assert_eq!(Some((false, true)), v_opt);
     }
 
+    #[test]
+    fn tick_info() {
+        let storage_changes = vec![
+            StorageChange {
+                address: hex!("7858e59e0c01ea06df3af3d20ac7b0003275d4bf").to_vec(),
+                key: hex!("59d3454e6bb14d1f2ae9ab5d64a71e9d2d3eec41710c33f701d47eb206f29613").to_vec(),
+                old_value: hex!("0000000000000000000000000000000000000000000000000000000000000000").to_vec(),
+                new_value: hex!("ffffffffffffffffffff749ebcd26169000000000000000000008b61432d9e96").to_vec(),
+                ordinal: 0,
+            },
+            StorageChange {
+                address: hex!("7858e59e0c01ea06df3af3d20ac7b0003275d4bf").to_vec(),
+                key: hex!("59d3454e6bb14d1f2ae9ab5d64a71e9d2d3eec41710c33f701d47eb206f29616").to_vec(),
+                old_value: hex!("0000000000000000000000000000000000000000000000000000000000000000").to_vec(),
+                new_value: hex!("016091bfa60000000000000000314c3c8ef0a2c4b9b2ce9d09ffffffffffff9c").to_vec(),
+                ordinal: 0,
+            },
+        ];
+
+        let storage = UniswapPoolStorage::new(
+            &storage_changes,
+            &hex!("7858e59e0c01ea06df3af3d20ac7b0003275d4bf").to_vec(),
+        );
+
+        let tick = storage.ticks(&BigInt::from(193200));
+        assert_eq!(
+            Some((BigInt::zero(), BigInt::from_str("153249855151766").unwrap())),
+            tick.liquidity_gross()
+        );
+        assert_eq!(
+            Some((BigInt::zero(), BigInt::from_str("-153249855151767").unwrap())),
+            tick.liquidity_net()
+        );
+        assert_eq!(
+            Some((BigInt::zero(), BigInt::from(-100))),
+            tick.tick_cumulative_outside()
+        );
+        assert_eq!(
+            Some((
+                BigInt::zero(),
+                BigInt::from_str("15256929822302888647438802185").unwrap()
+            )),
+            tick.seconds_per_liquidity_outside_x128()
+        );
+        assert_eq!(Some((BigInt::zero(), BigInt::from(1620164518))), tick.seconds_outside());
+    }
+
+    #[test]
+    fn crossed_ticks() {
+        let storage_changes = vec![
+            StorageChange {
+                address: hex!("7858e59e0c01ea06df3af3d20ac7b0003275d4bf").to_vec(),
+                key: hex!("0000000000000000000000000000000000000000000000000000000000000004").to_vec(),
+                old_value: hex!("00000000000000000000000000000000000000000000000000008b61432d9e96").to_vec(),
+                new_value: hex!("0000000000000000000000000000000000000000000000000000000000000000").to_vec(),
+                ordinal: 0,
+            },
+            StorageChange {
+                address: hex!("7858e59e0c01ea06df3af3d20ac7b0003275d4bf").to_vec(),
+                key: hex!("59d3454e6bb14d1f2ae9ab5d64a71e9d2d3eec41710c33f701d47eb206f29616").to_vec(),
+                old_value: hex!("016091bfa60000000000000000314c3c8ef0a2c4b9b2ce9d0900000041d2241f").to_vec(),
+                new_value: hex!("01000000010000000000000000000000000000000000000000ffffffbe2ddbe1").to_vec(),
+                ordinal: 1,
+            },
+        ];
+
+        let storage = UniswapPoolStorage::new(
+            &storage_changes,
+            &hex!("7858e59e0c01ea06df3af3d20ac7b0003275d4bf").to_vec(),
+        );
+
+        assert_eq!(vec![193200], storage.crossed_ticks(193250, 193100, 10));
+        assert_eq!(vec![193200], storage.crossed_ticks(193200, 193199, 10));
+        assert_eq!(vec![193200], storage.crossed_ticks(193150, 193200, 10));
+        assert_eq!(Vec::<i32>::new(), storage.crossed_ticks(193210, 193250, 10));
+        // the walk stops at the last written tick struct instead of going down to the new tick
+        assert_eq!(vec![193200], storage.crossed_ticks(193250, -887270, 1));
+    }
+
+    #[test]
+    fn position_fee_growth_inside() {
+        let storage_changes = vec![
+            StorageChange {
+                address: hex!("7858e59e0c01ea06df3af3d20ac7b0003275d4bf").to_vec(),
+                key: hex!("1e6af8071088a01456f67390dba7029f946afa9f048993785717e0f8f1055970").to_vec(),
+                old_value: hex!("0000000000000000000000000000000000000000000000000000000000000000").to_vec(),
+                new_value: hex!("00000000000000000000000000000004d89db07e848644d71c4496a64b7ac568").to_vec(),
+                ordinal: 0,
+            },
+            StorageChange {
+                address: hex!("7858e59e0c01ea06df3af3d20ac7b0003275d4bf").to_vec(),
+                key: hex!("1e6af8071088a01456f67390dba7029f946afa9f048993785717e0f8f1055971").to_vec(),
+                old_value: hex!("0000000000000000000000000000000000000000000000000000000000000000").to_vec(),
+                new_value: hex!("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").to_vec(),
+                ordinal: 0,
+            },
+        ];
+
+        let storage = UniswapPoolStorage::new(
+            &storage_changes,
+            &hex!("7858e59e0c01ea06df3af3d20ac7b0003275d4bf").to_vec(),
+        );
+
+        let position = storage.positions(&hex!("c36442b4a4522e871399cd717abdd847ab11fe88"), -887220, 887220);
+        assert_eq!(
+            Some((
+                BigInt::zero(),
+                BigInt::from_str("1649061485107938712274954833111042147688").unwrap()
+            )),
+            position.fee_growth_inside_0_last_x128()
+        );
+        assert_eq!(
+            Some((
+                BigInt::zero(),
+                BigInt::from_str("115792089237316195423570985008687907853269984665640564039457584007913129639935")
+                    .unwrap()
+            )),
+            position.fee_growth_inside_1_last_x128()
+        );
+        assert_eq!(None, position.liquidity());
+    }
+
+    #[test]
+    fn position_tokens_owed() {
+        let storage_changes = vec![StorageChange {
+            address: hex!("7858e59e0c01ea06df3af3d20ac7b0003275d4bf").to_vec(),
+            key: hex!("1e6af8071088a01456f67390dba7029f946afa9f048993785717e0f8f1055972").to_vec(),
+            old_value: hex!("0000000000000000000000000000000500000000000000000000000000000000").to_vec(),
+            new_value: hex!("000000000000000000000000000000050000000000000000000000000000000a").to_vec(),
+            ordinal: 0,
+        }];
+
+        let storage = UniswapPoolStorage::new(
+            &storage_changes,
+            &hex!("7858e59e0c01ea06df3af3d20ac7b0003275d4bf").to_vec(),
+        );
+
+        let position = storage.positions(&hex!("c36442b4a4522e871399cd717abdd847ab11fe88"), -887220, 887220);
+        assert_eq!(Some((BigInt::zero(), BigInt::from(10))), position.tokens_owed0());
+        assert_eq!(None, position.tokens_owed1());
+    }
+
     #[test]
     fn liquidity() {
         let storage_changes = vec![
@@ -569,6 +1245,62 @@ mod tests {
         );
     }
 
+    #[test]
+    fn protocol_fees() {
+        let storage_changes = vec![StorageChange {
+            address: hex!("779dfffb81550bf503c19d52b1e91e9251234faa").to_vec(),
+            key: hex!("0000000000000000000000000000000000000000000000000000000000000003").to_vec(),
+            old_value: hex!("00000000000000000000000000000001000000000000000000000000000003e8").to_vec(),
+            new_value: hex!("00000000000000000000000000000001000000000000000000000000000007d0").to_vec(),
+            ordinal: 42,
+        }];
+
+        let storage = UniswapPoolStorage::new(
+            &storage_changes,
+            &hex!("779dfffb81550bf503c19d52b1e91e9251234faa").to_vec(),
+        );
+
+        assert_eq!(
+            Some((BigInt::from(1000), BigInt::from(2000))),
+            storage.protocol_fees_token0()
+        );
+        // token1 did not move, so no change is reported
+        assert_eq!(None, storage.protocol_fees_token1());
+    }
+
+    #[test]
+    fn observation() {
+        let storage_changes = vec![StorageChange {
+            address: hex!("7858e59e0c01ea06df3af3d20ac7b0003275d4bf").to_vec(),
+            key: hex!("0000000000000000000000000000000000000000000000000000000000000009").to_vec(),
+            old_value: hex!("0000000000000000000000000000000000000000000000000000000000000000").to_vec(),
+            new_value: hex!("0100000000000000000000000000000000000000fffffffffffffc186091bfa6").to_vec(),
+            ordinal: 0,
+        }];
+
+        let storage = UniswapPoolStorage::new(
+            &storage_changes,
+            &hex!("7858e59e0c01ea06df3af3d20ac7b0003275d4bf").to_vec(),
+        );
+
+        assert_eq!(vec![BigInt::from(1)], storage.observation_indexes());
+
+        let observation = storage.observations(&BigInt::from(1));
+        assert_eq!(
+            Some((BigInt::from(0), BigInt::from(1620164518))),
+            observation.block_timestamp()
+        );
+        assert_eq!(
+            Some((BigInt::from(0), BigInt::from(-1000))),
+            observation.tick_cumulative()
+        );
+        assert_eq!(
+            Some((BigInt::from(0), BigInt::from(255))),
+            observation.seconds_per_liquidity_cumulative_x128()
+        );
+        assert_eq!(Some((false, true)), observation.initialized());
+    }
+
     #[test]
     fn slot_calc() {
         // slot of ticks map
@@ -32,6 +32,25 @@ pub fn get_storage_change<'a>(
     Some((old_data, new_data))
 }
 
+// Like `get_storage_change`, but returns the last write to the slot even when the bytes we are
+// looking at did not move. Useful for structs that are always written as a whole, where we need
+// the value of every member after the write and not only of the ones that changed.
+pub fn get_storage_slot_change<'a>(
+    storage_changes: &'a Vec<&StorageChange>,
+    slot_key: [u8; 32],
+    offset: usize,
+    number_of_bytes: usize,
+) -> Option<(&'a [u8], &'a [u8])> {
+    let storage = storage_changes
+        .iter()
+        .filter(|&&storage_change| storage_change.key.eq(slot_key.as_slice()))
+        .max_by(|x, y| x.ordinal.cmp(&y.ordinal))?;
+
+    let old_data = read_bytes(&storage.old_value, offset, number_of_bytes);
+    let new_data = read_bytes(&storage.new_value, offset, number_of_bytes);
+    Some((old_data, new_data))
+}
+
 pub fn calc_map_slot(map_index: &[u8; 32], base_slot: &[u8; 32]) -> [u8; 32] {
     let mut output = [0u8; 32];
     let mut hasher = Keccak::v256();
@@ -114,6 +133,14 @@ pub fn read_bytes(buf: &Vec<u8>, offset: usize, number_of_bytes: usize) -> &[u8]
     &buf[start..=end]
 }
 
+// uint members can have their most significant bit set, prepend a zero byte so they don't get
+// decoded as negative numbers
+pub fn unsigned_bigint(data: &[u8]) -> BigInt {
+    let mut bytes = vec![0u8];
+    bytes.extend_from_slice(data);
+    BigInt::from_signed_bytes_be(bytes.as_slice())
+}
+
 #[cfg(test)]
 mod tests {
     use crate::storage::utils::{get_storage_change, left_pad, read_bytes};
@@ -1,20 +1,20 @@
+use crate::config::Config;
 use crate::ethpb::v2::TransactionTrace;
+use crate::math;
 use crate::pb::uniswap::events;
 use crate::pb::AdjustedAmounts;
+use crate::storage::uniswap_v3_pool::PoolLayout;
 use crate::uniswap::events::Transaction;
-use crate::{storage, Erc20Token, StorageChange, WHITELIST_TOKENS};
+use crate::{storage, Erc20Token, StorageChange};
 use std::ops::{Add, Mul};
 use std::string::ToString;
-use substreams::prelude::StoreGetBigDecimal;
+use substreams::prelude::{StoreGetBigDecimal, StoreGetBigInt};
 use substreams::scalar::{BigDecimal, BigInt};
 use substreams::store::StoreGet;
 use substreams::{hex, log, Hex};
-
-pub const UNISWAP_V3_FACTORY: [u8; 20] = hex!("1f98431c8ad98523631ae4a59f267346ea31f984");
+use tiny_keccak::{Hasher, Keccak};
 
 pub const ZERO_ADDRESS: [u8; 20] = hex!("0000000000000000000000000000000000000000");
-pub const NON_FUNGIBLE_POSITION_MANAGER: [u8; 20] = hex!("c36442b4a4522e871399cd717abdd847ab11fe88");
-pub const ERROR_POOL: [u8; 20] = hex!("8fe8d9bb8eeba3ed688069c3d6b556c9ca258248");
 
 const DGD_TOKEN_ADDRESS: [u8; 20] = hex!("e0b7927c4af23765cb51314a0e0521a9645f0e2a");
 const AAVE_TOKEN_ADDRESS: [u8; 20] = hex!("7fc66500c84a76ad7e9c93437bfc5ac33e2ddae9");
@@ -83,10 +83,11 @@ pub fn extract_pool_fee_growth_global_updates(
     log_ordinal: u64,
     pool_address: &Vec<u8>,
     storage_changes: &Vec<StorageChange>,
+    layout: PoolLayout,
 ) -> Vec<events::FeeGrowthGlobal> {
     let mut fee_growth_global = vec![];
 
-    let storage = storage::uniswap_v3_pool::UniswapPoolStorage::new(storage_changes, pool_address);
+    let storage = storage::uniswap_v3_pool::UniswapPoolStorage::with_layout(storage_changes, pool_address, layout);
 
     if let Some((_, new_value)) = storage.fee_growth_global0x128() {
         fee_growth_global.push(events::FeeGrowthGlobal {
@@ -126,6 +127,32 @@ pub fn calculate_amount_usd(
         );
 }
 
+// derived USD value of the amounts at the given ordinal, zero when a price is not known yet
+pub fn get_amount_usd(
+    ordinal: u64,
+    token0_addr: &String,
+    token1_addr: &String,
+    amount0: &BigDecimal,
+    amount1: &BigDecimal,
+    eth_prices_store: &StoreGetBigDecimal,
+) -> BigDecimal {
+    let token0_derived_eth_price = eth_prices_store
+        .get_at(ordinal, format!("token:{token0_addr}:dprice:eth"))
+        .unwrap_or_default();
+    let token1_derived_eth_price = eth_prices_store
+        .get_at(ordinal, format!("token:{token1_addr}:dprice:eth"))
+        .unwrap_or_default();
+    let bundle_eth_price = eth_prices_store.get_at(ordinal, "bundle").unwrap_or_default();
+
+    calculate_amount_usd(
+        amount0,
+        amount1,
+        &token0_derived_eth_price,
+        &token1_derived_eth_price,
+        &bundle_eth_price,
+    )
+}
+
 pub fn get_tracked_amount_usd(
     token0_id: &String,
     token1_id: &String,
@@ -134,6 +161,7 @@ pub fn get_tracked_amount_usd(
     amount0_abs: &BigDecimal,
     amount1_abs: &BigDecimal,
     eth_price_in_usd: &BigDecimal,
+    config: &Config,
 ) -> BigDecimal {
     let price0_usd = token0_derived_eth_price.clone().mul(eth_price_in_usd.clone());
     let price1_usd = token1_derived_eth_price.clone().mul(eth_price_in_usd.clone());
@@ -142,7 +170,7 @@ pub fn get_tracked_amount_usd(
     log::info!("price1_usd: {}", price1_usd);
 
     // both are whitelist tokens, return sum of both amounts
-    if WHITELIST_TOKENS.contains(&token0_id.as_str()) && WHITELIST_TOKENS.contains(&token1_id.as_str()) {
+    if config.is_whitelisted(token0_id) && config.is_whitelisted(token1_id) {
         return amount0_abs
             .clone()
             .mul(price0_usd)
@@ -150,12 +178,12 @@ pub fn get_tracked_amount_usd(
     }
 
     // take double value of the whitelisted token amount
-    if WHITELIST_TOKENS.contains(&token0_id.as_str()) && !WHITELIST_TOKENS.contains(&token1_id.as_str()) {
+    if config.is_whitelisted(token0_id) && !config.is_whitelisted(token1_id) {
         return amount0_abs.clone().mul(price0_usd).mul(BigDecimal::from(2 as i32));
     }
 
     // take double value of the whitelisted token amount
-    if !WHITELIST_TOKENS.contains(&token0_id.as_str()) && WHITELIST_TOKENS.contains(&token1_id.as_str()) {
+    if !config.is_whitelisted(token0_id) && config.is_whitelisted(token1_id) {
         return amount1_abs.clone().mul(price1_usd).mul(BigDecimal::from(2 as i32));
     }
 
@@ -171,6 +199,7 @@ pub fn get_adjusted_amounts(
     token0_derived_eth_price: &BigDecimal,
     token1_derived_eth_price: &BigDecimal,
     bundle_eth_price_usd: &BigDecimal,
+    config: &Config,
 ) -> AdjustedAmounts {
     log::info!("token0_addr {:}", token0_addr);
     log::info!("token1_addr {:}", token1_addr);
@@ -193,18 +222,18 @@ pub fn get_adjusted_amounts(
         .mul(token0_derived_eth_price.clone())
         .add(token1_amount.clone().mul(token1_derived_eth_price.clone()));
 
-    if WHITELIST_TOKENS.contains(&token0_addr.as_str()) && WHITELIST_TOKENS.contains(&token1_addr.as_str()) {
+    if config.is_whitelisted(token0_addr) && config.is_whitelisted(token1_addr) {
         eth = eth_untracked.clone()
     }
 
-    if WHITELIST_TOKENS.contains(&token0_addr.as_str()) && !WHITELIST_TOKENS.contains(&token1_addr.as_str()) {
+    if config.is_whitelisted(token0_addr) && !config.is_whitelisted(token1_addr) {
         eth = token0_amount
             .clone()
             .mul(token0_derived_eth_price.clone())
             .mul(BigDecimal::from(2 as i32));
     }
 
-    if !WHITELIST_TOKENS.contains(&token0_addr.as_str()) && WHITELIST_TOKENS.contains(&token1_addr.as_str()) {
+    if !config.is_whitelisted(token0_addr) && config.is_whitelisted(token1_addr) {
         eth = token1_amount
             .clone()
             .mul(token1_derived_eth_price.clone())
@@ -246,6 +275,41 @@ pub fn load_transaction(
     transaction
 }
 
+// EIP-55 mixed-case checksum encoding of an address, with its `0x` prefix
+pub fn checksum_address(address: &[u8]) -> String {
+    let address = Hex(address).to_string();
+
+    let mut hash = [0u8; 32];
+    let mut hasher = Keccak::v256();
+    hasher.update(address.as_bytes());
+    hasher.finalize(&mut hash);
+
+    // a letter is uppercased when the matching nibble of the hash is 8 or more
+    let checksummed: String = address
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = if i % 2 == 0 {
+                hash[i / 2] >> 4
+            } else {
+                hash[i / 2] & 0x0f
+            };
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect();
+
+    format!("0x{checksummed}")
+}
+
+// id of the `Factory` entity of a factory, the checksummed address like in the subgraph
+pub fn factory_id(factory_address: &str) -> String {
+    checksum_address(&::hex::decode(factory_address).unwrap())
+}
+
 pub fn get_derived_eth_price(ordinal: u64, token_addr: &String, eth_prices_store: &StoreGetBigDecimal) -> BigDecimal {
     return match eth_prices_store.get_at(ordinal, format!("token:{token_addr}:dprice:eth")) {
         None => panic!("token eth price not found for token {}", token_addr),
@@ -270,3 +334,34 @@ pub fn get_token_tvl(ordinal: u64, token_addr: &String, total_value_locked_store
         .get_at(ordinal, format!("token:{token_addr}"))
         .unwrap() // impossible
 }
+
+// fee growth inside of a range of the pool from the latest fee growth global and feeGrowthOutside of its
+// ticks, an uninitialized tick has no feeGrowthOutside
+pub fn get_fee_growth_inside(
+    pool_addr: &String,
+    tick_current: i32,
+    tick_lower: i32,
+    tick_upper: i32,
+    token_idx: i32,
+    fee_growth_globals_store: &StoreGetBigInt,
+    ticks_store: &StoreGetBigInt,
+) -> BigInt {
+    let fee_growth_global = fee_growth_globals_store
+        .get_last(format!("pool:{pool_addr}:feeGrowthGlobal{token_idx}X128"))
+        .unwrap_or(BigInt::zero());
+    let fee_growth_outside_lower = ticks_store
+        .get_last(format!("tick:{pool_addr}:{tick_lower}:feeGrowthOutside{token_idx}X128"))
+        .unwrap_or(BigInt::zero());
+    let fee_growth_outside_upper = ticks_store
+        .get_last(format!("tick:{pool_addr}:{tick_upper}:feeGrowthOutside{token_idx}X128"))
+        .unwrap_or(BigInt::zero());
+
+    math::fee_growth_inside(
+        tick_current,
+        tick_lower,
+        tick_upper,
+        &fee_growth_global,
+        &fee_growth_outside_lower,
+        &fee_growth_outside_upper,
+    )
+}